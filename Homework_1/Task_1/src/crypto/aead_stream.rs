@@ -0,0 +1,264 @@
+//! Chunked (online) AEAD encryption for inputs that do not fit in memory.
+//! - Follows the STREAM construction (Hoang, Reyhanitabar, Rogaway, Vizár 2015) on AES-256-GCM.
+//! - Wire layout: nonce_prefix (7 bytes) || segment_0 || segment_1 || ... || segment_last
+//! - Each segment is `ciphertext || tag` of at most CHUNK_SIZE plaintext bytes.
+//! - Nonce of segment i: nonce_prefix (7) || i as u32 big-endian (4) || last flag (1).
+//!   Reordered or dropped segments fail because the counter no longer matches, and a
+//!   stream cut at a segment boundary fails because its final segment lacks the last flag.
+
+use std::io::{self, Read, Write};
+
+use aes_gcm::{Aes256Gcm, KeyInit, aead::{Aead, Payload}};
+use rand::{RngCore, rngs::OsRng};
+
+use super::aead::{Key, Nonce};
+
+/// Plaintext bytes per segment (64 KiB).
+pub const CHUNK_SIZE: usize = 64 * 1024;
+/// Length of the random nonce prefix written at the start of the stream.
+pub const NONCE_PREFIX_LEN: usize = 7;
+/// AES-GCM tag length appended to every segment.
+pub const TAG_LEN: usize = 16;
+
+const SEGMENT_LEN: usize = CHUNK_SIZE + TAG_LEN;
+
+/// Build the per-segment nonce: prefix || counter || last flag.
+fn segment_nonce(prefix: &[u8; NONCE_PREFIX_LEN], counter: u32, last: bool) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+fn auth_failed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "stream segment failed authentication")
+}
+
+/// `Write` adapter that encrypts everything written to it into `inner`.
+/// Call `finish` when done; dropping the writer without it leaves a stream the reader rejects as truncated.
+pub struct EncryptWriter<W: Write> {
+    inner: W,
+    cipher: Aes256Gcm,
+    prefix: [u8; NONCE_PREFIX_LEN],
+    counter: u32,
+    ad: Vec<u8>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    /// Start a new stream under `key`. A fresh random nonce prefix is written to `inner` immediately.
+    /// `ad` is authenticated with every segment.
+    pub fn new(key: &Key, ad: &[u8], mut inner: W) -> io::Result<Self> {
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut prefix);
+        inner.write_all(&prefix)?;
+        Ok(Self {
+            inner,
+            cipher: Aes256Gcm::new(key.into()),
+            prefix,
+            counter: 0,
+            ad: ad.to_vec(),
+            buf: Vec::with_capacity(CHUNK_SIZE + 1),
+        })
+    }
+
+    fn seal_segment(&mut self, len: usize, last: bool) -> io::Result<()> {
+        let nonce = segment_nonce(&self.prefix, self.counter, last);
+        let ct = self.cipher
+            .encrypt((&nonce).into(), Payload { msg: &self.buf[..len], aad: &self.ad })
+            .map_err(|_| io::Error::other("segment encryption failed"))?;
+        self.inner.write_all(&ct)?;
+        self.buf.drain(..len);
+        if !last {
+            self.counter = self.counter.checked_add(1)
+                .ok_or_else(|| io::Error::other("stream segment counter exhausted"))?;
+        }
+        Ok(())
+    }
+
+    /// Seal the buffered tail as the last segment and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let len = self.buf.len();
+        self.seal_segment(len, true)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        // Keep at least one byte back: a full chunk may turn out to be the last one.
+        while self.buf.len() > CHUNK_SIZE {
+            self.seal_segment(CHUNK_SIZE, false)?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// `Read` adapter that decrypts a stream produced by `EncryptWriter`.
+/// Only authenticated plaintext is ever returned; any tampering surfaces as `ErrorKind::InvalidData`.
+pub struct DecryptReader<R: Read> {
+    inner: R,
+    cipher: Aes256Gcm,
+    prefix: [u8; NONCE_PREFIX_LEN],
+    counter: u32,
+    ad: Vec<u8>,
+    segment: Vec<u8>,
+    plaintext: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read> DecryptReader<R> {
+    /// Read the nonce prefix from `inner` and prepare to decrypt under `key` and `ad`.
+    pub fn new(key: &Key, ad: &[u8], mut inner: R) -> io::Result<Self> {
+        let mut prefix = [0u8; NONCE_PREFIX_LEN];
+        inner.read_exact(&mut prefix)?;
+        Ok(Self {
+            inner,
+            cipher: Aes256Gcm::new(key.into()),
+            prefix,
+            counter: 0,
+            ad: ad.to_vec(),
+            segment: Vec::with_capacity(SEGMENT_LEN + 1),
+            plaintext: Vec::new(),
+            pos: 0,
+            done: false,
+        })
+    }
+
+    /// Pull the next segment from `inner` and decrypt it into `self.plaintext`.
+    fn open_segment(&mut self) -> io::Result<()> {
+        // Read one byte past a full segment to learn whether this is the last one.
+        fill(&mut self.inner, &mut self.segment, SEGMENT_LEN + 1)?;
+        let last = self.segment.len() <= SEGMENT_LEN;
+        let len = if last { self.segment.len() } else { SEGMENT_LEN };
+        if len < TAG_LEN {
+            return Err(auth_failed());
+        }
+
+        let nonce = segment_nonce(&self.prefix, self.counter, last);
+        self.plaintext = self.cipher
+            .decrypt((&nonce).into(), Payload { msg: &self.segment[..len], aad: &self.ad })
+            .map_err(|_| auth_failed())?;
+        self.pos = 0;
+        self.segment.drain(..len);
+
+        if last {
+            self.done = true;
+        } else {
+            self.counter = self.counter.checked_add(1).ok_or_else(auth_failed)?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plaintext.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.open_segment()?;
+        }
+        let n = out.len().min(self.plaintext.len() - self.pos);
+        out[..n].copy_from_slice(&self.plaintext[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Read from `r` until `buf` holds `want` bytes or EOF is reached.
+fn fill<R: Read>(r: &mut R, buf: &mut Vec<u8>, want: usize) -> io::Result<()> {
+    let mut tmp = [0u8; 8192];
+    while buf.len() < want {
+        let n = match r.read(&mut tmp[..(want - buf.len()).min(8192)]) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        buf.extend_from_slice(&tmp[..n]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypt_all(key: &Key, ad: &[u8], pt: &[u8]) -> Vec<u8> {
+        let mut w = EncryptWriter::new(key, ad, Vec::new()).unwrap();
+        // write in odd-sized pieces to exercise the buffering
+        for piece in pt.chunks(10_007) {
+            w.write_all(piece).unwrap();
+        }
+        w.finish().unwrap()
+    }
+
+    fn decrypt_all(key: &Key, ad: &[u8], ct: &[u8]) -> io::Result<Vec<u8>> {
+        let mut r = DecryptReader::new(key, ad, ct)?;
+        let mut out = Vec::new();
+        r.read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    fn random_key() -> Key {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        key
+    }
+
+    #[test]
+    fn roundtrip_various_lengths() {
+        let key = random_key();
+        for len in [0, 1, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE + 1, 3 * CHUNK_SIZE + 17] {
+            let pt: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let ct = encrypt_all(&key, b"file.bin", &pt);
+            let segments = len / CHUNK_SIZE + usize::from(len % CHUNK_SIZE != 0 || len == 0);
+            assert_eq!(ct.len(), NONCE_PREFIX_LEN + len + segments * TAG_LEN);
+            assert_eq!(decrypt_all(&key, b"file.bin", &ct).unwrap(), pt);
+        }
+    }
+
+    #[test]
+    fn truncation_at_segment_boundary_fails() {
+        let key = random_key();
+        let pt = vec![7u8; 2 * CHUNK_SIZE + 5];
+        let ct = encrypt_all(&key, b"", &pt);
+        let cut = NONCE_PREFIX_LEN + 2 * SEGMENT_LEN; // drop the final segment only
+        assert!(decrypt_all(&key, b"", &ct[..cut]).is_err());
+        assert!(decrypt_all(&key, b"", &ct[..ct.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn dropped_and_reordered_segments_fail() {
+        let key = random_key();
+        let pt = vec![1u8; 3 * CHUNK_SIZE + 1];
+        let ct = encrypt_all(&key, b"", &pt);
+        let seg = |i: usize| &ct[NONCE_PREFIX_LEN + i * SEGMENT_LEN..NONCE_PREFIX_LEN + (i + 1) * SEGMENT_LEN];
+        let tail = &ct[NONCE_PREFIX_LEN + 3 * SEGMENT_LEN..];
+
+        let dropped = [&ct[..NONCE_PREFIX_LEN], seg(0), seg(2), tail].concat();
+        assert!(decrypt_all(&key, b"", &dropped).is_err());
+
+        let reordered = [&ct[..NONCE_PREFIX_LEN], seg(1), seg(0), seg(2), tail].concat();
+        assert!(decrypt_all(&key, b"", &reordered).is_err());
+    }
+
+    #[test]
+    fn tamper_or_wrong_ad_fails() {
+        let key = random_key();
+        let mut ct = encrypt_all(&key, b"AD1", b"attack at dawn");
+        assert!(decrypt_all(&key, b"AD2", &ct).is_err());
+        ct[NONCE_PREFIX_LEN] ^= 0x01;
+        let err = decrypt_all(&key, b"AD1", &ct).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,14 +1,250 @@
+//! HKDF (RFC 5869) with a selectable hash, plus the TLS 1.3 HKDF-Expand-Label / Derive-Secret
+//! helpers (RFC 8446 §7.1).
+//! - `extract` -> `Prk`, then `expand` any number of outputs of up to 255 * HashLen bytes.
+//! - `derive_aes256gcm_key` keeps its HKDF-SHA3-256 behaviour for existing callers.
+
+use std::fmt;
+
 use hkdf::Hkdf;
-use sha3::Sha3_256;
+use sha2::{Digest, Sha256, Sha384};
+use sha3::{Sha3_256, Sha3_512};
+use zeroize::Zeroizing;
+
+use super::secret::SecretKey;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlg {
+    Sha256,
+    Sha384,
+    Sha3_256,
+    Sha3_512,
+}
+
+/// Run `$body` with `$h` bound to the hash type of `$alg`.
+macro_rules! with_hash {
+    ($alg:expr, $h:ident => $body:expr) => {
+        match $alg {
+            HashAlg::Sha256 => { type $h = Sha256; $body }
+            HashAlg::Sha384 => { type $h = Sha384; $body }
+            HashAlg::Sha3_256 => { type $h = Sha3_256; $body }
+            HashAlg::Sha3_512 => { type $h = Sha3_512; $body }
+        }
+    };
+}
+
+impl HashAlg {
+    /// HashLen in bytes.
+    pub fn output_len(self) -> usize {
+        match self {
+            HashAlg::Sha256 | HashAlg::Sha3_256 => 32,
+            HashAlg::Sha384 => 48,
+            HashAlg::Sha3_512 => 64,
+        }
+    }
+
+    /// Largest output `expand` can produce: 255 * HashLen.
+    pub fn max_output_len(self) -> usize {
+        255 * self.output_len()
+    }
+
+    pub fn hash(self, data: &[u8]) -> Vec<u8> {
+        with_hash!(self, H => H::digest(data).to_vec())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HkdfError {
+    /// Requested more than 255 * HashLen bytes.
+    OutputTooLong { requested: usize, max: usize },
+    /// A PRK must be at least HashLen bytes.
+    PrkTooShort,
+    /// HKDF-Expand-Label: "tls13 " + label must fit in 255 bytes, context in 255 bytes, length in 16 bits.
+    LabelTooLong,
+}
+
+impl fmt::Display for HkdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HkdfError::OutputTooLong { requested, max } => write!(f, "HKDF output of {requested} bytes exceeds {max}"),
+            HkdfError::PrkTooShort => write!(f, "PRK shorter than the hash output"),
+            HkdfError::LabelTooLong => write!(f, "HKDF label, context or length too long"),
+        }
+    }
+}
+
+impl std::error::Error for HkdfError {}
+
+/// Pseudorandom key produced by `extract`, tagged with its hash.
+#[derive(Clone)]
+pub struct Prk {
+    alg: HashAlg,
+    bytes: Zeroizing<Vec<u8>>,
+}
+
+impl Prk {
+    /// Use an existing secret (e.g. a TLS 1.3 traffic secret) as PRK.
+    pub fn from_bytes(alg: HashAlg, bytes: &[u8]) -> Result<Self, HkdfError> {
+        if bytes.len() < alg.output_len() {
+            return Err(HkdfError::PrkTooShort);
+        }
+        Ok(Self { alg, bytes: Zeroizing::new(bytes.to_vec()) })
+    }
+
+    pub fn alg(&self) -> HashAlg {
+        self.alg
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// HKDF-Extract(salt, IKM). `salt`: None uses HashLen zero bytes per RFC 5869.
+pub fn extract(alg: HashAlg, salt: Option<&[u8]>, ikm: &[u8]) -> Prk {
+    let bytes = Zeroizing::new(with_hash!(alg, H => Hkdf::<H>::extract(salt, ikm).0.to_vec()));
+    Prk { alg, bytes }
+}
+
+/// HKDF-Expand(PRK, info, L) into `out`; L = `out.len()`.
+pub fn expand_into(prk: &Prk, info: &[u8], out: &mut [u8]) -> Result<(), HkdfError> {
+    let max = prk.alg.max_output_len();
+    if out.len() > max {
+        return Err(HkdfError::OutputTooLong { requested: out.len(), max });
+    }
+    with_hash!(prk.alg, H => {
+        let hk = Hkdf::<H>::from_prk(&prk.bytes).map_err(|_| HkdfError::PrkTooShort)?;
+        hk.expand(info, out).map_err(|_| HkdfError::OutputTooLong { requested: out.len(), max })
+    })
+}
+
+/// HKDF-Expand(PRK, info, L).
+pub fn expand(prk: &Prk, info: &[u8], len: usize) -> Result<Vec<u8>, HkdfError> {
+    let mut out = vec![0u8; len];
+    expand_into(prk, info, &mut out)?;
+    Ok(out)
+}
+
+/// Extract-then-expand into an N-byte key.
+pub fn derive_key<const N: usize>(alg: HashAlg, ikm: &[u8], salt: Option<&[u8]>, info: &[u8]) -> Result<SecretKey<N>, HkdfError> {
+    let mut out = SecretKey::new([0u8; N]);
+    expand_into(&extract(alg, salt, ikm), info, out.expose_secret_mut())?;
+    Ok(out)
+}
+
+/// HKDF-Expand-Label(Secret, Label, Context, Length) from TLS 1.3:
+/// info = uint16 Length || opaque label<7..255> = "tls13 " + Label || opaque context<0..255>.
+pub fn expand_label(secret: &Prk, label: &[u8], context: &[u8], len: usize) -> Result<Vec<u8>, HkdfError> {
+    let full_label = [&b"tls13 "[..], label].concat();
+    let len_u16 = u16::try_from(len).map_err(|_| HkdfError::LabelTooLong)?;
+    let (Ok(label_len), Ok(context_len)) = (u8::try_from(full_label.len()), u8::try_from(context.len())) else {
+        return Err(HkdfError::LabelTooLong);
+    };
+    let info = [&len_u16.to_be_bytes()[..], &[label_len], &full_label, &[context_len], context].concat();
+    expand(secret, &info, len)
+}
+
+/// Derive-Secret(Secret, Label, Messages) = HKDF-Expand-Label(Secret, Label, Transcript-Hash(Messages), HashLen).
+pub fn derive_secret(secret: &Prk, label: &[u8], messages: &[u8]) -> Result<Prk, HkdfError> {
+    let alg = secret.alg;
+    let bytes = Zeroizing::new(expand_label(secret, label, &alg.hash(messages), alg.output_len())?);
+    Ok(Prk { alg, bytes })
+}
 
 /// Derive a 32-byte key for AES-256-GCM using HKDF-SHA3-256.
 pub fn derive_aes256gcm_key(
     seed: &[u8],                // input key material
     salt: Option<&[u8]>,        // salt, optional(None -> zero-salt)
     context: &[u8],                // context string / transcript hash, etc.
-) -> [u8; 32] {
-    let hk = Hkdf::<Sha3_256>::new(salt, seed);
-    let mut key = [0u8; 32];
-    hk.expand(context, &mut key).expect("This key is for AES-GCM, deriving from DH shared secret");
-    key
-}
\ No newline at end of file
+) -> SecretKey<32> {
+    derive_key(HashAlg::Sha3_256, seed, salt, context).expect("32 bytes is always a valid HKDF-SHA3-256 output length")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    /// RFC 5869 Appendix A.1 - A.3 (SHA-256): (IKM, salt, info, PRK, OKM).
+    #[test]
+    fn rfc5869_sha256_vectors() {
+        let cases = [
+            (
+                vec![0x0b; 22],
+                Some((0x00..=0x0c).collect::<Vec<u8>>()),
+                (0xf0..=0xf9).collect(),
+                "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+            ),
+            (
+                (0x00..=0x4f).collect(),
+                Some((0x60..=0xaf).collect()),
+                (0xb0..=0xff).collect(),
+                "06a6b88c5853361a06104c9ceb35b45cef760014904671014a193f40c15fc244",
+                "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71cc30c58179ec3e87c14c01d5c1f3434f1d87",
+            ),
+            (
+                vec![0x0b; 22],
+                None,
+                vec![],
+                "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04",
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8",
+            ),
+        ];
+        for (ikm, salt, info, prk, okm) in cases {
+            let p = extract(HashAlg::Sha256, salt.as_deref(), &ikm);
+            assert_eq!(p.as_bytes(), h(prk));
+            assert_eq!(expand(&p, &info, okm.len() / 2).unwrap(), h(okm));
+        }
+    }
+
+    /// RFC 5869 A.1 inputs with the other hashes; expected values from Python's hmac module.
+    #[test]
+    fn other_hashes() {
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        for (alg, okm) in [
+            (HashAlg::Sha384, "9b5097a86038b805309076a44b3a9f38063e25b516dcbf369f394cfab43685f748b6457763e4f0204fc5"),
+            (HashAlg::Sha3_256, "0c5160501d65021deaf2c14f5abce04c5bd2635abceeba61c2edb6e8ed72674900557728f2c9f2c4c179"),
+            (HashAlg::Sha3_512, "40e9f17e9bf2ef99425c2b23ccdf20a018ea5513f9ae68e1ea8c626deb57dfa4d56c27ccf2a2a24488a5"),
+        ] {
+            let prk = extract(alg, Some(&salt), &[0x0b; 22]);
+            assert_eq!(prk.as_bytes().len(), alg.output_len());
+            assert_eq!(expand(&prk, &info, 42).unwrap(), h(okm), "{alg:?}");
+        }
+    }
+
+    #[test]
+    fn output_length_limits() {
+        for alg in [HashAlg::Sha256, HashAlg::Sha384, HashAlg::Sha3_256, HashAlg::Sha3_512] {
+            let prk = extract(alg, None, b"ikm");
+            let max = alg.max_output_len();
+            assert_eq!(expand(&prk, b"", max).unwrap().len(), max);
+            assert_eq!(expand(&prk, b"", max + 1), Err(HkdfError::OutputTooLong { requested: max + 1, max }));
+            // shorter outputs are prefixes of longer ones
+            assert_eq!(expand(&prk, b"x", 10).unwrap(), expand(&prk, b"x", 100).unwrap()[..10]);
+        }
+        assert!(matches!(Prk::from_bytes(HashAlg::Sha384, &[0u8; 32]), Err(HkdfError::PrkTooShort)));
+        assert_eq!(derive_key::<8161>(HashAlg::Sha256, b"", None, b""), Err(HkdfError::OutputTooLong { requested: 8161, max: 8160 }));
+    }
+
+    /// RFC 8448 §3: early secret and its "derived" secret for a handshake without PSK.
+    #[test]
+    fn tls13_key_schedule() {
+        let early = extract(HashAlg::Sha256, None, &[0u8; 32]);
+        assert_eq!(early.as_bytes(), h("33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a"));
+        let derived = derive_secret(&early, b"derived", b"").unwrap();
+        assert_eq!(derived.as_bytes(), h("6f2615a108c702c5678f54fc9dbab69716c076189c48250cebeac3576c3611ba"));
+
+        assert_eq!(expand_label(&early, &[b'a'; 250], b"", 16), Err(HkdfError::LabelTooLong));
+        assert_eq!(expand_label(&early, b"key", &[0u8; 256], 16), Err(HkdfError::LabelTooLong));
+    }
+
+    #[test]
+    fn aes_key_helper_is_unchanged() {
+        let prk = extract(HashAlg::Sha3_256, Some(b"salt"), b"seed");
+        assert_eq!(derive_aes256gcm_key(b"seed", Some(b"salt"), b"ctx").expose_secret().to_vec(), expand(&prk, b"ctx", 32).unwrap());
+    }
+}
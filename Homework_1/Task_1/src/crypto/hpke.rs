@@ -0,0 +1,511 @@
+//! Hybrid Public Key Encryption (RFC 9180) with DHKEM(X25519, HKDF-SHA256) and HKDF-SHA256.
+//! - Modes: Base, PSK, Auth and AuthPSK (chosen by which of `psk` / sender key are given).
+//! - AEADs: AES-128-GCM, AES-256-GCM, ChaCha20-Poly1305 and export-only.
+//! - `SenderContext::seal` / `RecipientContext::open` handle the per-message nonces;
+//!   both sides can `export` secrets bound to the session.
+
+use std::fmt;
+
+use aes_gcm::{Aes128Gcm, KeyInit, aead::{Aead, Payload}};
+use hkdf::Hkdf;
+use rand::{RngCore, rngs::OsRng};
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use super::aead::CipherSuite;
+use super::secret::SharedSecret;
+
+pub const KEM_ID: u16 = 0x0020; // DHKEM(X25519, HKDF-SHA256)
+pub const KDF_ID: u16 = 0x0001; // HKDF-SHA256
+const N_SECRET: usize = 32;
+const N_H: usize = 32;
+const N_N: usize = 12;
+const N_PK: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Base = 0x00,
+    Psk = 0x01,
+    Auth = 0x02,
+    AuthPsk = 0x03,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HpkeAead {
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+    /// No encryption, only `export` is available.
+    ExportOnly,
+}
+
+impl HpkeAead {
+    pub fn id(self) -> u16 {
+        match self {
+            HpkeAead::Aes128Gcm => 0x0001,
+            HpkeAead::Aes256Gcm => 0x0002,
+            HpkeAead::ChaCha20Poly1305 => 0x0003,
+            HpkeAead::ExportOnly => 0xffff,
+        }
+    }
+
+    fn key_len(self) -> usize {
+        match self {
+            HpkeAead::Aes128Gcm => 16,
+            HpkeAead::Aes256Gcm | HpkeAead::ChaCha20Poly1305 => 32,
+            HpkeAead::ExportOnly => 0,
+        }
+    }
+
+    fn seal(self, key: &[u8], nonce: &[u8], aad: &[u8], pt: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        match self {
+            HpkeAead::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|_| HpkeError::Seal)?
+                .encrypt(nonce.into(), Payload { msg: pt, aad }),
+            HpkeAead::Aes256Gcm => CipherSuite::Aes256Gcm.encrypt(key, nonce, pt, aad),
+            HpkeAead::ChaCha20Poly1305 => CipherSuite::ChaCha20Poly1305.encrypt(key, nonce, pt, aad),
+            HpkeAead::ExportOnly => return Err(HpkeError::ExportOnly),
+        }
+        .map_err(|_| HpkeError::Seal)
+    }
+
+    fn open(self, key: &[u8], nonce: &[u8], aad: &[u8], ct: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        match self {
+            HpkeAead::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|_| HpkeError::Open)?
+                .decrypt(nonce.into(), Payload { msg: ct, aad }),
+            HpkeAead::Aes256Gcm => CipherSuite::Aes256Gcm.decrypt(key, nonce, ct, aad),
+            HpkeAead::ChaCha20Poly1305 => CipherSuite::ChaCha20Poly1305.decrypt(key, nonce, ct, aad),
+            HpkeAead::ExportOnly => return Err(HpkeError::ExportOnly),
+        }
+        .map_err(|_| HpkeError::Open)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HpkeError {
+    /// `psk` and `psk_id` must be both empty or both non-empty.
+    InconsistentPsk,
+    /// `enc` is not a 32-byte X25519 public key.
+    BadEncapsulation,
+    /// A Diffie-Hellman output was all zero (peer key of small order).
+    NonContributory,
+    Seal,
+    /// Wrong key, wrong AAD, tampered ciphertext or messages out of order.
+    Open,
+    /// The sequence number would overflow.
+    MessageLimitReached,
+    /// seal/open called on an export-only context.
+    ExportOnly,
+    /// Requested export length exceeds 255 * 32 bytes.
+    ExportTooLong,
+}
+
+impl fmt::Display for HpkeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HpkeError::InconsistentPsk => write!(f, "psk and psk_id must be given together"),
+            HpkeError::BadEncapsulation => write!(f, "invalid encapsulated key"),
+            HpkeError::NonContributory => write!(f, "Diffie-Hellman output is all zero"),
+            HpkeError::Seal => write!(f, "encryption failed"),
+            HpkeError::Open => write!(f, "decryption failed"),
+            HpkeError::MessageLimitReached => write!(f, "message limit reached"),
+            HpkeError::ExportOnly => write!(f, "context is export-only"),
+            HpkeError::ExportTooLong => write!(f, "export length too large"),
+        }
+    }
+}
+
+impl std::error::Error for HpkeError {}
+
+/// Pre-shared key and its identifier for the PSK and AuthPSK modes.
+#[derive(Clone, Copy)]
+pub struct Psk<'a> {
+    pub psk: &'a [u8],
+    pub psk_id: &'a [u8],
+}
+
+/// HPKE cipher suite. KEM and KDF are fixed, the AEAD is selectable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Suite {
+    pub aead: HpkeAead,
+}
+
+impl Suite {
+    pub fn new(aead: HpkeAead) -> Self {
+        Self { aead }
+    }
+
+    /// "HPKE" || kem_id || kdf_id || aead_id
+    fn suite_id(&self) -> Vec<u8> {
+        [&b"HPKE"[..], &KEM_ID.to_be_bytes(), &KDF_ID.to_be_bytes(), &self.aead.id().to_be_bytes()].concat()
+    }
+}
+
+fn kem_suite_id() -> Vec<u8> {
+    [&b"KEM"[..], &KEM_ID.to_be_bytes()].concat()
+}
+
+/// Extract(salt, "HPKE-v1" || suite_id || label || ikm)
+fn labeled_extract(suite_id: &[u8], salt: &[u8], label: &[u8], ikm: &[u8]) -> [u8; N_H] {
+    let labeled_ikm = [&b"HPKE-v1"[..], suite_id, label, ikm].concat();
+    Hkdf::<Sha256>::extract(Some(salt), &labeled_ikm).0.into()
+}
+
+/// Expand(prk, I2OSP(L, 2) || "HPKE-v1" || suite_id || label || info, L)
+fn labeled_expand(suite_id: &[u8], prk: &[u8], label: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, HpkeError> {
+    let len_bytes = u16::try_from(len).map_err(|_| HpkeError::ExportTooLong)?.to_be_bytes();
+    let labeled_info = [&len_bytes[..], b"HPKE-v1", suite_id, label, info].concat();
+    let hk = Hkdf::<Sha256>::from_prk(prk).expect("PRK is one hash long");
+    let mut out = vec![0u8; len];
+    hk.expand(&labeled_info, &mut out).map_err(|_| HpkeError::ExportTooLong)?;
+    Ok(out)
+}
+
+/// DeriveKeyPair(ikm) for X25519 (RFC 9180 §7.1.3).
+pub fn derive_key_pair(ikm: &[u8]) -> (StaticSecret, PublicKey) {
+    let sid = kem_suite_id();
+    let dkp_prk = labeled_extract(&sid, b"", b"dkp_prk", ikm);
+    let sk: [u8; 32] = labeled_expand(&sid, &dkp_prk, b"sk", b"", 32).unwrap().try_into().unwrap();
+    let sk = StaticSecret::from(sk);
+    let pk = PublicKey::from(&sk);
+    (sk, pk)
+}
+
+/// Fresh random recipient (or sender) key pair.
+pub fn generate_key_pair() -> (StaticSecret, PublicKey) {
+    let mut ikm = [0u8; 32];
+    OsRng.fill_bytes(&mut ikm);
+    derive_key_pair(&ikm)
+}
+
+fn dh(sk: &StaticSecret, pk: &PublicKey) -> Result<SharedSecret, HpkeError> {
+    let ss = sk.diffie_hellman(pk);
+    if !ss.was_contributory() {
+        return Err(HpkeError::NonContributory);
+    }
+    Ok(SharedSecret::new(ss.to_bytes()))
+}
+
+/// ExtractAndExpand(dh, kem_context)
+fn extract_and_expand(dh: &[u8], kem_context: &[u8]) -> SharedSecret<N_SECRET> {
+    let sid = kem_suite_id();
+    let eae_prk = Zeroizing::new(labeled_extract(&sid, b"", b"eae_prk", dh));
+    let ss = Zeroizing::new(labeled_expand(&sid, &*eae_prk, b"shared_secret", kem_context, N_SECRET).unwrap());
+    SharedSecret::from_slice(&ss).unwrap()
+}
+
+/// Encap / AuthEncap with a given ephemeral key. Returns (shared_secret, enc).
+fn encap(sk_e: &StaticSecret, pk_r: &PublicKey, sk_s: Option<&StaticSecret>) -> Result<(SharedSecret<N_SECRET>, [u8; N_PK]), HpkeError> {
+    let enc = PublicKey::from(sk_e).to_bytes();
+    let mut dh_bytes = Zeroizing::new(dh(sk_e, pk_r)?.expose_secret().to_vec());
+    let mut kem_context = [enc, pk_r.to_bytes()].concat();
+    if let Some(sk_s) = sk_s {
+        dh_bytes.extend_from_slice(dh(sk_s, pk_r)?.expose_secret());
+        kem_context.extend_from_slice(&PublicKey::from(sk_s).to_bytes());
+    }
+    Ok((extract_and_expand(&dh_bytes, &kem_context), enc))
+}
+
+/// Decap / AuthDecap.
+fn decap(enc: &[u8], sk_r: &StaticSecret, pk_s: Option<&PublicKey>) -> Result<SharedSecret<N_SECRET>, HpkeError> {
+    let enc: [u8; N_PK] = enc.try_into().map_err(|_| HpkeError::BadEncapsulation)?;
+    let pk_e = PublicKey::from(enc);
+    let mut dh_bytes = Zeroizing::new(dh(sk_r, &pk_e)?.expose_secret().to_vec());
+    let mut kem_context = [enc, PublicKey::from(sk_r).to_bytes()].concat();
+    if let Some(pk_s) = pk_s {
+        dh_bytes.extend_from_slice(dh(sk_r, pk_s)?.expose_secret());
+        kem_context.extend_from_slice(pk_s.as_bytes());
+    }
+    Ok(extract_and_expand(&dh_bytes, &kem_context))
+}
+
+fn mode_of(psk: Option<Psk>, auth: bool) -> Mode {
+    match (psk.is_some(), auth) {
+        (false, false) => Mode::Base,
+        (true, false) => Mode::Psk,
+        (false, true) => Mode::Auth,
+        (true, true) => Mode::AuthPsk,
+    }
+}
+
+/// KeySchedule (RFC 9180 §5.1).
+fn key_schedule(suite: &Suite, mode: Mode, shared_secret: &[u8], info: &[u8], psk: Option<Psk>) -> Result<Context, HpkeError> {
+    let (psk, psk_id) = psk.map_or((&[][..], &[][..]), |p| (p.psk, p.psk_id));
+    if psk.is_empty() != psk_id.is_empty() || (psk.is_empty() && matches!(mode, Mode::Psk | Mode::AuthPsk)) {
+        return Err(HpkeError::InconsistentPsk);
+    }
+
+    let sid = suite.suite_id();
+    let psk_id_hash = labeled_extract(&sid, b"", b"psk_id_hash", psk_id);
+    let info_hash = labeled_extract(&sid, b"", b"info_hash", info);
+    let ks_context = [&[mode as u8][..], &psk_id_hash, &info_hash].concat();
+
+    let secret = Zeroizing::new(labeled_extract(&sid, shared_secret, b"secret", psk));
+    let (key, base_nonce) = if suite.aead == HpkeAead::ExportOnly {
+        (Zeroizing::default(), Vec::new())
+    } else {
+        (
+            Zeroizing::new(labeled_expand(&sid, &*secret, b"key", &ks_context, suite.aead.key_len())?),
+            labeled_expand(&sid, &*secret, b"base_nonce", &ks_context, N_N)?,
+        )
+    };
+    let exporter_secret = Zeroizing::new(labeled_expand(&sid, &*secret, b"exp", &ks_context, N_H)?);
+    Ok(Context { suite: *suite, key, base_nonce, seq: 0, exporter_secret })
+}
+
+/// Shared state of an HPKE session; wrapped by the sender and recipient context types.
+struct Context {
+    suite: Suite,
+    key: Zeroizing<Vec<u8>>,
+    base_nonce: Vec<u8>,
+    seq: u64,
+    exporter_secret: Zeroizing<Vec<u8>>,
+}
+
+impl Context {
+    /// base_nonce XOR I2OSP(seq, Nn). The last sequence number is never used so `seq` cannot wrap.
+    fn nonce(&self) -> Result<Vec<u8>, HpkeError> {
+        if self.suite.aead == HpkeAead::ExportOnly {
+            return Err(HpkeError::ExportOnly);
+        }
+        if self.seq == u64::MAX {
+            return Err(HpkeError::MessageLimitReached);
+        }
+        let mut nonce = self.base_nonce.clone();
+        for (n, s) in nonce[N_N - 8..].iter_mut().zip(self.seq.to_be_bytes()) {
+            *n ^= s;
+        }
+        Ok(nonce)
+    }
+
+    fn export(&self, exporter_context: &[u8], len: usize) -> Result<Vec<u8>, HpkeError> {
+        if len > 255 * N_H {
+            return Err(HpkeError::ExportTooLong);
+        }
+        labeled_expand(&self.suite.suite_id(), &self.exporter_secret, b"sec", exporter_context, len)
+    }
+}
+
+/// Sender side of an HPKE session.
+pub struct SenderContext(Context);
+
+impl SenderContext {
+    /// Encrypt the next message. Messages must be opened in the same order.
+    pub fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        let ct = self.0.suite.aead.seal(&self.0.key, &self.0.nonce()?, aad, plaintext)?;
+        self.0.seq += 1;
+        Ok(ct)
+    }
+
+    /// Secret of `len` bytes bound to this session and `exporter_context`.
+    pub fn export(&self, exporter_context: &[u8], len: usize) -> Result<Vec<u8>, HpkeError> {
+        self.0.export(exporter_context, len)
+    }
+}
+
+/// Recipient side of an HPKE session.
+pub struct RecipientContext(Context);
+
+impl RecipientContext {
+    /// Decrypt the next message; the sequence number only advances on success.
+    pub fn open(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, HpkeError> {
+        let pt = self.0.suite.aead.open(&self.0.key, &self.0.nonce()?, aad, ciphertext)?;
+        self.0.seq += 1;
+        Ok(pt)
+    }
+
+    pub fn export(&self, exporter_context: &[u8], len: usize) -> Result<Vec<u8>, HpkeError> {
+        self.0.export(exporter_context, len)
+    }
+}
+
+/// Set up the sender side towards recipient key `pk_r`. Returns (enc, context); `enc` must be sent to the recipient.
+/// The mode is Base, PSK, Auth or AuthPSK depending on whether `psk` and/or the sender's static key `sk_s` are given.
+pub fn setup_sender(suite: &Suite, pk_r: &PublicKey, info: &[u8], psk: Option<Psk>, sk_s: Option<&StaticSecret>) -> Result<(Vec<u8>, SenderContext), HpkeError> {
+    let (sk_e, _) = generate_key_pair();
+    setup_sender_with_ephemeral(suite, pk_r, info, psk, sk_s, &sk_e)
+}
+
+fn setup_sender_with_ephemeral(suite: &Suite, pk_r: &PublicKey, info: &[u8], psk: Option<Psk>, sk_s: Option<&StaticSecret>, sk_e: &StaticSecret) -> Result<(Vec<u8>, SenderContext), HpkeError> {
+    let (shared_secret, enc) = encap(sk_e, pk_r, sk_s)?;
+    let ctx = key_schedule(suite, mode_of(psk, sk_s.is_some()), shared_secret.expose_secret(), info, psk)?;
+    Ok((enc.to_vec(), SenderContext(ctx)))
+}
+
+/// Set up the recipient side from the sender's `enc`. `psk` and the sender's public key `pk_s`
+/// must match what the sender used.
+pub fn setup_recipient(suite: &Suite, enc: &[u8], sk_r: &StaticSecret, info: &[u8], psk: Option<Psk>, pk_s: Option<&PublicKey>) -> Result<RecipientContext, HpkeError> {
+    let shared_secret = decap(enc, sk_r, pk_s)?;
+    let ctx = key_schedule(suite, mode_of(psk, pk_s.is_some()), shared_secret.expose_secret(), info, psk)?;
+    Ok(RecipientContext(ctx))
+}
+
+/// Single-shot Base mode encryption. Returns (enc, ciphertext).
+pub fn seal(suite: &Suite, pk_r: &PublicKey, info: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), HpkeError> {
+    let (enc, mut ctx) = setup_sender(suite, pk_r, info, None, None)?;
+    Ok((enc, ctx.seal(aad, plaintext)?))
+}
+
+/// Single-shot Base mode decryption.
+pub fn open(suite: &Suite, enc: &[u8], sk_r: &StaticSecret, info: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, HpkeError> {
+    setup_recipient(suite, enc, sk_r, info, None, None)?.open(aad, ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    const INFO: &[u8] = b"Ode on a Grecian Urn";
+    const PT: &[u8] = b"Beauty is truth, truth beauty";
+    const PSK: &str = "0247fd33b913760fa1fa51e1892d9f307fbe65eb171e8132c2af18555a738b82";
+    const PSK_ID: &[u8] = b"Ennyn Durin aran Moria";
+
+    /// Key schedule outputs for RFC 9180 A.1: (ikmE, ikmR, ikmS, enc, key, base_nonce, exporter_secret).
+    fn check_vector(psk: Option<Psk>, ikm: [&str; 3], enc: &str, key: &str, base_nonce: &str, exporter_secret: &str) -> SenderContext {
+        let suite = Suite::new(HpkeAead::Aes128Gcm);
+        let (sk_e, _) = derive_key_pair(&h(ikm[0]));
+        let (sk_r, pk_r) = derive_key_pair(&h(ikm[1]));
+        let sender = (!ikm[2].is_empty()).then(|| derive_key_pair(&h(ikm[2])));
+
+        let (got_enc, ctx) = setup_sender_with_ephemeral(&suite, &pk_r, INFO, psk, sender.as_ref().map(|s| &s.0), &sk_e).unwrap();
+        assert_eq!(got_enc, h(enc));
+        assert_eq!(*ctx.0.key, h(key));
+        assert_eq!(ctx.0.base_nonce, h(base_nonce));
+        assert_eq!(*ctx.0.exporter_secret, h(exporter_secret));
+
+        let rctx = setup_recipient(&suite, &got_enc, &sk_r, INFO, psk, sender.as_ref().map(|s| &s.1)).unwrap();
+        assert_eq!(rctx.0.key, ctx.0.key);
+        ctx
+    }
+
+    #[test]
+    fn rfc9180_base() {
+        let (sk_e, pk_e) = derive_key_pair(&h("7268600d403fce431561aef583ee1613527cff655c1343f29812e66706df3234"));
+        assert_eq!(sk_e.to_bytes().to_vec(), h("52c4a758a802cd8b936eceea314432798d5baf2d7e9235dc084ab1b9cfa2f736"));
+        assert_eq!(pk_e.as_bytes().to_vec(), h("37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431"));
+
+        let mut ctx = check_vector(
+            None,
+            ["7268600d403fce431561aef583ee1613527cff655c1343f29812e66706df3234", "6db9df30aa07dd42ee5e8181afdb977e538f5e1fec8a06223f33f7013e525037", ""],
+            "37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431",
+            "4531685d41d65f03dc48f6b8302c05b0",
+            "56d890e5accaaf011cff4b7d",
+            "45ff1c2e220db587171952c0592d5f5ebe103f1561a2614e38f2ffd47e99e3f8",
+        );
+        assert_eq!(
+            ctx.seal(b"Count-0", PT).unwrap(),
+            h("f938558b5d72f1a23810b4be2ab4f84331acc02fc97babc53a52ae8218a355a96d8770ac83d07bea87e13c512a")
+        );
+        assert_eq!(ctx.export(b"", 32).unwrap(), h("3853fe2b4035195a573ffc53856e77058e15d9ea064de3e59f4961d0095250ee"));
+        assert_eq!(ctx.export(&[0], 32).unwrap(), h("2e8f0b54673c7029649d4eb9d5e33bf1872cf76d623ff164ac185da9e88c21a5"));
+        assert_eq!(ctx.export(b"TestContext", 32).unwrap(), h("e9e43065102c3836401bed8c3c3c75ae46be1639869391d62c61f1ec7af54931"));
+    }
+
+    #[test]
+    fn rfc9180_psk_auth_auth_psk() {
+        let psk = h(PSK);
+        let psk = Psk { psk: &psk, psk_id: PSK_ID };
+
+        let mut ctx = check_vector(
+            Some(psk),
+            ["78628c354e46f3e169bd231be7b2ff1c77aa302460a26dbfa15515684c00130b", "d4a09d09f575fef425905d2ab396c1449141463f698f8efdb7accfaff8995098", ""],
+            "0ad0950d9fb9588e59690b74f1237ecdf1d775cd60be2eca57af5a4b0471c91b",
+            "15026dba546e3ae05836fc7de5a7bb26",
+            "9518635eba129d5ce0914555",
+            "3d76025dbbedc49448ec3f9080a1abab6b06e91c0b11ad23c912f043a0ee7655",
+        );
+        assert_eq!(
+            ctx.seal(b"Count-0", PT).unwrap(),
+            h("e52c6fed7f758d0cf7145689f21bc1be6ec9ea097fef4e959440012f4feb73fb611b946199e681f4cfc34db8ea")
+        );
+
+        check_vector(
+            None,
+            ["6e6d8f200ea2fb20c30b003a8b4f433d2f4ed4c2658d5bc8ce2fef718059c9f7", "f1d4a30a4cef8d6d4e3b016e6fd3799ea057db4f345472ed302a67ce1c20cdec", "94b020ce91d73fca4649006c7e7329a67b40c55e9e93cc907d282bbbff386f58"],
+            "23fb952571a14a25e3d678140cd0e5eb47a0961bb18afcf85896e5453c312e76",
+            "b062cb2c4dd4bca0ad7c7a12bbc341e6",
+            "a1bc314c1942ade7051ffed0",
+            "ee1a093e6e1c393c162ea98fdf20560c75909653550540a2700511b65c88c6f1",
+        );
+
+        check_vector(
+            Some(psk),
+            ["4303619085a20ebcf18edd22782952b8a7161e1dbae6e46e143a52a96127cf84", "4b16221f3b269a88e207270b5e1de28cb01f847841b344b8314d6a622fe5ee90", "62f77dcf5df0dd7eac54eac9f654f426d4161ec850cc65c54f8b65d2e0b4e345"],
+            "820818d3c23993492cc5623ab437a48a0a7ca3e9639c140fe1e33811eb844b7c",
+            "1364ead92c47aa7becfa95203037b19a",
+            "99d8b5c54669807e9fc70df1",
+            "f048d55eacbf60f9c6154bd4021774d1075ebf963c6adc71fa846f183ab2dde6",
+        );
+    }
+
+    #[test]
+    fn all_modes_and_aeads_roundtrip() {
+        let (sk_r, pk_r) = generate_key_pair();
+        let (sk_s, pk_s) = generate_key_pair();
+        let psk = Psk { psk: b"0123456789abcdef0123456789abcdef", psk_id: b"id" };
+
+        for aead in [HpkeAead::Aes128Gcm, HpkeAead::Aes256Gcm, HpkeAead::ChaCha20Poly1305] {
+            let suite = Suite::new(aead);
+            for (psk, auth) in [(None, false), (Some(psk), false), (None, true), (Some(psk), true)] {
+                let (enc, mut s) = setup_sender(&suite, &pk_r, b"info", psk, auth.then_some(&sk_s)).unwrap();
+                let mut r = setup_recipient(&suite, &enc, &sk_r, b"info", psk, auth.then_some(&pk_s)).unwrap();
+                for i in 0..3u8 {
+                    let ct = s.seal(&[i], b"message").unwrap();
+                    assert_eq!(r.open(&[i], &ct).unwrap(), b"message");
+                }
+                assert_eq!(s.export(b"ctx", 64).unwrap(), r.export(b"ctx", 64).unwrap());
+            }
+        }
+
+        let suite = Suite::new(HpkeAead::ChaCha20Poly1305);
+        let (enc, ct) = seal(&suite, &pk_r, b"info", b"aad", b"single shot").unwrap();
+        assert_eq!(open(&suite, &enc, &sk_r, b"info", b"aad", &ct).unwrap(), b"single shot");
+    }
+
+    #[test]
+    fn mismatched_inputs_fail() {
+        let suite = Suite::new(HpkeAead::Aes128Gcm);
+        let (sk_r, pk_r) = generate_key_pair();
+        let (sk_s, _) = generate_key_pair();
+        let (_, pk_other) = generate_key_pair();
+        let psk = Psk { psk: b"0123456789abcdef0123456789abcdef", psk_id: b"id" };
+        let wrong_psk = Psk { psk: b"fedcba9876543210fedcba9876543210", psk_id: b"id" };
+
+        let (enc, mut s) = setup_sender(&suite, &pk_r, b"info", Some(psk), Some(&sk_s)).unwrap();
+        let ct = s.seal(b"aad", b"msg").unwrap();
+        for (info, psk, pk_s) in [(&b"info"[..], wrong_psk, &pk_other), (b"info", wrong_psk, &PublicKey::from(&sk_s)), (b"other", psk, &PublicKey::from(&sk_s))] {
+            let mut r = setup_recipient(&suite, &enc, &sk_r, info, Some(psk), Some(pk_s)).unwrap();
+            assert_eq!(r.open(b"aad", &ct), Err(HpkeError::Open));
+        }
+
+        let mut r = setup_recipient(&suite, &enc, &sk_r, b"info", Some(psk), Some(&PublicKey::from(&sk_s))).unwrap();
+        assert_eq!(r.open(b"other aad", &ct), Err(HpkeError::Open));
+        assert_eq!(r.open(b"aad", &ct).unwrap(), b"msg");
+        // replaying the same ciphertext uses the next nonce and fails
+        assert_eq!(r.open(b"aad", &ct), Err(HpkeError::Open));
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        let (sk_r, pk_r) = generate_key_pair();
+        let suite = Suite::new(HpkeAead::Aes128Gcm);
+        let half = Psk { psk: b"secret", psk_id: b"" };
+        assert_eq!(setup_sender(&suite, &pk_r, b"", Some(half), None).err(), Some(HpkeError::InconsistentPsk));
+        assert_eq!(setup_recipient(&suite, &[0u8; 31], &sk_r, b"", None, None).err(), Some(HpkeError::BadEncapsulation));
+        // the identity point gives an all-zero shared secret
+        assert_eq!(setup_sender(&suite, &PublicKey::from([0u8; 32]), b"", None, None).err(), Some(HpkeError::NonContributory));
+
+        let export_only = Suite::new(HpkeAead::ExportOnly);
+        let (enc, mut s) = setup_sender(&export_only, &pk_r, b"", None, None).unwrap();
+        let r = setup_recipient(&export_only, &enc, &sk_r, b"", None, None).unwrap();
+        assert_eq!(s.seal(b"", b"x"), Err(HpkeError::ExportOnly));
+        assert_eq!(s.export(b"e", 255 * 32).unwrap(), r.export(b"e", 255 * 32).unwrap());
+        assert_eq!(s.export(b"e", 255 * 32 + 1), Err(HpkeError::ExportTooLong));
+    }
+}
@@ -0,0 +1,215 @@
+//! Key-derivation tree: many purpose-bound subkeys from one root secret.
+//! - A path such as "tenant-3/files" names a node; each segment is one HKDF-Expand step from
+//!   the parent node's PRK, and node PRKs are cached so siblings share the walk from the root.
+//! - A leaf key is one more HKDF-Expand from the node, bound to its purpose (AEAD, MAC, signing
+//!   seed) and output length.
+//!
+//! Domain separation: node and leaf steps carry different tag bytes, and every variable field is
+//! length-prefixed, so the info strings are prefix-free and distinct (path, purpose) pairs never
+//! feed HKDF the same input. `aead_key("a/b")`, `aead_key("ab")` and `mac_key("a/b")` are unrelated.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+
+use zeroize::Zeroizing;
+
+use super::hkdf::{self, HashAlg, HkdfError, Prk};
+use super::secret::SecretKey;
+
+/// Salt of the root extraction, so a root secret used elsewhere yields an unrelated tree.
+const ROOT_SALT: &[u8] = b"Task_1 key hierarchy v1";
+const NODE_TAG: u8 = 0x01;
+const LEAF_TAG: u8 = 0x02;
+
+pub const AEAD_KEY_LEN: usize = 32;
+pub const MAC_KEY_LEN: usize = 32;
+pub const SIGNING_SEED_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HierarchyError {
+    /// Empty path, empty segment ("a//b", leading or trailing '/') or a segment over 255 bytes.
+    InvalidPath(String),
+    Hkdf(HkdfError),
+}
+
+impl fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HierarchyError::InvalidPath(p) => write!(f, "invalid key path {p:?}"),
+            HierarchyError::Hkdf(e) => write!(f, "key derivation failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for HierarchyError {}
+
+impl From<HkdfError> for HierarchyError {
+    fn from(e: HkdfError) -> Self {
+        HierarchyError::Hkdf(e)
+    }
+}
+
+/// What a leaf key is for; part of the leaf's HKDF info.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Purpose {
+    Aead,
+    Mac,
+    SigningSeed,
+}
+
+impl Purpose {
+    fn label(self) -> &'static [u8] {
+        match self {
+            Purpose::Aead => b"aead",
+            Purpose::Mac => b"mac",
+            Purpose::SigningSeed => b"signing-seed",
+        }
+    }
+}
+
+/// Key for AES-256-GCM / ChaCha20-Poly1305.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AeadKey(pub SecretKey<AEAD_KEY_LEN>);
+/// Key for HMAC.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MacKey(pub SecretKey<MAC_KEY_LEN>);
+/// Seed for a signing key pair (e.g. an Ed25519 secret key).
+#[derive(Debug, PartialEq, Eq)]
+pub struct SigningSeed(pub SecretKey<SIGNING_SEED_LEN>);
+
+pub struct KeyHierarchy {
+    root: Prk,
+    /// Node PRKs by normalized path ("a", "a/b", ...).
+    cache: RefCell<HashMap<String, Prk>>,
+}
+
+impl KeyHierarchy {
+    /// Extract the root PRK from `root_secret` with HKDF-`alg`.
+    pub fn new(alg: HashAlg, root_secret: &[u8]) -> Self {
+        Self::from_prk(hkdf::extract(alg, Some(ROOT_SALT), root_secret))
+    }
+
+    /// Use an existing PRK (e.g. a node handed over by `subtree`) as root.
+    pub fn from_prk(root: Prk) -> Self {
+        Self { root, cache: RefCell::new(HashMap::new()) }
+    }
+
+    pub fn alg(&self) -> HashAlg {
+        self.root.alg()
+    }
+
+    pub fn aead_key(&self, path: &str) -> Result<AeadKey, HierarchyError> {
+        self.leaf(path, Purpose::Aead).map(AeadKey)
+    }
+
+    pub fn mac_key(&self, path: &str) -> Result<MacKey, HierarchyError> {
+        self.leaf(path, Purpose::Mac).map(MacKey)
+    }
+
+    pub fn signing_seed(&self, path: &str) -> Result<SigningSeed, HierarchyError> {
+        self.leaf(path, Purpose::SigningSeed).map(SigningSeed)
+    }
+
+    /// Hierarchy rooted at the node `path`: `subtree("a")?.aead_key("b")` equals `aead_key("a/b")`.
+    /// Lets a component derive its own keys without seeing the rest of the tree.
+    pub fn subtree(&self, path: &str) -> Result<KeyHierarchy, HierarchyError> {
+        Ok(Self::from_prk(self.node(path)?))
+    }
+
+    fn leaf<const N: usize>(&self, path: &str, purpose: Purpose) -> Result<SecretKey<N>, HierarchyError> {
+        let node = self.node(path)?;
+        let label = purpose.label();
+        let len = u16::try_from(N).map_err(|_| HkdfError::LabelTooLong)?;
+        let info = [&[LEAF_TAG, label.len() as u8][..], label, &len.to_be_bytes()].concat();
+        let mut out = SecretKey::new([0u8; N]);
+        hkdf::expand_into(&node, &info, out.expose_secret_mut())?;
+        Ok(out)
+    }
+
+    /// PRK of the node at `path`, walking down from the deepest cached ancestor.
+    fn node(&self, path: &str) -> Result<Prk, HierarchyError> {
+        let segments: Vec<&str> = path.split('/').collect();
+        if segments.iter().any(|s| s.is_empty() || s.len() > u8::MAX as usize) {
+            return Err(HierarchyError::InvalidPath(path.to_string()));
+        }
+
+        let mut cache = self.cache.borrow_mut();
+        let mut prk = self.root.clone();
+        for depth in 1..=segments.len() {
+            let prefix = segments[..depth].join("/");
+            prk = match cache.get(&prefix) {
+                Some(cached) => cached.clone(),
+                None => {
+                    let segment = segments[depth - 1].as_bytes();
+                    let info = [&[NODE_TAG, segment.len() as u8][..], segment].concat();
+                    let child = Prk::from_bytes(prk.alg(), &Zeroizing::new(hkdf::expand(&prk, &info, prk.alg().output_len())?))?;
+                    cache.insert(prefix, child.clone());
+                    child
+                }
+            };
+        }
+        Ok(prk)
+    }
+
+    #[cfg(test)]
+    fn cached_nodes(&self) -> usize {
+        self.cache.borrow().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const ROOT: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn paths_and_purposes_are_separated() {
+        let tree = KeyHierarchy::new(HashAlg::Sha3_256, ROOT);
+        let paths = ["a", "b", "ab", "a/b", "b/a", "a/b/c", "a/bc", "ab/c", "files", "audit", "device-7"];
+        let mut seen = HashSet::new();
+        for path in paths {
+            assert!(seen.insert(*tree.aead_key(path).unwrap().0.expose_secret()), "{path}");
+            assert!(seen.insert(*tree.mac_key(path).unwrap().0.expose_secret()), "{path}");
+            assert!(seen.insert(*tree.signing_seed(path).unwrap().0.expose_secret()), "{path}");
+        }
+    }
+
+    #[test]
+    fn deterministic_and_root_bound() {
+        let a = KeyHierarchy::new(HashAlg::Sha3_256, ROOT);
+        let b = KeyHierarchy::new(HashAlg::Sha3_256, ROOT);
+        assert_eq!(a.aead_key("files").unwrap(), b.aead_key("files").unwrap());
+
+        let other_root = KeyHierarchy::new(HashAlg::Sha3_256, b"another root secret");
+        let other_hash = KeyHierarchy::new(HashAlg::Sha384, ROOT);
+        assert_ne!(a.aead_key("files").unwrap(), other_root.aead_key("files").unwrap());
+        assert_ne!(a.aead_key("files").unwrap(), other_hash.aead_key("files").unwrap());
+    }
+
+    #[test]
+    fn subtree_matches_full_path_and_nodes_are_cached() {
+        let tree = KeyHierarchy::new(HashAlg::Sha256, ROOT);
+        let sub = tree.subtree("tenant-3").unwrap();
+        assert_eq!(sub.mac_key("audit/2024").unwrap(), tree.mac_key("tenant-3/audit/2024").unwrap());
+
+        // "tenant-3", "tenant-3/audit", "tenant-3/audit/2024"; siblings only add their own node
+        assert_eq!(tree.cached_nodes(), 3);
+        tree.mac_key("tenant-3/audit/2025").unwrap();
+        assert_eq!(tree.cached_nodes(), 4);
+        tree.aead_key("tenant-3/audit/2025").unwrap();
+        assert_eq!(tree.cached_nodes(), 4);
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        let tree = KeyHierarchy::new(HashAlg::Sha3_256, ROOT);
+        let long = "x".repeat(256);
+        for path in ["", "/files", "files/", "a//b", long.as_str()] {
+            assert_eq!(tree.aead_key(path).err(), Some(HierarchyError::InvalidPath(path.to_string())));
+        }
+        assert!(tree.aead_key(&"x".repeat(255)).is_ok());
+    }
+}
@@ -1,4 +1,9 @@
+pub mod secret;
 pub mod dhke;
 pub mod hkdf;
+pub mod key_hierarchy;
 pub mod aead;
+pub mod aead_stream;
+pub mod passfile;
+pub mod hpke;
 pub mod signdemo;
\ No newline at end of file
@@ -0,0 +1,260 @@
+//! Passphrase-based file encryption: Argon2id -> HKDF-SHA3-256 -> chunked AES-256-GCM.
+//! File layout:
+//!   magic "T1PF" (4) | version (1) | m_cost KiB (4) | t_cost (4) | p_cost (4) | salt (16)
+//!   | password check (32) | aead_stream output (nonce prefix || segments)
+//! The password check is a separate HKDF output of the Argon2id key, so a wrong password is
+//! reported as such before any segment is touched. The header is the associated data of the
+//! stream, so changing the parameters or salt is detected as corruption.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::{RngCore, rngs::OsRng};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use super::aead_stream::{DecryptReader, EncryptWriter};
+use super::hkdf::derive_aes256gcm_key;
+use super::secret::SecretKey;
+
+pub const MAGIC: [u8; 4] = *b"T1PF";
+pub const VERSION: u8 = 1;
+pub const SALT_LEN: usize = 16;
+const CHECK_LEN: usize = 32;
+const HEADER_LEN: usize = 4 + 1 + 12 + SALT_LEN + CHECK_LEN;
+/// Refuse headers asking for more than 1 GiB of Argon2 memory; checked before anything is allocated.
+const MAX_M_COST: u32 = 1024 * 1024;
+/// Refuse headers asking for more passes than anyone would wait for.
+const MAX_T_COST: u32 = 64;
+/// Refuse headers asking for more lanes than we could run in parallel.
+const MAX_P_COST: u32 = 64;
+
+/// Argon2id cost parameters, stored in the file header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory in KiB.
+    pub m_cost: u32,
+    /// Number of passes.
+    pub t_cost: u32,
+    /// Degree of parallelism.
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    /// OWASP recommendation for Argon2id: 19 MiB, 2 passes, 1 lane.
+    fn default() -> Self {
+        Self { m_cost: Params::DEFAULT_M_COST, t_cost: Params::DEFAULT_T_COST, p_cost: Params::DEFAULT_P_COST }
+    }
+}
+
+#[derive(Debug)]
+pub enum PassfileError {
+    /// Input does not start with the passfile magic.
+    NotEncrypted,
+    UnsupportedVersion(u8),
+    /// Cost parameters are invalid or exceed our limits.
+    BadParams(String),
+    /// The password check in the header does not match.
+    WrongPassword,
+    /// Header or ciphertext was truncated or modified.
+    Corrupted,
+    Io(io::Error),
+}
+
+impl fmt::Display for PassfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassfileError::NotEncrypted => write!(f, "input is not a password-encrypted file"),
+            PassfileError::UnsupportedVersion(v) => write!(f, "unsupported file version {v}"),
+            PassfileError::BadParams(e) => write!(f, "invalid key derivation parameters: {e}"),
+            PassfileError::WrongPassword => write!(f, "wrong password"),
+            PassfileError::Corrupted => write!(f, "file is corrupted or has been tampered with"),
+            PassfileError::Io(e) => write!(f, "I/O error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PassfileError {}
+
+impl From<io::Error> for PassfileError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => PassfileError::Corrupted,
+            _ => PassfileError::Io(e),
+        }
+    }
+}
+
+/// Run Argon2id and split the result into (file key, password check).
+fn derive_keys(password: &[u8], salt: &[u8], params: KdfParams) -> Result<(SecretKey<32>, [u8; 32]), PassfileError> {
+    if params.m_cost > MAX_M_COST {
+        return Err(PassfileError::BadParams(format!("m_cost {} KiB exceeds {} KiB", params.m_cost, MAX_M_COST)));
+    }
+    if params.t_cost > MAX_T_COST {
+        return Err(PassfileError::BadParams(format!("t_cost {} exceeds {}", params.t_cost, MAX_T_COST)));
+    }
+    if params.p_cost > MAX_P_COST {
+        return Err(PassfileError::BadParams(format!("p_cost {} exceeds {}", params.p_cost, MAX_P_COST)));
+    }
+    let p = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| PassfileError::BadParams(e.to_string()))?;
+    let mut master = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, p)
+        .hash_password_into(password, salt, &mut *master)
+        .map_err(|e| PassfileError::BadParams(e.to_string()))?;
+
+    let file_key = derive_aes256gcm_key(&*master, Some(salt), b"passfile v1 file key");
+    // stored in the header, so not secret
+    let check = *derive_aes256gcm_key(&*master, Some(salt), b"passfile v1 password check").expose_secret();
+    Ok((file_key, check))
+}
+
+fn encode_header(params: KdfParams, salt: &[u8; SALT_LEN], check: &[u8; CHECK_LEN]) -> [u8; HEADER_LEN] {
+    let mut h = [0u8; HEADER_LEN];
+    h[..4].copy_from_slice(&MAGIC);
+    h[4] = VERSION;
+    h[5..9].copy_from_slice(&params.m_cost.to_be_bytes());
+    h[9..13].copy_from_slice(&params.t_cost.to_be_bytes());
+    h[13..17].copy_from_slice(&params.p_cost.to_be_bytes());
+    h[17..17 + SALT_LEN].copy_from_slice(salt);
+    h[17 + SALT_LEN..].copy_from_slice(check);
+    h
+}
+
+/// Encrypt everything from `input` into `output` under `password`.
+pub fn encrypt<R: Read, W: Write>(password: &[u8], params: KdfParams, mut input: R, mut output: W) -> Result<(), PassfileError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let (file_key, check) = derive_keys(password, &salt, params)?;
+
+    let header = encode_header(params, &salt, &check);
+    output.write_all(&header)?;
+    let mut writer = EncryptWriter::new(file_key.expose_secret(), &header, output).map_err(PassfileError::Io)?;
+    io::copy(&mut input, &mut writer).map_err(PassfileError::Io)?;
+    writer.finish().map_err(PassfileError::Io)?;
+    Ok(())
+}
+
+/// Decrypt a file produced by `encrypt` into `output`.
+/// Plaintext is written segment by segment as it is authenticated; if `Corrupted` is returned,
+/// whatever was written so far must be discarded by the caller.
+pub fn decrypt<R: Read, W: Write>(password: &[u8], mut input: R, mut output: W) -> Result<(), PassfileError> {
+    let mut header = [0u8; HEADER_LEN];
+    read_header(&mut input, &mut header)?;
+    if header[4] != VERSION {
+        return Err(PassfileError::UnsupportedVersion(header[4]));
+    }
+    let field = |i: usize| u32::from_be_bytes(header[i..i + 4].try_into().unwrap());
+    let params = KdfParams { m_cost: field(5), t_cost: field(9), p_cost: field(13) };
+    let salt = &header[17..17 + SALT_LEN];
+
+    let (file_key, check) = derive_keys(password, salt, params)?;
+    if !bool::from(check.ct_eq(&header[17 + SALT_LEN..])) {
+        return Err(PassfileError::WrongPassword);
+    }
+
+    let mut reader = DecryptReader::new(file_key.expose_secret(), &header, input)?;
+    io::copy(&mut reader, &mut output)?;
+    output.flush().map_err(PassfileError::Io)?;
+    Ok(())
+}
+
+/// Read the fixed-size header, telling "not our format" apart from "our format, but cut short".
+fn read_header<R: Read>(input: &mut R, header: &mut [u8; HEADER_LEN]) -> Result<(), PassfileError> {
+    let mut filled = 0;
+    while filled < HEADER_LEN {
+        match input.read(&mut header[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(PassfileError::Io(e)),
+        }
+    }
+    if filled < MAGIC.len() || header[..4] != MAGIC {
+        return Err(PassfileError::NotEncrypted);
+    }
+    if filled < HEADER_LEN {
+        return Err(PassfileError::Corrupted);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // cheap parameters so the tests stay fast
+    const FAST: KdfParams = KdfParams { m_cost: 64, t_cost: 1, p_cost: 1 };
+
+    fn encrypt_bytes(password: &[u8], pt: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        encrypt(password, FAST, pt, &mut out).unwrap();
+        out
+    }
+
+    fn decrypt_bytes(password: &[u8], ct: &[u8]) -> Result<Vec<u8>, PassfileError> {
+        let mut out = Vec::new();
+        decrypt(password, ct, &mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn roundtrip() {
+        let pt: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let ct = encrypt_bytes(b"correct horse", &pt);
+        assert_eq!(decrypt_bytes(b"correct horse", &ct).unwrap(), pt);
+        assert_eq!(decrypt_bytes(b"pw", &encrypt_bytes(b"pw", b"")).unwrap(), b"");
+    }
+
+    #[test]
+    fn wrong_password_is_not_corruption() {
+        let ct = encrypt_bytes(b"correct horse", b"secret");
+        assert!(matches!(decrypt_bytes(b"battery staple", &ct), Err(PassfileError::WrongPassword)));
+    }
+
+    #[test]
+    fn corruption_is_detected() {
+        let ct = encrypt_bytes(b"pw", b"some file contents");
+
+        let mut body = ct.clone();
+        *body.last_mut().unwrap() ^= 1;
+        assert!(matches!(decrypt_bytes(b"pw", &body), Err(PassfileError::Corrupted)));
+
+        assert!(matches!(decrypt_bytes(b"pw", &ct[..ct.len() - 5]), Err(PassfileError::Corrupted)));
+        assert!(matches!(decrypt_bytes(b"pw", &ct[..HEADER_LEN - 1]), Err(PassfileError::Corrupted)));
+
+        // raising t_cost changes the derived key, so this looks like a wrong password
+        let mut params = ct.clone();
+        params[12] = 2;
+        assert!(matches!(decrypt_bytes(b"pw", &params), Err(PassfileError::WrongPassword)));
+    }
+
+    #[test]
+    fn rejects_foreign_input_and_versions() {
+        assert!(matches!(decrypt_bytes(b"pw", b"plain text file"), Err(PassfileError::NotEncrypted)));
+        let mut ct = encrypt_bytes(b"pw", b"x");
+        ct[4] = 9;
+        assert!(matches!(decrypt_bytes(b"pw", &ct), Err(PassfileError::UnsupportedVersion(9))));
+
+        let mut huge = encrypt_bytes(b"pw", b"x");
+        huge[5..9].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(decrypt_bytes(b"pw", &huge), Err(PassfileError::BadParams(_))));
+    }
+
+    /// Our own limits reject the header; Argon2 is never asked for the memory.
+    #[test]
+    fn rejects_excessive_costs() {
+        for (offset, limit, name) in [(5, MAX_M_COST, "m_cost"), (9, MAX_T_COST, "t_cost"), (13, MAX_P_COST, "p_cost")] {
+            let mut ct = encrypt_bytes(b"pw", b"x");
+            ct[offset..offset + 4].copy_from_slice(&(limit + 1).to_be_bytes());
+            match decrypt_bytes(b"pw", &ct) {
+                Err(PassfileError::BadParams(e)) => assert!(e.starts_with(name) && e.contains("exceeds"), "{e}"),
+                other => panic!("{name}: {other:?}"),
+            }
+        }
+        let mut out = Vec::new();
+        let slow = KdfParams { t_cost: MAX_T_COST + 1, ..FAST };
+        assert!(matches!(encrypt(b"pw", slow, &b"x"[..], &mut out), Err(PassfileError::BadParams(_))));
+    }
+}
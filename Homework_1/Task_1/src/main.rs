@@ -2,15 +2,25 @@ mod crypto;
 mod io;
 mod encode;
 
-use anyhow::Result;
-use crypto::{dhke, hkdf, aead};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{Result, anyhow, bail};
+use tempfile::NamedTempFile;
+use zeroize::Zeroizing;
+use crypto::{dhke, hkdf, aead, passfile, signdemo};
 use rand::{rngs::OsRng, RngCore};
 use encode::encode_b64::b64;
-use io::readline::read_line_prompt;
 use crypto::signdemo::{keygen, sign, verify};
 use base64::{engine::general_purpose, Engine as _};
 
 fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(cmd @ ("encrypt" | "decrypt")) = args.first().map(String::as_str) {
+        return passfile_cli(cmd, &args[1..]);
+    }
+
     println!("DHKE + HKDF + AEAD demo");
     println!(" - DH: X25519 (ephemeral/ephemeral in one process)");
     println!(" - KDF: HKDF-SHA3-256");
@@ -24,22 +34,17 @@ fn main() -> Result<()> {
     println!("Bob's pk (Base64): {}", b64(&bob.pk.to_bytes()));
     let ss_alice = dhke::shared_secret(alice.sk, &bob.pk);
     let ss_bob   = dhke::shared_secret(bob.sk, &alice.pk);
-    println!("Shared secret (Base64): {}", b64(&ss_alice));
+    println!("Shared secret (Base64): {}", b64(ss_alice.expose_secret()));
 
     println!("\n Signing demo using");
     // sign sigma_a
     let keypair_alice = keygen();
-    let sigma_a_sk_bytes = keypair_alice.sk.to_bytes();        // [u8; 32]
-    let sigma_a_pk_bytes = keypair_alice.pk.to_bytes();        // [u8; 32]
-    println!("Public Key signing pair Alice (Base64): {}", b64(&sigma_a_sk_bytes));
-    println!("Secret Key signing pair Alice (Base64): {}", b64(&sigma_a_pk_bytes));
+    println!("Public key signing pair Alice (SPKI):\n{}", signdemo::public_key_to_pem(&keypair_alice.pk));
 
     // sign sigma_b
     let keypair_bob = keygen();
-    let sigma_b_sk_bytes = keypair_bob.sk.to_bytes();        // [u8; 32]
-    let sigma_b_pk_bytes = keypair_bob.pk.to_bytes();        // [u8; 32]
-    println!("Public Key signing pair Bob (Base64): {}", b64(&sigma_a_sk_bytes));
-    println!("Secret Key signing pair Bob (Base64): {}", b64(&sigma_a_pk_bytes));
+    println!("Public key signing pair Bob (JWK): {}", signdemo::public_key_to_jwk(&keypair_bob.pk));
+    println!("Public key signing pair Bob (OpenSSH): {}", signdemo::public_key_to_openssh(&keypair_bob.pk, "bob"));
 
 
     // Sign sigma_a
@@ -60,4 +65,80 @@ fn main() -> Result<()> {
     println!("Verify (same message): {ok_bob}");
     
     Ok(())
-}
\ No newline at end of file
+}
+
+const PASSFILE_USAGE: &str = "usage: Task_1 encrypt [--m-cost KiB] [--t-cost N] [--p-cost N] <input> <output>\n       Task_1 decrypt <input> <output>\n('-' is stdin/stdout; the password is read from $PASSFILE_PASSWORD or prompted for)";
+
+/// `encrypt`/`decrypt` subcommands: password-based file encryption (see crypto::passfile).
+fn passfile_cli(cmd: &str, args: &[String]) -> Result<()> {
+    let mut params = passfile::KdfParams::default();
+    let mut paths = Vec::new();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let cost = match arg.as_str() {
+            "--m-cost" => &mut params.m_cost,
+            "--t-cost" => &mut params.t_cost,
+            "--p-cost" => &mut params.p_cost,
+            _ => {
+                paths.push(arg.as_str());
+                continue;
+            }
+        };
+        if cmd == "decrypt" {
+            bail!("{arg} is read from the file header when decrypting");
+        }
+        let value = it.next().ok_or_else(|| anyhow!("{arg} needs a value"))?;
+        *cost = value.parse().map_err(|_| anyhow!("{arg}: '{value}' is not a number"))?;
+    }
+    let [input, output] = paths[..] else {
+        bail!(PASSFILE_USAGE);
+    };
+    if input != "-" && output != "-" && same_file(input, output) {
+        bail!("{input}: input and output are the same file");
+    }
+
+    let password = match std::env::var("PASSFILE_PASSWORD") {
+        Ok(pw) => pw,
+        Err(_) if input == "-" || output == "-" => bail!("set PASSFILE_PASSWORD when using stdin/stdout"),
+        Err(_) => {
+            eprint!("Password: ");
+            std::io::stderr().flush()?;
+            rpassword::read_password()?
+        }
+    };
+    let password = Zeroizing::new(password);
+
+    let reader: Box<dyn Read> = if input == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(input).map_err(|e| anyhow!("cannot open {input}: {e}"))?))
+    };
+    let run = |writer: &mut dyn Write| match cmd {
+        "encrypt" => passfile::encrypt(password.as_bytes(), params, reader, writer),
+        _ => passfile::decrypt(password.as_bytes(), reader, writer),
+    };
+    if output == "-" {
+        return Ok(run(&mut std::io::stdout().lock())?);
+    }
+
+    // write next to the destination and only replace it once everything checked out, so a wrong
+    // password or corrupted input never clobbers an existing file or leaves partial output behind
+    let dir = match Path::new(output).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let tmp = NamedTempFile::new_in(dir).map_err(|e| anyhow!("cannot create a temporary file in {}: {e}", dir.display()))?;
+    let mut writer = BufWriter::new(tmp);
+    run(&mut writer)?;
+    let tmp = writer.into_inner().map_err(|e| e.into_error())?;
+    tmp.persist(output).map_err(|e| anyhow!("cannot write {output}: {}", e.error))?;
+    Ok(())
+}
+
+/// Whether `a` and `b` name the same existing file (`b` may not exist yet).
+fn same_file(a: &str, b: &str) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
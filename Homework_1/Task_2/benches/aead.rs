@@ -0,0 +1,56 @@
+//! Compare the one-shot `aead::encrypt`/`decrypt` (key schedule + allocation per call)
+//! with a reused `AeadContext` working in place on a caller-owned buffer.
+//! Run with `cargo bench --bench aead`.
+
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use Task_2::crypto::aead::{self, AeadContext, Aes256GcmSuite};
+
+const KEY: [u8; 32] = [0x42; 32];
+const NONCE: [u8; 12] = [0x24; 12];
+const SIZES: [usize; 4] = [64, 1024, 16 * 1024, 64 * 1024];
+
+fn bench_encrypt(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aes256gcm-encrypt");
+    for size in SIZES {
+        let plaintext = vec![0u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("one-shot", size), &plaintext, |b, pt| {
+            b.iter(|| aead::encrypt(&KEY, &NONCE, black_box(pt), b"").unwrap())
+        });
+
+        let ctx = AeadContext::<Aes256GcmSuite>::new(&KEY).unwrap();
+        let mut buf = plaintext.clone();
+        group.bench_function(BenchmarkId::new("context-in-place-detached", size), |b| {
+            b.iter(|| ctx.encrypt_in_place_detached(&NONCE, b"", black_box(&mut buf)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_decrypt(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aes256gcm-decrypt");
+    for size in SIZES {
+        let ciphertext = aead::encrypt(&KEY, &NONCE, &vec![0u8; size], b"").unwrap();
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("one-shot", size), &ciphertext, |b, ct| {
+            b.iter(|| aead::decrypt(&KEY, &NONCE, black_box(ct), b"").unwrap())
+        });
+
+        let ctx = AeadContext::<Aes256GcmSuite>::new(&KEY).unwrap();
+        let (body, tag) = ciphertext.split_at(size);
+        let mut buf = body.to_vec();
+        group.bench_function(BenchmarkId::new("context-in-place-detached", size), |b| {
+            b.iter(|| {
+                // decrypting in place turns `buf` into plaintext, so restore it each round
+                buf.copy_from_slice(body);
+                ctx.decrypt_in_place_detached(&NONCE, b"", black_box(&mut buf), tag).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encrypt, bench_decrypt);
+criterion_main!(benches);
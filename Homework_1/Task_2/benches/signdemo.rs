@@ -0,0 +1,33 @@
+//! Compare `signdemo::verify_batch` with calling `signdemo::verify` once per signature.
+//! Run with `cargo bench --bench signdemo`.
+
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use Task_2::crypto::signdemo::{self, keygen, sign};
+
+const BATCH_SIZES: [usize; 4] = [16, 64, 256, 1024];
+
+fn bench_verify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ed25519-verify");
+    for n in BATCH_SIZES {
+        let signed: Vec<_> = (0..n).map(|i| {
+            let kp = keygen();
+            let msg = format!("audit log entry {i}").into_bytes();
+            let sig = sign(&kp.sk, &msg);
+            (kp.pk, msg, sig)
+        }).collect();
+        let items: Vec<_> = signed.iter().map(|(pk, msg, sig)| (*pk, msg.as_slice(), *sig)).collect();
+        group.throughput(Throughput::Elements(n as u64));
+
+        group.bench_with_input(BenchmarkId::new("single", n), &items, |b, items| {
+            b.iter(|| items.iter().all(|(pk, msg, sig)| signdemo::verify(pk, black_box(msg), sig)))
+        });
+
+        group.bench_with_input(BenchmarkId::new("batch", n), &items, |b, items| {
+            b.iter(|| signdemo::verify_batch(black_box(items)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_verify);
+criterion_main!(benches);
@@ -0,0 +1,19 @@
+//! `cargo +nightly fuzz run wire` from Task_2: every handshake parser on arbitrary bytes.
+//! Parsing must never panic, and whatever parses must encode back to the same bytes.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use Task_2::protocol::handshake::Message;
+use Task_2::protocol::wire;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = Message::from_bytes(data) {
+        assert_eq!(message.to_bytes().unwrap(), data);
+    }
+    if let Ok((cert, finished)) = wire::decode_server_flight(data) {
+        assert_eq!(wire::encode_server_flight(&cert, &finished).unwrap(), data);
+    }
+    if let Ok(finished) = wire::decode_client_finished(data) {
+        assert_eq!(wire::encode_client_finished(&finished).unwrap(), data);
+    }
+});
@@ -0,0 +1,59 @@
+//! Handshake client over TCP.
+//!
+//!   hs-client [--connect ADDR] [--ca FILE] [--server-name NAME] [--timeout SECS] [--message TEXT]
+//!
+//! Trusts server certificate chains leading to a root in FILE (`ca.pem` from `hs-server keygen`)
+//! and issued for NAME (default localhost). After the handshake it sends TEXT over the record
+//! layer, closes its side and prints the server's echo.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use Task_2::crypto::aead::CipherSuite;
+use Task_2::protocol::handshake::ClientConfig;
+use Task_2::protocol::record::{Role, SecureChannel};
+use Task_2::protocol::{keyfile, transport};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:4433";
+const DEFAULT_NAME: &str = "localhost";
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+fn main() -> Result<()> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut ca = Path::new("keys").join("ca.pem");
+    let mut server_name = DEFAULT_NAME.to_string();
+    let mut timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECS);
+    let mut message = "hello".to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().with_context(|| format!("{arg} needs a value"))?;
+        match arg.as_str() {
+            "--connect" => addr = value,
+            "--ca" => ca = value.into(),
+            "--server-name" => server_name = value,
+            "--message" => message = value,
+            "--timeout" => timeout = Duration::from_secs(value.parse().context("--timeout takes whole seconds")?),
+            _ => bail!("unknown argument '{arg}'"),
+        }
+    }
+
+    let ca_pem = fs::read_to_string(&ca).with_context(|| format!("reading {}", ca.display()))?;
+    let config = ClientConfig {
+        trust_store: keyfile::trust_store_from_pem(&ca_pem)?,
+        server_name,
+        cipher_suites: vec![CipherSuite::ChaCha20Poly1305, CipherSuite::Aes256Gcm],
+    };
+    let (stream, keys) = transport::connect(addr.as_str(), config, timeout).with_context(|| format!("handshake with {addr}"))?;
+    println!("handshake with {} complete, cipher suite {}", stream.peer_addr()?, keys.suite.name());
+
+    let mut channel = SecureChannel::new(stream, keys, Role::Client)?;
+    channel.write_all(message.as_bytes())?;
+    channel.close()?;
+    let mut echo = String::new();
+    channel.read_to_string(&mut echo).context("reading the echo")?;
+    println!("echo: {echo}");
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Handshake server over TCP.
+//!
+//!   hs-server [--listen ADDR] [--cert FILE] [--key FILE] [--timeout SECS] [--once]
+//!   hs-server keygen [--dir DIR] [--scheme NAME] [--name SERVER_NAME]
+//!
+//! `keygen` creates an X.509 root CA and a server certificate for SERVER_NAME (default localhost)
+//! in DIR: ca.pem (the clients' trust store), ca.key, server.pem and server.key.
+//!
+//! The server answers one connection at a time: after the handshake it reads application data
+//! until the client's close_notify, echoes it back and closes. `--once` exits after the first
+//! connection.
+
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use Task_2::crypto::aead::CipherSuite;
+use Task_2::crypto::signature_scheme::SignatureAlgorithm;
+use Task_2::protocol::ca::CertificateAuthority;
+use Task_2::protocol::handshake::{ServerConfig, TrafficKeys};
+use Task_2::protocol::record::{Role, SecureChannel};
+use Task_2::protocol::{keyfile, transport};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:4433";
+const DEFAULT_DIR: &str = "keys";
+const DEFAULT_NAME: &str = "localhost";
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("keygen") {
+        args.next();
+        return keygen(args);
+    }
+
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut cert = Path::new(DEFAULT_DIR).join("server.pem");
+    let mut key = Path::new(DEFAULT_DIR).join("server.key");
+    let mut timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECS);
+    let mut once = false;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--listen" => addr = value()?,
+            "--cert" => cert = value()?.into(),
+            "--key" => key = value()?.into(),
+            "--timeout" => timeout = Duration::from_secs(value()?.parse().context("--timeout takes whole seconds")?),
+            "--once" => once = true,
+            _ => bail!("unknown argument '{arg}'"),
+        }
+    }
+
+    let cert_pem = fs::read_to_string(&cert).with_context(|| format!("reading {}", cert.display()))?;
+    let key_pem = fs::read_to_string(&key).with_context(|| format!("reading {}", key.display()))?;
+    let (certificate_chain, signature_algorithm, signing_key) = keyfile::server_credentials_from_pem(&cert_pem, &key_pem)?;
+    let config = ServerConfig { certificate_chain, signature_algorithm, signing_key, cipher_suites: CipherSuite::ALL.to_vec() };
+
+    let listener = TcpListener::bind(&addr).with_context(|| format!("binding {addr}"))?;
+    println!("listening on {}", listener.local_addr()?);
+    loop {
+        let result = transport::accept(&listener, config.clone(), timeout).context("handshake failed").and_then(|(stream, keys)| echo(stream, keys));
+        match result {
+            Ok(()) => {}
+            Err(e) if once => return Err(e),
+            Err(e) => eprintln!("{e:#}"),
+        }
+        if once {
+            return Ok(());
+        }
+    }
+}
+
+fn echo(stream: TcpStream, keys: TrafficKeys) -> Result<()> {
+    println!("handshake with {} complete, cipher suite {}", stream.peer_addr()?, keys.suite.name());
+    let mut channel = SecureChannel::new(stream, keys, Role::Server)?;
+    let mut data = Vec::new();
+    channel.read_to_end(&mut data).context("reading application data")?;
+    println!("received {} bytes", data.len());
+    channel.write_all(&data)?;
+    Ok(channel.close()?)
+}
+
+fn keygen(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let mut dir = PathBuf::from(DEFAULT_DIR);
+    let mut scheme = SignatureAlgorithm::Ed25519;
+    let mut name = DEFAULT_NAME.to_string();
+    while let Some(arg) = args.next() {
+        let value = args.next().with_context(|| format!("{arg} needs a value"))?;
+        match arg.as_str() {
+            "--dir" => dir = value.into(),
+            "--scheme" => scheme = SignatureAlgorithm::from_name(&value).with_context(|| format!("unknown signature scheme '{value}'"))?,
+            "--name" => name = value,
+            _ => bail!("unknown argument '{arg}'"),
+        }
+    }
+
+    let ca = CertificateAuthority::generate("hs-server CA")?;
+    let (certificate, key) = ca.issue_server(&name, scheme).with_context(|| format!("{scheme} keys cannot be certified"))?;
+
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    write_new(&dir.join("ca.pem"), &keyfile::certificates_to_pem(&[ca.certificate().to_vec()]), false)?;
+    write_new(&dir.join("ca.key"), &keyfile::private_key_to_pem(ca.key().algorithm(), ca.key().signing_key()), true)?;
+    write_new(&dir.join("server.pem"), &keyfile::certificates_to_pem(&[certificate]), false)?;
+    write_new(&dir.join("server.key"), &keyfile::private_key_to_pem(scheme, key.signing_key()), true)?;
+    println!("wrote CA and {scheme} server credentials for {name} to {}", dir.display());
+    Ok(())
+}
+
+/// Refuses to overwrite, so an existing CA is never replaced by accident.
+fn write_new(path: &Path, contents: &str, private: bool) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).with_context(|| format!("creating {}", path.display()))?;
+    Ok(file.write_all(contents.as_bytes())?)
+}
@@ -1,5 +1,7 @@
-use aes_gcm::{Aes256Gcm, KeyInit, aead::{Aead, Payload, Error}};
-use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::{Aes256Gcm, KeyInit, aead::{Aead, AeadCore, AeadInPlace, Buffer, KeySizeUser, Payload, Error, Tag}};
+use aes_gcm::aead::generic_array::typenum::Unsigned;
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
 
 /// 32-byte AES-256 key
 pub type Key = [u8; 32];
@@ -10,17 +12,293 @@ pub type Nonce = [u8; 12];
 /// authenticating `ad` as associated data.
 /// Returns: ciphertext || tag (the tag is appended by the library).
 pub fn encrypt(key: &Key, nonce: &Nonce, plaintext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
-    let cipher = Aes256Gcm::new(GenericArray::from_slice(key));
-    let n = GenericArray::from_slice(nonce);
-    cipher.encrypt(n, Payload { msg: plaintext, aad: ad }) // returns Vec<u8>
+    Aes256GcmSuite::encrypt(key, nonce, plaintext, ad)
 }
 
 /// Decrypts AES-256-GCM using `key`, `nonce`, and `ad`.
 /// Returns plaintext on success; on any tampering / mismatch it returns an error.
 pub fn decrypt(key: &Key, nonce: &Nonce, ciphertext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
-    let cipher = Aes256Gcm::new(GenericArray::from_slice(key));
-    let nonce_ga = GenericArray::from_slice(nonce);
-    cipher.decrypt(nonce_ga, Payload { msg: ciphertext, aad: ad })
+    Aes256GcmSuite::decrypt(key, nonce, ciphertext, ad)
+}
+
+/// An AEAD algorithm with fixed key/nonce/tag sizes.
+/// All operations take keys and nonces as slices and return `Error` if their length is wrong,
+/// so callers that pick a suite at runtime do not need per-algorithm array types.
+pub trait AeadSuite {
+    /// The RustCrypto cipher backing this suite.
+    type Cipher: KeyInit + AeadInPlace;
+
+    /// Which `CipherSuite` this is.
+    const SUITE: CipherSuite;
+    const KEY_LEN: usize = <<Self::Cipher as KeySizeUser>::KeySize as Unsigned>::USIZE;
+    const NONCE_LEN: usize = <<Self::Cipher as AeadCore>::NonceSize as Unsigned>::USIZE;
+    const TAG_LEN: usize = <<Self::Cipher as AeadCore>::TagSize as Unsigned>::USIZE;
+
+    /// Run the key schedule for `key`.
+    fn cipher(key: &[u8]) -> Result<Self::Cipher, Error> {
+        Self::Cipher::new_from_slice(key).map_err(|_| Error)
+    }
+
+    /// Returns: ciphertext || tag
+    fn encrypt(key: &[u8], nonce: &[u8], plaintext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
+        check_len(nonce, Self::NONCE_LEN)?;
+        Self::cipher(key)?.encrypt(nonce.into(), Payload { msg: plaintext, aad: ad })
+    }
+
+    /// Expects ciphertext || tag, returns the plaintext.
+    fn decrypt(key: &[u8], nonce: &[u8], ciphertext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
+        check_len(nonce, Self::NONCE_LEN)?;
+        Self::cipher(key)?.decrypt(nonce.into(), Payload { msg: ciphertext, aad: ad })
+    }
+
+    /// Encrypts `buffer` in place and appends the tag to it.
+    fn encrypt_in_place(key: &[u8], nonce: &[u8], ad: &[u8], buffer: &mut Vec<u8>) -> Result<(), Error> {
+        check_len(nonce, Self::NONCE_LEN)?;
+        Self::cipher(key)?.encrypt_in_place(nonce.into(), ad, buffer)
+    }
+
+    /// Verifies and strips the trailing tag, then decrypts `buffer` in place.
+    /// On failure `buffer` is left unchanged.
+    fn decrypt_in_place(key: &[u8], nonce: &[u8], ad: &[u8], buffer: &mut Vec<u8>) -> Result<(), Error> {
+        check_len(nonce, Self::NONCE_LEN)?;
+        Self::cipher(key)?.decrypt_in_place(nonce.into(), ad, buffer)
+    }
+}
+
+fn check_len(bytes: &[u8], expected: usize) -> Result<(), Error> {
+    if bytes.len() == expected { Ok(()) } else { Err(Error) }
+}
+
+/// A suite with its key schedule already run, for encrypting many messages under one key
+/// without re-expanding the key or allocating per message.
+/// The `*_in_place` methods take any `aead::Buffer`, e.g. `Vec<u8>` or `aead::heapless::Vec<u8, N>`.
+pub struct AeadContext<S: AeadSuite> {
+    cipher: S::Cipher,
+}
+
+impl<S: AeadSuite> AeadContext<S> {
+    pub fn new(key: &[u8]) -> Result<Self, Error> {
+        Ok(Self { cipher: S::cipher(key)? })
+    }
+
+    /// Encrypts `buffer` in place and returns the tag separately.
+    pub fn encrypt_in_place_detached(&self, nonce: &[u8], ad: &[u8], buffer: &mut [u8]) -> Result<Tag<S::Cipher>, Error> {
+        check_len(nonce, S::NONCE_LEN)?;
+        self.cipher.encrypt_in_place_detached(nonce.into(), ad, buffer)
+    }
+
+    /// Verifies `tag` and decrypts `buffer` in place. On failure `buffer` is left unchanged.
+    pub fn decrypt_in_place_detached(&self, nonce: &[u8], ad: &[u8], buffer: &mut [u8], tag: &[u8]) -> Result<(), Error> {
+        check_len(nonce, S::NONCE_LEN)?;
+        check_len(tag, S::TAG_LEN)?;
+        self.cipher.decrypt_in_place_detached(nonce.into(), ad, buffer, tag.into())
+    }
+
+    /// Encrypts `buffer` in place and appends the tag; fails if a fixed-capacity buffer is full.
+    pub fn encrypt_in_place(&self, nonce: &[u8], ad: &[u8], buffer: &mut dyn Buffer) -> Result<(), Error> {
+        check_len(nonce, S::NONCE_LEN)?;
+        self.cipher.encrypt_in_place(nonce.into(), ad, buffer)
+    }
+
+    /// Verifies and strips the trailing tag, then decrypts `buffer` in place.
+    pub fn decrypt_in_place(&self, nonce: &[u8], ad: &[u8], buffer: &mut dyn Buffer) -> Result<(), Error> {
+        check_len(nonce, S::NONCE_LEN)?;
+        self.cipher.decrypt_in_place(nonce.into(), ad, buffer)
+    }
+
+    /// Returns: ciphertext || tag
+    pub fn encrypt(&self, nonce: &[u8], plaintext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
+        check_len(nonce, S::NONCE_LEN)?;
+        self.cipher.encrypt(nonce.into(), Payload { msg: plaintext, aad: ad })
+    }
+
+    pub fn decrypt(&self, nonce: &[u8], ciphertext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
+        check_len(nonce, S::NONCE_LEN)?;
+        self.cipher.decrypt(nonce.into(), Payload { msg: ciphertext, aad: ad })
+    }
+}
+
+/// AES-256-GCM (96-bit nonce)
+pub struct Aes256GcmSuite;
+/// ChaCha20-Poly1305 as in RFC 8439 (96-bit nonce)
+pub struct ChaCha20Poly1305Suite;
+/// XChaCha20-Poly1305 (192-bit nonce, safe to pick at random)
+pub struct XChaCha20Poly1305Suite;
+/// AES-256-GCM-SIV as in RFC 8452; a repeated nonce only reveals repeated messages
+pub struct Aes256GcmSivSuite;
+
+impl AeadSuite for Aes256GcmSuite {
+    type Cipher = Aes256Gcm;
+    const SUITE: CipherSuite = CipherSuite::Aes256Gcm;
+}
+
+impl AeadSuite for ChaCha20Poly1305Suite {
+    type Cipher = ChaCha20Poly1305;
+    const SUITE: CipherSuite = CipherSuite::ChaCha20Poly1305;
+}
+
+impl AeadSuite for XChaCha20Poly1305Suite {
+    type Cipher = XChaCha20Poly1305;
+    const SUITE: CipherSuite = CipherSuite::XChaCha20Poly1305;
+}
+
+impl AeadSuite for Aes256GcmSivSuite {
+    type Cipher = Aes256GcmSiv;
+    const SUITE: CipherSuite = CipherSuite::Aes256GcmSiv;
+}
+
+/// Runtime selector for an `AeadSuite`, e.g. when the two parties negotiate the cipher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CipherSuite {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+    XChaCha20Poly1305,
+    Aes256GcmSiv,
+}
+
+/// Dispatch `$body` with `S` bound to the `AeadSuite` type of `$suite`.
+macro_rules! with_suite {
+    ($suite:expr, $s:ident => $body:expr) => {
+        match $suite {
+            CipherSuite::Aes256Gcm => { type $s = Aes256GcmSuite; $body }
+            CipherSuite::ChaCha20Poly1305 => { type $s = ChaCha20Poly1305Suite; $body }
+            CipherSuite::XChaCha20Poly1305 => { type $s = XChaCha20Poly1305Suite; $body }
+            CipherSuite::Aes256GcmSiv => { type $s = Aes256GcmSivSuite; $body }
+        }
+    };
+}
+
+impl CipherSuite {
+    /// All suites, in our order of preference.
+    pub const ALL: [CipherSuite; 4] = [
+        CipherSuite::Aes256Gcm,
+        CipherSuite::ChaCha20Poly1305,
+        CipherSuite::XChaCha20Poly1305,
+        CipherSuite::Aes256GcmSiv,
+    ];
+
+    /// 2-byte identifier used on the wire.
+    pub fn id(self) -> u16 {
+        match self {
+            CipherSuite::Aes256Gcm => 0x0001,
+            CipherSuite::ChaCha20Poly1305 => 0x0002,
+            CipherSuite::XChaCha20Poly1305 => 0x0003,
+            CipherSuite::Aes256GcmSiv => 0x0004,
+        }
+    }
+
+    pub fn from_id(id: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.id() == id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CipherSuite::Aes256Gcm => "AES-256-GCM",
+            CipherSuite::ChaCha20Poly1305 => "ChaCha20-Poly1305",
+            CipherSuite::XChaCha20Poly1305 => "XChaCha20-Poly1305",
+            CipherSuite::Aes256GcmSiv => "AES-256-GCM-SIV",
+        }
+    }
+
+    pub fn key_len(self) -> usize {
+        with_suite!(self, S => S::KEY_LEN)
+    }
+
+    pub fn nonce_len(self) -> usize {
+        with_suite!(self, S => S::NONCE_LEN)
+    }
+
+    pub fn tag_len(self) -> usize {
+        with_suite!(self, S => S::TAG_LEN)
+    }
+
+    pub fn encrypt(self, key: &[u8], nonce: &[u8], plaintext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
+        with_suite!(self, S => S::encrypt(key, nonce, plaintext, ad))
+    }
+
+    pub fn decrypt(self, key: &[u8], nonce: &[u8], ciphertext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
+        with_suite!(self, S => S::decrypt(key, nonce, ciphertext, ad))
+    }
+
+    pub fn encrypt_in_place(self, key: &[u8], nonce: &[u8], ad: &[u8], buffer: &mut Vec<u8>) -> Result<(), Error> {
+        with_suite!(self, S => S::encrypt_in_place(key, nonce, ad, buffer))
+    }
+
+    pub fn decrypt_in_place(self, key: &[u8], nonce: &[u8], ad: &[u8], buffer: &mut Vec<u8>) -> Result<(), Error> {
+        with_suite!(self, S => S::decrypt_in_place(key, nonce, ad, buffer))
+    }
+
+    /// Pick the first suite in `preferred` (our order) that the peer also `offered`.
+    pub fn negotiate(offered: &[CipherSuite], preferred: &[CipherSuite]) -> Option<CipherSuite> {
+        preferred.iter().copied().find(|s| offered.contains(s))
+    }
+}
+
+/// `AeadContext` for a suite chosen at runtime.
+pub enum CipherContext {
+    Aes256Gcm(AeadContext<Aes256GcmSuite>),
+    ChaCha20Poly1305(AeadContext<ChaCha20Poly1305Suite>),
+    XChaCha20Poly1305(AeadContext<XChaCha20Poly1305Suite>),
+    Aes256GcmSiv(AeadContext<Aes256GcmSivSuite>),
+}
+
+/// Dispatch `$body` with `$c` bound to the inner `AeadContext`.
+macro_rules! with_context {
+    ($ctx:expr, $c:ident => $body:expr) => {
+        match $ctx {
+            CipherContext::Aes256Gcm($c) => $body,
+            CipherContext::ChaCha20Poly1305($c) => $body,
+            CipherContext::XChaCha20Poly1305($c) => $body,
+            CipherContext::Aes256GcmSiv($c) => $body,
+        }
+    };
+}
+
+impl CipherContext {
+    pub fn new(suite: CipherSuite, key: &[u8]) -> Result<Self, Error> {
+        Ok(match suite {
+            CipherSuite::Aes256Gcm => CipherContext::Aes256Gcm(AeadContext::new(key)?),
+            CipherSuite::ChaCha20Poly1305 => CipherContext::ChaCha20Poly1305(AeadContext::new(key)?),
+            CipherSuite::XChaCha20Poly1305 => CipherContext::XChaCha20Poly1305(AeadContext::new(key)?),
+            CipherSuite::Aes256GcmSiv => CipherContext::Aes256GcmSiv(AeadContext::new(key)?),
+        })
+    }
+
+    pub fn suite(&self) -> CipherSuite {
+        match self {
+            CipherContext::Aes256Gcm(_) => CipherSuite::Aes256Gcm,
+            CipherContext::ChaCha20Poly1305(_) => CipherSuite::ChaCha20Poly1305,
+            CipherContext::XChaCha20Poly1305(_) => CipherSuite::XChaCha20Poly1305,
+            CipherContext::Aes256GcmSiv(_) => CipherSuite::Aes256GcmSiv,
+        }
+    }
+
+    /// Encrypts `buffer` in place and writes the tag into `tag` (which must be `tag_len` bytes).
+    pub fn encrypt_in_place_detached(&self, nonce: &[u8], ad: &[u8], buffer: &mut [u8], tag: &mut [u8]) -> Result<(), Error> {
+        check_len(tag, self.suite().tag_len())?;
+        with_context!(self, c => tag.copy_from_slice(c.encrypt_in_place_detached(nonce, ad, buffer)?.as_slice()));
+        Ok(())
+    }
+
+    pub fn decrypt_in_place_detached(&self, nonce: &[u8], ad: &[u8], buffer: &mut [u8], tag: &[u8]) -> Result<(), Error> {
+        with_context!(self, c => c.decrypt_in_place_detached(nonce, ad, buffer, tag))
+    }
+
+    pub fn encrypt_in_place(&self, nonce: &[u8], ad: &[u8], buffer: &mut dyn Buffer) -> Result<(), Error> {
+        with_context!(self, c => c.encrypt_in_place(nonce, ad, buffer))
+    }
+
+    pub fn decrypt_in_place(&self, nonce: &[u8], ad: &[u8], buffer: &mut dyn Buffer) -> Result<(), Error> {
+        with_context!(self, c => c.decrypt_in_place(nonce, ad, buffer))
+    }
+
+    pub fn encrypt(&self, nonce: &[u8], plaintext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
+        with_context!(self, c => c.encrypt(nonce, plaintext, ad))
+    }
+
+    pub fn decrypt(&self, nonce: &[u8], ciphertext: &[u8], ad: &[u8]) -> Result<Vec<u8>, Error> {
+        with_context!(self, c => c.decrypt(nonce, ciphertext, ad))
+    }
 }
 
 #[cfg(test)]
@@ -63,4 +341,89 @@ mod tests {
         let ct = encrypt(&key, &nonce, pt, b"AD1").unwrap();
         assert!(decrypt(&key, &nonce, &ct, b"AD2").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn every_suite_roundtrips_and_detects_tampering() {
+        for suite in CipherSuite::ALL {
+            let mut key = vec![0u8; suite.key_len()]; OsRng.fill_bytes(&mut key);
+            let mut nonce = vec![0u8; suite.nonce_len()]; OsRng.fill_bytes(&mut nonce);
+
+            let mut ct = suite.encrypt(&key, &nonce, b"hello suite", b"ad").unwrap();
+            assert_eq!(ct.len(), b"hello suite".len() + suite.tag_len());
+            assert_eq!(suite.decrypt(&key, &nonce, &ct, b"ad").unwrap(), b"hello suite");
+
+            let mut buf = b"in place".to_vec();
+            suite.encrypt_in_place(&key, &nonce, b"ad", &mut buf).unwrap();
+            suite.decrypt_in_place(&key, &nonce, b"ad", &mut buf).unwrap();
+            assert_eq!(buf, b"in place");
+
+            ct[0] ^= 0x01;
+            assert!(suite.decrypt(&key, &nonce, &ct, b"ad").is_err(), "{}", suite.name());
+        }
+    }
+
+    #[test]
+    fn suites_reject_wrong_key_or_nonce_length() {
+        assert_eq!(XChaCha20Poly1305Suite::NONCE_LEN, 24);
+        assert!(XChaCha20Poly1305Suite::encrypt(&[0u8; 32], &[0u8; 12], b"m", b"").is_err());
+        assert!(ChaCha20Poly1305Suite::encrypt(&[0u8; 16], &[0u8; 12], b"m", b"").is_err());
+    }
+
+    #[test]
+    fn suite_ids_roundtrip_and_negotiation() {
+        for suite in CipherSuite::ALL {
+            assert_eq!(CipherSuite::from_id(suite.id()), Some(suite));
+        }
+        assert_eq!(CipherSuite::from_id(0xffff), None);
+
+        let offered = [CipherSuite::XChaCha20Poly1305, CipherSuite::ChaCha20Poly1305];
+        assert_eq!(CipherSuite::negotiate(&offered, &CipherSuite::ALL), Some(CipherSuite::ChaCha20Poly1305));
+        assert_eq!(CipherSuite::negotiate(&offered, &[CipherSuite::Aes256Gcm]), None);
+    }
+
+    #[test]
+    fn context_detached_matches_one_shot() {
+        let key = [3u8; 32];
+        let nonce = [9u8; 12];
+        let ctx = AeadContext::<Aes256GcmSuite>::new(&key).unwrap();
+
+        let mut buf = *b"detached tag message";
+        let tag = ctx.encrypt_in_place_detached(&nonce, b"ad", &mut buf).unwrap();
+        let one_shot = encrypt(&key, &nonce, b"detached tag message", b"ad").unwrap();
+        assert_eq!([&buf[..], &tag[..]].concat(), one_shot);
+
+        let mut bad_tag = tag;
+        bad_tag[0] ^= 1;
+        assert!(ctx.decrypt_in_place_detached(&nonce, b"ad", &mut buf, &bad_tag).is_err());
+        ctx.decrypt_in_place_detached(&nonce, b"ad", &mut buf, &tag).unwrap();
+        assert_eq!(&buf, b"detached tag message");
+    }
+
+    #[test]
+    fn context_works_with_heapless_buffers() {
+        use aes_gcm::aead::heapless::Vec as HVec;
+
+        for suite in CipherSuite::ALL {
+            let key = vec![1u8; suite.key_len()];
+            let nonce = vec![2u8; suite.nonce_len()];
+            let ctx = CipherContext::new(suite, &key).unwrap();
+            assert_eq!(ctx.suite(), suite);
+
+            let mut buf: HVec<u8, 64> = HVec::from_slice(b"no heap here").unwrap();
+            ctx.encrypt_in_place(&nonce, b"", &mut buf).unwrap();
+            assert_eq!(buf.len(), 12 + suite.tag_len());
+            ctx.decrypt_in_place(&nonce, b"", &mut buf).unwrap();
+            assert_eq!(&buf[..], b"no heap here");
+
+            // not enough room for the tag
+            let mut full: HVec<u8, 12> = HVec::from_slice(b"no heap here").unwrap();
+            assert!(ctx.encrypt_in_place(&nonce, b"", &mut full).is_err());
+
+            let mut detached = *b"record";
+            let mut tag = vec![0u8; suite.tag_len()];
+            ctx.encrypt_in_place_detached(&nonce, b"", &mut detached, &mut tag).unwrap();
+            ctx.decrypt_in_place_detached(&nonce, b"", &mut detached, &tag).unwrap();
+            assert_eq!(&detached, b"record");
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! AEAD keys that own their nonce generation.
+//! - `CounterNonces`: nonce = static IV XOR big-endian message counter (TLS 1.3, RFC 8446 §5.3).
+//!   The receiver derives the same nonce, so nothing is transmitted.
+//! - `RandomNonces`: a fresh random nonce per message; it has to travel with the ciphertext (see `seal_envelope`).
+//!
+//! Both keys count messages and refuse to go past the configured usage limit or let the counter wrap.
+
+use std::fmt;
+
+use rand::{RngCore, rngs::OsRng};
+
+use super::aead::{CipherContext, CipherSuite};
+use super::envelope::Envelope;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyError {
+    /// Key or IV length does not match the suite.
+    BadLength,
+    /// The 64-bit message counter would wrap around.
+    CounterExhausted,
+    /// The key has protected as many messages as it is allowed to.
+    UsageLimitReached,
+    Encryption,
+    Decryption,
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::BadLength => write!(f, "key or IV has the wrong length for the cipher suite"),
+            KeyError::CounterExhausted => write!(f, "nonce counter exhausted"),
+            KeyError::UsageLimitReached => write!(f, "key usage limit reached, rekey required"),
+            KeyError::Encryption => write!(f, "encryption failed"),
+            KeyError::Decryption => write!(f, "decryption failed"),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+/// Source of per-message nonces for one key.
+pub trait NonceSequence {
+    /// Produce the nonce for the next message.
+    fn advance(&mut self) -> Result<Vec<u8>, KeyError>;
+    /// Largest number of messages this sequence can safely produce.
+    fn max_messages(&self) -> u64;
+}
+
+/// TLS 1.3 style nonces: the counter is left-padded to the IV length and XORed into it.
+pub struct CounterNonces {
+    iv: Vec<u8>,
+    counter: u64,
+}
+
+impl CounterNonces {
+    /// `iv` must be at least 8 bytes (normally the suite's nonce length).
+    pub fn new(iv: &[u8]) -> Result<Self, KeyError> {
+        if iv.len() < 8 {
+            return Err(KeyError::BadLength);
+        }
+        Ok(Self { iv: iv.to_vec(), counter: 0 })
+    }
+
+    /// Number of nonces handed out so far (= sequence number of the next message).
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+}
+
+impl NonceSequence for CounterNonces {
+    fn advance(&mut self) -> Result<Vec<u8>, KeyError> {
+        if self.counter == u64::MAX {
+            return Err(KeyError::CounterExhausted);
+        }
+        let mut nonce = self.iv.clone();
+        let offset = nonce.len() - 8;
+        for (n, c) in nonce[offset..].iter_mut().zip(self.counter.to_be_bytes()) {
+            *n ^= c;
+        }
+        self.counter += 1;
+        Ok(nonce)
+    }
+
+    fn max_messages(&self) -> u64 {
+        u64::MAX
+    }
+}
+
+/// Random nonces of the suite's length.
+pub struct RandomNonces {
+    len: usize,
+}
+
+impl RandomNonces {
+    pub fn new(suite: CipherSuite) -> Self {
+        Self { len: suite.nonce_len() }
+    }
+}
+
+impl NonceSequence for RandomNonces {
+    fn advance(&mut self) -> Result<Vec<u8>, KeyError> {
+        let mut nonce = vec![0u8; self.len];
+        OsRng.fill_bytes(&mut nonce);
+        Ok(nonce)
+    }
+
+    /// 96-bit random nonces are capped at 2^32 messages (NIST SP 800-38D §8.3);
+    /// 192-bit ones (XChaCha20) are only bounded by the counter.
+    fn max_messages(&self) -> u64 {
+        if self.len >= 24 { u64::MAX } else { 1 << 32 }
+    }
+}
+
+/// Encrypting half of an AEAD key.
+pub struct SealingKey<N: NonceSequence> {
+    ctx: CipherContext,
+    nonces: N,
+    limit: u64,
+    used: u64,
+}
+
+impl SealingKey<CounterNonces> {
+    /// Counter-based key with static `iv` of the suite's nonce length.
+    pub fn counter(suite: CipherSuite, key: &[u8], iv: &[u8]) -> Result<Self, KeyError> {
+        if iv.len() != suite.nonce_len() {
+            return Err(KeyError::BadLength);
+        }
+        Self::new(suite, key, CounterNonces::new(iv)?)
+    }
+
+    /// Returns ciphertext || tag. The nonce is not included; the peer's `OpeningKey` recomputes it.
+    pub fn seal(&mut self, plaintext: &[u8], ad: &[u8]) -> Result<Vec<u8>, KeyError> {
+        let nonce = self.next_nonce()?;
+        self.ctx.encrypt(&nonce, plaintext, ad).map_err(|_| KeyError::Encryption)
+    }
+
+    /// Encrypts `buffer` in place and writes the tag into `tag`, without allocating.
+    pub fn seal_in_place_detached(&mut self, ad: &[u8], buffer: &mut [u8], tag: &mut [u8]) -> Result<(), KeyError> {
+        let nonce = self.next_nonce()?;
+        self.ctx.encrypt_in_place_detached(&nonce, ad, buffer, tag).map_err(|_| KeyError::Encryption)
+    }
+}
+
+impl SealingKey<RandomNonces> {
+    /// Random nonces must travel with the ciphertext, so this key only seals envelopes:
+    ///
+    /// ```compile_fail
+    /// use Task_2::crypto::{aead::CipherSuite, aead_key::SealingKey};
+    /// let mut key = SealingKey::random(CipherSuite::XChaCha20Poly1305, &[0; 32]).unwrap();
+    /// key.seal(b"the nonce would be lost", b"");
+    /// ```
+    pub fn random(suite: CipherSuite, key: &[u8]) -> Result<Self, KeyError> {
+        Self::new(suite, key, RandomNonces::new(suite))
+    }
+}
+
+impl<N: NonceSequence> SealingKey<N> {
+    pub fn new(suite: CipherSuite, key: &[u8], nonces: N) -> Result<Self, KeyError> {
+        let ctx = CipherContext::new(suite, key).map_err(|_| KeyError::BadLength)?;
+        let limit = nonces.max_messages();
+        Ok(Self { ctx, nonces, limit, used: 0 })
+    }
+
+    /// Lower the number of messages this key may seal. Limits above what the nonce sequence allows are ignored.
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = limit.min(self.nonces.max_messages());
+        self
+    }
+
+    /// Messages sealed so far.
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    fn next_nonce(&mut self) -> Result<Vec<u8>, KeyError> {
+        if self.used >= self.limit {
+            return Err(KeyError::UsageLimitReached);
+        }
+        let nonce = self.nonces.advance()?;
+        self.used += 1;
+        Ok(nonce)
+    }
+
+    /// Seal into an `Envelope` that carries the nonce; open it with `envelope::open`.
+    pub fn seal_envelope(&mut self, key_id: &[u8], plaintext: &[u8], ad: &[u8]) -> Result<Envelope, KeyError> {
+        let nonce = self.next_nonce()?;
+        super::envelope::seal_with_context(&self.ctx, key_id, &nonce, plaintext, ad)
+            .map_err(|_| KeyError::Encryption)
+    }
+}
+
+/// Decrypting half of a counter-based AEAD key. Messages must be opened in the order they were sealed.
+pub struct OpeningKey {
+    ctx: CipherContext,
+    nonces: CounterNonces,
+    limit: u64,
+}
+
+impl OpeningKey {
+    pub fn counter(suite: CipherSuite, key: &[u8], iv: &[u8]) -> Result<Self, KeyError> {
+        if iv.len() != suite.nonce_len() {
+            return Err(KeyError::BadLength);
+        }
+        let ctx = CipherContext::new(suite, key).map_err(|_| KeyError::BadLength)?;
+        Ok(Self { ctx, nonces: CounterNonces::new(iv)?, limit: u64::MAX })
+    }
+
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sequence number of the next message expected.
+    pub fn sequence(&self) -> u64 {
+        self.nonces.counter()
+    }
+
+    /// Decrypt the next message. The counter only moves forward on success,
+    /// so a forged message does not desynchronise the two sides.
+    pub fn open(&mut self, ciphertext: &[u8], ad: &[u8]) -> Result<Vec<u8>, KeyError> {
+        if self.nonces.counter() >= self.limit {
+            return Err(KeyError::UsageLimitReached);
+        }
+        let saved = self.nonces.counter;
+        let nonce = self.nonces.advance()?;
+        self.ctx.decrypt(&nonce, ciphertext, ad).map_err(|_| {
+            self.nonces.counter = saved;
+            KeyError::Decryption
+        })
+    }
+
+    /// In-place counterpart of `open` for a detached tag.
+    pub fn open_in_place_detached(&mut self, ad: &[u8], buffer: &mut [u8], tag: &[u8]) -> Result<(), KeyError> {
+        if self.nonces.counter() >= self.limit {
+            return Err(KeyError::UsageLimitReached);
+        }
+        let saved = self.nonces.counter;
+        let nonce = self.nonces.advance()?;
+        self.ctx.decrypt_in_place_detached(&nonce, ad, buffer, tag).map_err(|_| {
+            self.nonces.counter = saved;
+            KeyError::Decryption
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::envelope;
+
+    const KEY: [u8; 32] = [7u8; 32];
+    const IV: [u8; 12] = [0xa5; 12];
+
+    #[test]
+    fn counter_nonce_is_iv_xor_sequence_number() {
+        let mut seq = CounterNonces::new(&IV).unwrap();
+        assert_eq!(seq.advance().unwrap(), IV.to_vec());
+        let second = seq.advance().unwrap();
+        assert_eq!(&second[..11], &IV[..11]);
+        assert_eq!(second[11], 0xa5 ^ 0x01);
+    }
+
+    #[test]
+    fn counter_keys_roundtrip_in_order() {
+        for suite in [CipherSuite::Aes256Gcm, CipherSuite::ChaCha20Poly1305] {
+            let mut tx = SealingKey::counter(suite, &KEY, &IV).unwrap();
+            let mut rx = OpeningKey::counter(suite, &KEY, &IV).unwrap();
+            let c0 = tx.seal(b"first", b"").unwrap();
+            let c1 = tx.seal(b"second", b"").unwrap();
+            assert_ne!(c0[..5], c1[..5]);
+
+            // out of order fails and does not advance the receiver
+            assert_eq!(rx.open(&c1, b""), Err(KeyError::Decryption));
+            assert_eq!(rx.sequence(), 0);
+            assert_eq!(rx.open(&c0, b"").unwrap(), b"first");
+            assert_eq!(rx.open(&c1, b"").unwrap(), b"second");
+            // replay fails
+            assert!(rx.open(&c1, b"").is_err());
+
+            let mut record = *b"third";
+            let mut tag = [0u8; 16];
+            tx.seal_in_place_detached(b"hdr", &mut record, &mut tag).unwrap();
+            rx.open_in_place_detached(b"hdr", &mut record, &tag).unwrap();
+            assert_eq!(&record, b"third");
+        }
+    }
+
+    #[test]
+    fn usage_limit_is_enforced() {
+        let mut tx = SealingKey::counter(CipherSuite::Aes256Gcm, &KEY, &IV).unwrap().with_limit(2);
+        tx.seal(b"1", b"").unwrap();
+        tx.seal(b"2", b"").unwrap();
+        assert_eq!(tx.seal(b"3", b""), Err(KeyError::UsageLimitReached));
+        assert_eq!(tx.used(), 2);
+
+        let tx = SealingKey::random(CipherSuite::Aes256Gcm, &KEY).unwrap().with_limit(u64::MAX);
+        assert_eq!(tx.limit, 1 << 32);
+    }
+
+    #[test]
+    fn counter_refuses_to_wrap() {
+        let mut seq = CounterNonces::new(&IV).unwrap();
+        seq.counter = u64::MAX - 1;
+        assert!(seq.advance().is_ok());
+        assert_eq!(seq.advance(), Err(KeyError::CounterExhausted));
+    }
+
+    #[test]
+    fn random_nonces_travel_in_envelope() {
+        let suite = CipherSuite::XChaCha20Poly1305;
+        let mut tx = SealingKey::random(suite, &KEY).unwrap();
+        let a = tx.seal_envelope(b"k", b"same", b"").unwrap();
+        let b = tx.seal_envelope(b"k", b"same", b"").unwrap();
+        assert_ne!(a.nonce, b.nonce);
+        assert_eq!(envelope::open(&a, &KEY, b"").unwrap(), b"same");
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        assert_eq!(SealingKey::counter(CipherSuite::Aes256Gcm, &KEY, &[0u8; 24]).err(), Some(KeyError::BadLength));
+        assert_eq!(SealingKey::random(CipherSuite::Aes256Gcm, &KEY[..16]).err(), Some(KeyError::BadLength));
+    }
+}
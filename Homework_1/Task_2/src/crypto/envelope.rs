@@ -0,0 +1,269 @@
+//! Self-describing container for AEAD output.
+//! Binary layout (all integers big-endian):
+//!   magic "ENVL" (4) | version (1) | suite id (2) | key id length (1) | key id
+//!   | nonce (suite nonce length) | ciphertext length (4) | ciphertext | tag (suite tag length)
+//! Everything up to and including the nonce is the header; it is authenticated as associated
+//! data together with the caller's `ad`, so the algorithm or key id cannot be swapped unnoticed.
+
+use std::fmt;
+
+use rand::{RngCore, rngs::OsRng};
+
+use super::aead::{CipherContext, CipherSuite};
+use crate::encode::encode_b64::{b64, from_b64};
+
+pub const MAGIC: [u8; 4] = *b"ENVL";
+pub const VERSION: u8 = 1;
+
+const ARMOR_BEGIN: &str = "-----BEGIN SEALED ENVELOPE-----";
+const ARMOR_END: &str = "-----END SEALED ENVELOPE-----";
+const ARMOR_WIDTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvelopeError {
+    /// Input does not start with `MAGIC`.
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnknownSuite(u16),
+    /// Input ended before the field that was being read.
+    Truncated,
+    /// Extra bytes after the tag.
+    TrailingData,
+    /// Key ids are limited to 255 bytes.
+    KeyIdTooLong,
+    /// Missing armor lines or invalid Base64.
+    BadArmor,
+    Encryption,
+    /// Wrong key, wrong associated data or modified envelope.
+    Decryption,
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeError::BadMagic => write!(f, "not a sealed envelope (bad magic)"),
+            EnvelopeError::UnsupportedVersion(v) => write!(f, "unsupported envelope version {v}"),
+            EnvelopeError::UnknownSuite(id) => write!(f, "unknown cipher suite 0x{id:04x}"),
+            EnvelopeError::Truncated => write!(f, "envelope is truncated"),
+            EnvelopeError::TrailingData => write!(f, "trailing bytes after envelope"),
+            EnvelopeError::KeyIdTooLong => write!(f, "key id longer than 255 bytes"),
+            EnvelopeError::BadArmor => write!(f, "malformed armored envelope"),
+            EnvelopeError::Encryption => write!(f, "encryption failed"),
+            EnvelopeError::Decryption => write!(f, "decryption failed"),
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+/// A sealed message together with everything needed to open it except the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub suite: CipherSuite,
+    /// Private so it stays within the 255 bytes its length byte can encode.
+    key_id: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub tag: Vec<u8>,
+}
+
+/// Encrypt `plaintext` under `key` with a fresh random nonce.
+/// `key_id` is stored in clear so the receiver can look up the key; `ad` is not stored.
+pub fn seal(suite: CipherSuite, key_id: &[u8], key: &[u8], plaintext: &[u8], ad: &[u8]) -> Result<Envelope, EnvelopeError> {
+    let ctx = CipherContext::new(suite, key).map_err(|_| EnvelopeError::Encryption)?;
+    let mut nonce = vec![0u8; suite.nonce_len()];
+    OsRng.fill_bytes(&mut nonce);
+    seal_with_context(&ctx, key_id, &nonce, plaintext, ad)
+}
+
+/// Like `seal`, but with an existing cipher context and a caller-chosen nonce
+/// (e.g. from an `aead_key::SealingKey`). The caller must never repeat a nonce under the same key.
+pub fn seal_with_context(ctx: &CipherContext, key_id: &[u8], nonce: &[u8], plaintext: &[u8], ad: &[u8]) -> Result<Envelope, EnvelopeError> {
+    let suite = ctx.suite();
+    if key_id.len() > u8::MAX as usize {
+        return Err(EnvelopeError::KeyIdTooLong);
+    }
+    if nonce.len() != suite.nonce_len() {
+        return Err(EnvelopeError::Encryption);
+    }
+
+    let mut env = Envelope { suite, key_id: key_id.to_vec(), nonce: nonce.to_vec(), ciphertext: plaintext.to_vec(), tag: vec![0u8; suite.tag_len()] };
+    let aad = [&env.header()[..], ad].concat();
+    ctx.encrypt_in_place_detached(nonce, &aad, &mut env.ciphertext, &mut env.tag)
+        .map_err(|_| EnvelopeError::Encryption)?;
+    Ok(env)
+}
+
+/// Decrypt an envelope produced by `seal` with the same `key` and `ad`.
+pub fn open(env: &Envelope, key: &[u8], ad: &[u8]) -> Result<Vec<u8>, EnvelopeError> {
+    let aad = [&env.header()[..], ad].concat();
+    let sealed = [&env.ciphertext[..], &env.tag[..]].concat();
+    env.suite.decrypt(key, &env.nonce, &sealed, &aad).map_err(|_| EnvelopeError::Decryption)
+}
+
+impl Envelope {
+    /// Stored in clear so the receiver can look up the key.
+    pub fn key_id(&self) -> &[u8] {
+        &self.key_id
+    }
+
+    /// magic | version | suite id | key id length | key id | nonce
+    fn header(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(8 + self.key_id.len() + self.nonce.len());
+        out.extend_from_slice(&MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.suite.id().to_be_bytes());
+        out.push(self.key_id.len() as u8);
+        out.extend_from_slice(&self.key_id);
+        out.extend_from_slice(&self.nonce);
+        out
+    }
+
+    /// Stable binary encoding (see module docs).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.header();
+        out.extend_from_slice(&(self.ciphertext.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.ciphertext);
+        out.extend_from_slice(&self.tag);
+        out
+    }
+
+    /// Parse the binary encoding. Rejects unknown versions/suites, short input and trailing bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        let mut r = Reader(bytes);
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err(EnvelopeError::BadMagic);
+        }
+        let version = r.take(1)?[0];
+        if version != VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(version));
+        }
+        let id = u16::from_be_bytes(r.take(2)?.try_into().unwrap());
+        let suite = CipherSuite::from_id(id).ok_or(EnvelopeError::UnknownSuite(id))?;
+        let key_id_len = r.take(1)?[0] as usize;
+        let key_id = r.take(key_id_len)?.to_vec();
+        let nonce = r.take(suite.nonce_len())?.to_vec();
+        let ct_len = u32::from_be_bytes(r.take(4)?.try_into().unwrap()) as usize;
+        let ciphertext = r.take(ct_len)?.to_vec();
+        let tag = r.take(suite.tag_len())?.to_vec();
+        if !r.0.is_empty() {
+            return Err(EnvelopeError::TrailingData);
+        }
+        Ok(Envelope { suite, key_id, nonce, ciphertext, tag })
+    }
+
+    /// PEM-like text form: Base64 of `to_bytes` wrapped at 64 columns between armor lines.
+    pub fn to_armored(&self) -> String {
+        let body = b64(&self.to_bytes());
+        let mut out = String::from(ARMOR_BEGIN);
+        out.push('\n');
+        for line in body.as_bytes().chunks(ARMOR_WIDTH) {
+            out.push_str(std::str::from_utf8(line).unwrap());
+            out.push('\n');
+        }
+        out.push_str(ARMOR_END);
+        out.push('\n');
+        out
+    }
+
+    pub fn from_armored(text: &str) -> Result<Self, EnvelopeError> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        if lines.next() != Some(ARMOR_BEGIN) {
+            return Err(EnvelopeError::BadArmor);
+        }
+        let mut body = String::new();
+        let mut ended = false;
+        for line in lines.by_ref() {
+            if line == ARMOR_END {
+                ended = true;
+                break;
+            }
+            body.push_str(line);
+        }
+        if !ended || lines.next().is_some() {
+            return Err(EnvelopeError::BadArmor);
+        }
+        let bytes = from_b64(&body).map_err(|_| EnvelopeError::BadArmor)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Cursor over the input that reports `Truncated` instead of panicking.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], EnvelopeError> {
+        if self.0.len() < n {
+            return Err(EnvelopeError::Truncated);
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_for(suite: CipherSuite) -> Vec<u8> {
+        let mut key = vec![0u8; suite.key_len()];
+        OsRng.fill_bytes(&mut key);
+        key
+    }
+
+    #[test]
+    fn seal_open_roundtrip_through_bytes_and_armor() {
+        for suite in CipherSuite::ALL {
+            let key = key_for(suite);
+            let env = seal(suite, b"server-k1", &key, b"hello envelope", b"ctx").unwrap();
+
+            let parsed = Envelope::from_bytes(&env.to_bytes()).unwrap();
+            assert_eq!(parsed, env);
+            assert_eq!(open(&parsed, &key, b"ctx").unwrap(), b"hello envelope");
+
+            let armored = env.to_armored();
+            assert!(armored.starts_with(ARMOR_BEGIN));
+            assert_eq!(Envelope::from_armored(&armored).unwrap(), env);
+        }
+    }
+
+    #[test]
+    fn header_is_authenticated() {
+        let key = key_for(CipherSuite::Aes256Gcm);
+        let mut env = seal(CipherSuite::Aes256Gcm, b"k1", &key, b"msg", b"").unwrap();
+        assert!(open(&env, &key, b"other ad").is_err());
+        env.key_id = b"k2".to_vec();
+        assert_eq!(open(&env, &key, b""), Err(EnvelopeError::Decryption));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let key = key_for(CipherSuite::ChaCha20Poly1305);
+        let bytes = seal(CipherSuite::ChaCha20Poly1305, b"k", &key, b"msg", b"").unwrap().to_bytes();
+
+        for cut in 0..bytes.len() {
+            assert_eq!(Envelope::from_bytes(&bytes[..cut]), Err(EnvelopeError::Truncated), "cut at {cut}");
+        }
+        let mut long = bytes.clone();
+        long.push(0);
+        assert_eq!(Envelope::from_bytes(&long), Err(EnvelopeError::TrailingData));
+
+        let mut v2 = bytes.clone();
+        v2[4] = 2;
+        assert_eq!(Envelope::from_bytes(&v2), Err(EnvelopeError::UnsupportedVersion(2)));
+
+        let mut alg = bytes.clone();
+        alg[5..7].copy_from_slice(&0xbeefu16.to_be_bytes());
+        assert_eq!(Envelope::from_bytes(&alg), Err(EnvelopeError::UnknownSuite(0xbeef)));
+
+        let mut magic = bytes;
+        magic[0] = b'X';
+        assert_eq!(Envelope::from_bytes(&magic), Err(EnvelopeError::BadMagic));
+
+        assert_eq!(Envelope::from_armored("hello"), Err(EnvelopeError::BadArmor));
+        assert_eq!(seal(CipherSuite::Aes256Gcm, &[0u8; 256], &key, b"", b""), Err(EnvelopeError::KeyIdTooLong));
+        let longest = seal(CipherSuite::Aes256Gcm, &[7u8; 255], &key_for(CipherSuite::Aes256Gcm), b"", b"").unwrap();
+        assert_eq!(Envelope::from_bytes(&longest.to_bytes()).unwrap().key_id(), &[7u8; 255]);
+    }
+}
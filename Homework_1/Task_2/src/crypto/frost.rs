@@ -0,0 +1,633 @@
+//! FROST(Ed25519, SHA-512) threshold signatures (RFC 9591): any `min_signers` of `max_signers`
+//! participants jointly produce a plain Ed25519 signature, which `signdemo::verify` accepts.
+//! - Key generation: `trusted_dealer_keygen`, or a dealer-free DKG (`dkg_round1`, `dkg_round2`,
+//!   `dkg_finish`; Pedersen VSS with proofs of knowledge, as in the FROST paper).
+//! - Signing: `commit` (round one), then `sign` over a `SigningPackage` (round two), then `aggregate`.
+//! - Identifiable abort: `aggregate` checks every signature share and names the participants whose
+//!   share is wrong; the DKG names the participant whose proof or secret share is wrong.
+//!
+//! Nonces are single-use: `sign` consumes them, so a round-one commitment can never sign twice.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{Identity, IsIdentity};
+use ed25519_dalek::{Signature, VerifyingKey};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha512};
+use zeroize::Zeroizing;
+
+const CONTEXT_STRING: &[u8] = b"FROST-ED25519-SHA512-v1";
+
+/// Participant index, 1..=max_signers.
+pub type Identifier = u16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrostError {
+    /// Needs 2 <= min_signers <= max_signers.
+    InvalidParameters,
+    /// Zero, unknown, or not the participant the input was meant for.
+    InvalidIdentifier(Identifier),
+    TooFewSigners { got: usize, min: usize },
+    /// The signer's nonces do not match its commitment in the signing package.
+    CommitmentMismatch,
+    /// No signature share from a participant of the signing package.
+    MissingShare(Identifier),
+    /// A hiding or binding commitment is the identity (RFC 9591 §4.1).
+    InvalidCommitment(Identifier),
+    /// Identifiable abort: these participants sent signature shares that do not verify.
+    InvalidSignatureShares { culprits: Vec<Identifier> },
+    /// DKG round one: this participant's commitment or proof of knowledge is invalid.
+    InvalidProofOfKnowledge { culprit: Identifier },
+    /// DKG round two: the secret share sent by this participant does not match its commitment.
+    InvalidSecretShare { culprit: Identifier },
+}
+
+impl fmt::Display for FrostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrostError::InvalidParameters => write!(f, "need 2 <= min_signers <= max_signers"),
+            FrostError::InvalidIdentifier(id) => write!(f, "invalid participant identifier {id}"),
+            FrostError::TooFewSigners { got, min } => write!(f, "{got} signers, at least {min} needed"),
+            FrostError::CommitmentMismatch => write!(f, "nonces do not match the signing package"),
+            FrostError::MissingShare(id) => write!(f, "no signature share from participant {id}"),
+            FrostError::InvalidCommitment(id) => write!(f, "identity commitment from participant {id}"),
+            FrostError::InvalidSignatureShares { culprits } => write!(f, "invalid signature shares from participants {culprits:?}"),
+            FrostError::InvalidProofOfKnowledge { culprit } => write!(f, "invalid DKG commitment from participant {culprit}"),
+            FrostError::InvalidSecretShare { culprit } => write!(f, "invalid DKG secret share from participant {culprit}"),
+        }
+    }
+}
+
+impl std::error::Error for FrostError {}
+
+/// One participant's long-term signing material.
+pub struct KeyPackage {
+    pub identifier: Identifier,
+    signing_share: Zeroizing<Scalar>,
+    pub verifying_share: EdwardsPoint,
+    pub group_public_key: VerifyingKey,
+    pub min_signers: usize,
+}
+
+/// What the aggregator needs: the group key and every participant's verifying share.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKeyPackage {
+    pub verifying_shares: BTreeMap<Identifier, EdwardsPoint>,
+    pub group_public_key: VerifyingKey,
+}
+
+/// Round-one secret of one signer; consumed by `sign`.
+pub struct SigningNonces {
+    hiding: Zeroizing<Scalar>,
+    binding: Zeroizing<Scalar>,
+    commitments: SigningCommitments,
+}
+
+/// Round-one message of one signer, sent to the coordinator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SigningCommitments {
+    pub hiding: EdwardsPoint,
+    pub binding: EdwardsPoint,
+}
+
+/// The message and the commitments of the chosen signers, sent by the coordinator to each of them.
+pub struct SigningPackage {
+    commitments: BTreeMap<Identifier, SigningCommitments>,
+    message: Vec<u8>,
+}
+
+/// Round-two message of one signer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SignatureShare(pub Scalar);
+
+impl SigningPackage {
+    /// Rejects identity commitments, like `SigningCommitments::from_bytes`.
+    pub fn new(commitments: BTreeMap<Identifier, SigningCommitments>, message: &[u8]) -> Result<Self, FrostError> {
+        if let Some((&id, _)) = commitments.iter().find(|(_, c)| c.hiding.is_identity() || c.binding.is_identity()) {
+            return Err(FrostError::InvalidCommitment(id));
+        }
+        Ok(Self { commitments, message: message.to_vec() })
+    }
+}
+
+/// Split a fresh random group key into `max_signers` shares, any `min_signers` of which can sign.
+pub fn trusted_dealer_keygen(
+    min_signers: usize,
+    max_signers: usize,
+) -> Result<(BTreeMap<Identifier, KeyPackage>, PublicKeyPackage), FrostError> {
+    check_parameters(min_signers, max_signers)?;
+    let coefficients: Vec<Zeroizing<Scalar>> = (0..min_signers).map(|_| Zeroizing::new(random_scalar())).collect();
+    Ok(split(&coefficients, min_signers, max_signers))
+}
+
+/// Shamir shares f(1), ..., f(max_signers) of f(x) = sum coefficients[j] x^j; f(0) is the group secret.
+fn split(
+    coefficients: &[Zeroizing<Scalar>],
+    min_signers: usize,
+    max_signers: usize,
+) -> (BTreeMap<Identifier, KeyPackage>, PublicKeyPackage) {
+    let group_public_key = verifying_key(EdwardsPoint::mul_base(&coefficients[0]));
+    let mut key_packages = BTreeMap::new();
+    let mut verifying_shares = BTreeMap::new();
+    for id in 1..=max_signers as Identifier {
+        let signing_share = Zeroizing::new(evaluate_polynomial(coefficients, id));
+        let verifying_share = EdwardsPoint::mul_base(&signing_share);
+        verifying_shares.insert(id, verifying_share);
+        key_packages.insert(id, KeyPackage { identifier: id, signing_share, verifying_share, group_public_key, min_signers });
+    }
+    (key_packages, PublicKeyPackage { verifying_shares, group_public_key })
+}
+
+/// DKG state a participant keeps between rounds one and two.
+pub struct DkgRound1Secret {
+    identifier: Identifier,
+    coefficients: Vec<Zeroizing<Scalar>>,
+    min_signers: usize,
+    max_signers: usize,
+}
+
+/// DKG round-one broadcast: commitments to the polynomial and a Schnorr proof of knowledge
+/// of its constant term, so nobody can choose their contribution as a function of others'.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DkgRound1Package {
+    pub commitment: Vec<EdwardsPoint>,
+    pub proof: (EdwardsPoint, Scalar),
+}
+
+/// DKG state a participant keeps between rounds two and three.
+pub struct DkgRound2Secret {
+    identifier: Identifier,
+    commitment: Vec<EdwardsPoint>,
+    own_share: Zeroizing<Scalar>,
+    min_signers: usize,
+    max_signers: usize,
+}
+
+/// DKG round-two message: the sender's share for one recipient; must travel over a private channel.
+pub struct DkgRound2Package {
+    pub signing_share: Zeroizing<Scalar>,
+}
+
+/// DKG round one for participant `identifier`: the result's package goes to everybody.
+pub fn dkg_round1(
+    identifier: Identifier,
+    min_signers: usize,
+    max_signers: usize,
+) -> Result<(DkgRound1Secret, DkgRound1Package), FrostError> {
+    check_parameters(min_signers, max_signers)?;
+    check_identifier(identifier, max_signers)?;
+    let coefficients: Vec<Zeroizing<Scalar>> = (0..min_signers).map(|_| Zeroizing::new(random_scalar())).collect();
+    let commitment: Vec<EdwardsPoint> = coefficients.iter().map(|a| EdwardsPoint::mul_base(a)).collect();
+
+    let k = Zeroizing::new(random_scalar());
+    let r = EdwardsPoint::mul_base(&k);
+    let c = dkg_challenge(identifier, &commitment[0], &r);
+    let proof = (r, *k + *coefficients[0] * c);
+    Ok((DkgRound1Secret { identifier, coefficients, min_signers, max_signers }, DkgRound1Package { commitment, proof }))
+}
+
+/// DKG round two: checks the other participants' round-one packages and returns one private
+/// package per other participant.
+pub fn dkg_round2(
+    secret: DkgRound1Secret,
+    round1: &BTreeMap<Identifier, DkgRound1Package>,
+) -> Result<(DkgRound2Secret, BTreeMap<Identifier, DkgRound2Package>), FrostError> {
+    check_others(secret.identifier, secret.max_signers, round1.keys())?;
+    for (&sender, package) in round1 {
+        let (r, mu) = package.proof;
+        let valid = package.commitment.len() == secret.min_signers
+            && EdwardsPoint::mul_base(&mu) == r + package.commitment[0] * dkg_challenge(sender, &package.commitment[0], &r);
+        if !valid {
+            return Err(FrostError::InvalidProofOfKnowledge { culprit: sender });
+        }
+    }
+
+    let outgoing = round1.keys()
+        .map(|&id| (id, DkgRound2Package { signing_share: Zeroizing::new(evaluate_polynomial(&secret.coefficients, id)) }))
+        .collect();
+    let own_share = Zeroizing::new(evaluate_polynomial(&secret.coefficients, secret.identifier));
+    let commitment = secret.coefficients.iter().map(|a| EdwardsPoint::mul_base(a)).collect();
+    Ok((
+        DkgRound2Secret {
+            identifier: secret.identifier,
+            commitment,
+            own_share,
+            min_signers: secret.min_signers,
+            max_signers: secret.max_signers,
+        },
+        outgoing,
+    ))
+}
+
+/// DKG round three: checks the shares received from the others against their commitments and
+/// assembles this participant's key. Every participant arrives at the same `PublicKeyPackage`.
+pub fn dkg_finish(
+    secret: &DkgRound2Secret,
+    round1: &BTreeMap<Identifier, DkgRound1Package>,
+    round2: &BTreeMap<Identifier, DkgRound2Package>,
+) -> Result<(KeyPackage, PublicKeyPackage), FrostError> {
+    check_others(secret.identifier, secret.max_signers, round1.keys())?;
+    check_others(secret.identifier, secret.max_signers, round2.keys())?;
+    let mut signing_share = Zeroizing::new(*secret.own_share);
+    for (&sender, package) in round2 {
+        let expected = evaluate_commitment(&round1[&sender].commitment, secret.identifier);
+        if EdwardsPoint::mul_base(&package.signing_share) != expected {
+            return Err(FrostError::InvalidSecretShare { culprit: sender });
+        }
+        *signing_share += *package.signing_share;
+    }
+
+    let commitments: Vec<&[EdwardsPoint]> =
+        round1.values().map(|p| p.commitment.as_slice()).chain([secret.commitment.as_slice()]).collect();
+    let group_public_key = verifying_key(commitments.iter().map(|c| c[0]).sum());
+    let verifying_shares = (1..=secret.max_signers as Identifier)
+        .map(|id| (id, commitments.iter().map(|c| evaluate_commitment(c, id)).sum()))
+        .collect();
+    let key_package = KeyPackage {
+        identifier: secret.identifier,
+        verifying_share: EdwardsPoint::mul_base(&signing_share),
+        signing_share,
+        group_public_key,
+        min_signers: secret.min_signers,
+    };
+    Ok((key_package, PublicKeyPackage { verifying_shares, group_public_key }))
+}
+
+/// Round one: fresh nonces (kept) and their commitments (sent to the coordinator).
+pub fn commit(key: &KeyPackage) -> (SigningNonces, SigningCommitments) {
+    let mut hiding_randomness = Zeroizing::new([0u8; 32]);
+    let mut binding_randomness = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(&mut hiding_randomness[..]);
+    OsRng.fill_bytes(&mut binding_randomness[..]);
+    commit_with_randomness(key, &hiding_randomness, &binding_randomness)
+}
+
+/// nonce_generate (RFC 9591 §4.1): the nonces also depend on the signing share, so a weak RNG
+/// alone does not leak the key.
+fn commit_with_randomness(key: &KeyPackage, hiding: &[u8; 32], binding: &[u8; 32]) -> (SigningNonces, SigningCommitments) {
+    let nonce = |randomness: &[u8; 32]| Zeroizing::new(h(b"nonce", &[randomness, key.signing_share.as_bytes()]));
+    let (hiding, binding) = (nonce(hiding), nonce(binding));
+    let commitments = SigningCommitments { hiding: EdwardsPoint::mul_base(&hiding), binding: EdwardsPoint::mul_base(&binding) };
+    (SigningNonces { hiding, binding, commitments }, commitments)
+}
+
+/// Round two: this signer's share of the signature on `package.message`.
+pub fn sign(package: &SigningPackage, nonces: SigningNonces, key: &KeyPackage) -> Result<SignatureShare, FrostError> {
+    if package.commitments.len() < key.min_signers {
+        return Err(FrostError::TooFewSigners { got: package.commitments.len(), min: key.min_signers });
+    }
+    if package.commitments.get(&key.identifier) != Some(&nonces.commitments) {
+        return Err(FrostError::CommitmentMismatch);
+    }
+    let binding_factors = binding_factors(&key.group_public_key, package);
+    let group_commitment = group_commitment(package, &binding_factors);
+    let challenge = challenge(&group_commitment, &key.group_public_key, &package.message);
+    let lambda = interpolating_value(package.commitments.keys(), key.identifier);
+    let z = *nonces.hiding + *nonces.binding * binding_factors[&key.identifier] + lambda * *key.signing_share * challenge;
+    Ok(SignatureShare(z))
+}
+
+/// Combine the shares into an Ed25519 signature. Every share is verified first; if any is
+/// wrong, the error names all participants whose share is.
+pub fn aggregate(
+    package: &SigningPackage,
+    shares: &BTreeMap<Identifier, SignatureShare>,
+    public_keys: &PublicKeyPackage,
+) -> Result<Signature, FrostError> {
+    for &id in package.commitments.keys() {
+        if !shares.contains_key(&id) {
+            return Err(FrostError::MissingShare(id));
+        }
+        if !public_keys.verifying_shares.contains_key(&id) {
+            return Err(FrostError::InvalidIdentifier(id));
+        }
+    }
+    if let Some(&id) = shares.keys().find(|id| !package.commitments.contains_key(id)) {
+        return Err(FrostError::InvalidIdentifier(id));
+    }
+
+    let binding_factors = binding_factors(&public_keys.group_public_key, package);
+    let group_commitment = group_commitment(package, &binding_factors);
+    let challenge = challenge(&group_commitment, &public_keys.group_public_key, &package.message);
+    let culprits: Vec<Identifier> = package.commitments.iter()
+        .filter(|&(&id, commitments)| {
+            let lambda = interpolating_value(package.commitments.keys(), id);
+            let expected = commitments.hiding + commitments.binding * binding_factors[&id]
+                + public_keys.verifying_shares[&id] * (challenge * lambda);
+            EdwardsPoint::mul_base(&shares[&id].0) != expected
+        })
+        .map(|(&id, _)| id)
+        .collect();
+    if !culprits.is_empty() {
+        return Err(FrostError::InvalidSignatureShares { culprits });
+    }
+
+    let z: Scalar = shares.values().map(|share| share.0).sum();
+    Ok(Signature::from_components(group_commitment.compress().to_bytes(), z.to_bytes()))
+}
+
+fn check_parameters(min_signers: usize, max_signers: usize) -> Result<(), FrostError> {
+    if min_signers < 2 || min_signers > max_signers || max_signers > Identifier::MAX as usize {
+        return Err(FrostError::InvalidParameters);
+    }
+    Ok(())
+}
+
+fn check_identifier(id: Identifier, max_signers: usize) -> Result<(), FrostError> {
+    if id == 0 || id as usize > max_signers {
+        return Err(FrostError::InvalidIdentifier(id));
+    }
+    Ok(())
+}
+
+/// DKG inputs must come from every other participant, and only from them.
+fn check_others<'a>(own: Identifier, max_signers: usize, ids: impl ExactSizeIterator<Item = &'a Identifier>) -> Result<(), FrostError> {
+    let count = ids.len();
+    for &id in ids {
+        if id == own {
+            return Err(FrostError::InvalidIdentifier(id));
+        }
+        check_identifier(id, max_signers)?;
+    }
+    if count != max_signers - 1 {
+        return Err(FrostError::TooFewSigners { got: count + 1, min: max_signers });
+    }
+    Ok(())
+}
+
+/// H1 / H3 / HDKG of the ciphersuite: SHA-512(contextString || tag || parts) mod l.
+fn h(tag: &[u8], parts: &[&[u8]]) -> Scalar {
+    let mut hasher = Sha512::new().chain_update(CONTEXT_STRING).chain_update(tag);
+    for part in parts {
+        hasher.update(part);
+    }
+    Scalar::from_bytes_mod_order_wide(&hasher.finalize().into())
+}
+
+fn random_scalar() -> Scalar {
+    let mut bytes = Zeroizing::new([0u8; 64]);
+    OsRng.fill_bytes(&mut bytes[..]);
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+fn identifier_scalar(id: Identifier) -> Scalar {
+    Scalar::from(id as u64)
+}
+
+fn verifying_key(point: EdwardsPoint) -> VerifyingKey {
+    VerifyingKey::from_bytes(point.compress().as_bytes()).expect("a compressed point decompresses")
+}
+
+fn evaluate_polynomial(coefficients: &[Zeroizing<Scalar>], id: Identifier) -> Scalar {
+    let x = identifier_scalar(id);
+    coefficients.iter().rev().fold(Scalar::ZERO, |acc, a| acc * x + **a)
+}
+
+/// [f(id)]B from the commitments [a_j]B.
+fn evaluate_commitment(commitment: &[EdwardsPoint], id: Identifier) -> EdwardsPoint {
+    let x = identifier_scalar(id);
+    commitment.iter().rev().fold(EdwardsPoint::identity(), |acc, c| acc * x + c)
+}
+
+/// Lagrange coefficient of `id` at 0 over the signer set `ids`.
+fn interpolating_value<'a>(ids: impl Iterator<Item = &'a Identifier>, id: Identifier) -> Scalar {
+    let x_i = identifier_scalar(id);
+    let (mut num, mut den) = (Scalar::ONE, Scalar::ONE);
+    for &other in ids.filter(|&&other| other != id) {
+        let x_j = identifier_scalar(other);
+        num *= x_j;
+        den *= x_j - x_i;
+    }
+    num * den.invert()
+}
+
+fn dkg_challenge(id: Identifier, constant_commitment: &EdwardsPoint, r: &EdwardsPoint) -> Scalar {
+    h(b"dkg", &[identifier_scalar(id).as_bytes(), constant_commitment.compress().as_bytes(), r.compress().as_bytes()])
+}
+
+/// compute_binding_factors (RFC 9591 §4.4).
+fn binding_factors(group_public_key: &VerifyingKey, package: &SigningPackage) -> BTreeMap<Identifier, Scalar> {
+    let encoded_commitments: Vec<u8> = package.commitments.iter()
+        .flat_map(|(&id, c)| [identifier_scalar(id).to_bytes(), c.hiding.compress().to_bytes(), c.binding.compress().to_bytes()])
+        .flatten()
+        .collect();
+    let msg_hash = Sha512::new().chain_update(CONTEXT_STRING).chain_update(b"msg").chain_update(&package.message).finalize();
+    let commitment_hash = Sha512::new().chain_update(CONTEXT_STRING).chain_update(b"com").chain_update(&encoded_commitments).finalize();
+    package.commitments.keys()
+        .map(|&id| (id, h(b"rho", &[group_public_key.as_bytes(), &msg_hash, &commitment_hash, &identifier_scalar(id).to_bytes()])))
+        .collect()
+}
+
+fn group_commitment(package: &SigningPackage, binding_factors: &BTreeMap<Identifier, Scalar>) -> EdwardsPoint {
+    package.commitments.iter().map(|(id, c)| c.hiding + c.binding * binding_factors[id]).sum()
+}
+
+/// H2: the plain Ed25519 challenge SHA-512(R || A || msg), which is what makes the result a
+/// standard Ed25519 signature.
+fn challenge(group_commitment: &EdwardsPoint, group_public_key: &VerifyingKey, message: &[u8]) -> Scalar {
+    let h = Sha512::new()
+        .chain_update(group_commitment.compress().as_bytes())
+        .chain_update(group_public_key.as_bytes())
+        .chain_update(message)
+        .finalize();
+    Scalar::from_bytes_mod_order_wide(&h.into())
+}
+
+impl SigningCommitments {
+    /// The identity is not a valid commitment (RFC 9591 §4.1); parsing rejects it.
+    pub fn from_bytes(hiding: &[u8; 32], binding: &[u8; 32]) -> Option<Self> {
+        let point = |bytes: &[u8; 32]| CompressedEdwardsY(*bytes).decompress().filter(|p| !p.is_identity());
+        Some(Self { hiding: point(hiding)?, binding: point(binding)? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::signdemo;
+
+    fn scalar(s: &str) -> Scalar {
+        Scalar::from_canonical_bytes(hex::decode(s).unwrap().try_into().unwrap()).unwrap()
+    }
+
+    fn bytes32(s: &str) -> [u8; 32] {
+        hex::decode(s).unwrap().try_into().unwrap()
+    }
+
+    /// Run both rounds with `signers` and aggregate.
+    fn run_signing(
+        keys: &BTreeMap<Identifier, KeyPackage>,
+        public_keys: &PublicKeyPackage,
+        signers: &[Identifier],
+        message: &[u8],
+    ) -> Result<Signature, FrostError> {
+        let (nonces, commitments): (BTreeMap<_, _>, BTreeMap<_, _>) = signers.iter()
+            .map(|id| {
+                let (n, c) = commit(&keys[id]);
+                ((*id, n), (*id, c))
+            })
+            .unzip();
+        let package = SigningPackage::new(commitments, message).unwrap();
+        let shares = nonces.into_iter()
+            .map(|(id, n)| Ok((id, sign(&package, n, &keys[&id])?)))
+            .collect::<Result<BTreeMap<_, _>, FrostError>>()?;
+        aggregate(&package, &shares, public_keys)
+    }
+
+    /// RFC 9591 Appendix E.1, FROST(Ed25519, SHA-512), 2-of-3 with participants 1 and 3.
+    #[test]
+    fn rfc9591_ed25519_vector() {
+        let coefficients = [
+            Zeroizing::new(scalar("7b1c33d3f5291d85de664833beb1ad469f7fb6025a0ec78b3a790c6e13a98304")),
+            Zeroizing::new(scalar("178199860edd8c62f5212ee91eff1295d0d670ab4ed4506866bae57e7030b204")),
+        ];
+        let (keys, public_keys) = split(&coefficients, 2, 3);
+        assert_eq!(hex::encode(public_keys.group_public_key.as_bytes()), "15d21ccd7ee42959562fc8aa63224c8851fb3ec85a3faf66040d380fb9738673");
+        for (id, share) in [
+            (1, "929dcc590407aae7d388761cddb0c0db6f5627aea8e217f4a033f2ec83d93509"),
+            (2, "a91e66e012e4364ac9aaa405fcafd370402d9859f7b6685c07eed76bf409e80d"),
+            (3, "d3cb090a075eb154e82fdb4b3cb507f110040905468bb9c46da8bdea643a9a02"),
+        ] {
+            assert_eq!(*keys[&id].signing_share, scalar(share));
+        }
+
+        let (nonces1, commitments1) = commit_with_randomness(
+            &keys[&1],
+            &bytes32("0fd2e39e111cdc266f6c0f4d0fd45c947761f1f5d3cb583dfcb9bbaf8d4c9fec"),
+            &bytes32("69cd85f631d5f7f2721ed5e40519b1366f340a87c2f6856363dbdcda348a7501"),
+        );
+        let (nonces3, commitments3) = commit_with_randomness(
+            &keys[&3],
+            &bytes32("86d64a260059e495d0fb4fcc17ea3da7452391baa494d4b00321098ed2a0062f"),
+            &bytes32("13e6b25afb2eba51716a9a7d44130c0dbae0004a9ef8d7b5550c8a0e07c61775"),
+        );
+        assert_eq!(*nonces1.hiding, scalar("812d6104142944d5a55924de6d49940956206909f2acaeedecda2b726e630407"));
+        assert_eq!(*nonces1.binding, scalar("b1110165fc2334149750b28dd813a39244f315cff14d4e89e6142f262ed83301"));
+        assert_eq!(commitments1.hiding.compress().to_bytes(), bytes32("b5aa8ab305882a6fc69cbee9327e5a45e54c08af61ae77cb8207be3d2ce13de3"));
+        assert_eq!(commitments1.binding.compress().to_bytes(), bytes32("67e98ab55aa310c3120418e5050c9cf76cf387cb20ac9e4b6fdb6f82a469f932"));
+        assert_eq!(commitments3.hiding.compress().to_bytes(), bytes32("cfbdb165bd8aad6eb79deb8d287bcc0ab6658ae57fdcc98ed12c0669e90aec91"));
+        assert_eq!(commitments3.binding.compress().to_bytes(), bytes32("7487bc41a6e712eea2f2af24681b58b1cf1da278ea11fe4e8b78398965f13552"));
+
+        let package = SigningPackage::new(BTreeMap::from([(1, commitments1), (3, commitments3)]), b"test").unwrap();
+        assert_eq!(
+            binding_factors(&public_keys.group_public_key, &package)[&1],
+            scalar("f2cb9d7dd9beff688da6fcc83fa89046b3479417f47f55600b106760eb3b5603")
+        );
+        let share1 = sign(&package, nonces1, &keys[&1]).unwrap();
+        let share3 = sign(&package, nonces3, &keys[&3]).unwrap();
+        assert_eq!(share1.0, scalar("001719ab5a53ee1a12095cd088fd149702c0720ce5fd2f29dbecf24b7281b603"));
+        assert_eq!(share3.0, scalar("bd86125de990acc5e1f13781d8e32c03a9bbd4c53539bbc106058bfd14326007"));
+
+        let sig = aggregate(&package, &BTreeMap::from([(1, share1), (3, share3)]), &public_keys).unwrap();
+        assert_eq!(
+            hex::encode(sig.to_bytes()),
+            "36282629c383bb820a88b71cae937d41f2f2adfcc3d02e55507e2fb9e2dd3cbebd9d2b0844e49ae0f3fa935161e1419aab7b47d21a37ebeae1f17d4987b3160b"
+        );
+        assert!(signdemo::verify_strict(&public_keys.group_public_key, b"test", &sig));
+    }
+
+    #[test]
+    fn dealer_any_quorum_signs() {
+        let (keys, public_keys) = trusted_dealer_keygen(3, 5).unwrap();
+        for signers in [&[1, 2, 3][..], &[2, 4, 5], &[1, 3, 5], &[1, 2, 3, 4, 5]] {
+            let sig = run_signing(&keys, &public_keys, signers, b"release v1.2.3").unwrap();
+            assert!(signdemo::verify(&public_keys.group_public_key, b"release v1.2.3", &sig), "{signers:?}");
+            assert!(!signdemo::verify(&public_keys.group_public_key, b"release v1.2.4", &sig));
+        }
+        assert_eq!(run_signing(&keys, &public_keys, &[1, 2], b"m").err(), Some(FrostError::TooFewSigners { got: 2, min: 3 }));
+        assert_eq!(trusted_dealer_keygen(4, 3).err(), Some(FrostError::InvalidParameters));
+        assert_eq!(trusted_dealer_keygen(1, 3).err(), Some(FrostError::InvalidParameters));
+    }
+
+    fn run_dkg(min: usize, max: usize) -> Vec<(KeyPackage, PublicKeyPackage)> {
+        let ids: Vec<Identifier> = (1..=max as Identifier).collect();
+        let (secrets1, packages1): (Vec<_>, BTreeMap<_, _>) =
+            ids.iter().map(|&id| dkg_round1(id, min, max).unwrap()).zip(&ids).map(|((s, p), &id)| (s, (id, p))).unzip();
+        let others = |id: Identifier| packages1.iter().filter(|(other, _)| **other != id).map(|(k, v)| (*k, v.clone())).collect();
+        let mut round2: Vec<(DkgRound2Secret, BTreeMap<Identifier, DkgRound2Package>)> =
+            secrets1.into_iter().map(|s| { let id = s.identifier; dkg_round2(s, &others(id)).unwrap() }).collect();
+        let mut inbox: BTreeMap<Identifier, BTreeMap<Identifier, DkgRound2Package>> = BTreeMap::new();
+        for (secret, outgoing) in &mut round2 {
+            for (to, package) in std::mem::take(outgoing) {
+                inbox.entry(to).or_default().insert(secret.identifier, package);
+            }
+        }
+        round2.iter().map(|(s, _)| dkg_finish(s, &others(s.identifier), &inbox[&s.identifier]).unwrap()).collect()
+    }
+
+    #[test]
+    fn dkg_produces_a_working_group_key() {
+        let results = run_dkg(2, 3);
+        let public_keys = results[0].1.clone();
+        assert!(results.iter().all(|(_, p)| *p == public_keys));
+        for (key, _) in &results {
+            assert_eq!(public_keys.verifying_shares[&key.identifier], key.verifying_share);
+        }
+        let keys: BTreeMap<_, _> = results.into_iter().map(|(k, _)| (k.identifier, k)).collect();
+        for signers in [&[1, 2][..], &[1, 3], &[2, 3]] {
+            let sig = run_signing(&keys, &public_keys, signers, b"dkg").unwrap();
+            assert!(signdemo::verify(&public_keys.group_public_key, b"dkg", &sig));
+        }
+    }
+
+    #[test]
+    fn dkg_identifies_cheaters() {
+        let (s1, p1) = dkg_round1(1, 2, 3).unwrap();
+        let (s2, p2) = dkg_round1(2, 2, 3).unwrap();
+        let (_, mut p3) = dkg_round1(3, 2, 3).unwrap();
+        p3.proof.1 += Scalar::ONE;
+        let round1 = BTreeMap::from([(2, p2.clone()), (3, p3)]);
+        assert_eq!(dkg_round2(s1, &round1).err(), Some(FrostError::InvalidProofOfKnowledge { culprit: 3 }));
+
+        // participant 1 sends participant 2 a share off its committed polynomial
+        let (s3, p3) = dkg_round1(3, 2, 3).unwrap();
+        let (_, mut from3) = dkg_round2(s3, &BTreeMap::from([(1, p1.clone()), (2, p2.clone())])).unwrap();
+        let (s2, _) = dkg_round2(s2, &BTreeMap::from([(1, p1.clone()), (3, p3.clone())])).unwrap();
+        let bad = DkgRound2Package { signing_share: Zeroizing::new(Scalar::ONE) };
+        let inbox = BTreeMap::from([(1, bad), (3, from3.remove(&2).unwrap())]);
+        assert_eq!(
+            dkg_finish(&s2, &BTreeMap::from([(1, p1), (3, p3)]), &inbox).err(),
+            Some(FrostError::InvalidSecretShare { culprit: 1 })
+        );
+    }
+
+    #[test]
+    fn aggregate_identifies_bad_shares() {
+        let (keys, public_keys) = trusted_dealer_keygen(3, 4).unwrap();
+        let signers = [1, 2, 4];
+        let (mut nonces, commitments): (BTreeMap<_, _>, BTreeMap<_, _>) = signers.iter()
+            .map(|id| {
+                let (n, c) = commit(&keys[id]);
+                ((*id, n), (*id, c))
+            })
+            .unzip();
+        let package = SigningPackage::new(commitments, b"release").unwrap();
+
+        let mut shares = BTreeMap::new();
+        for id in signers {
+            shares.insert(id, sign(&package, nonces.remove(&id).unwrap(), &keys[&id]).unwrap());
+        }
+        shares.get_mut(&2).unwrap().0 += Scalar::ONE;
+        shares.get_mut(&4).unwrap().0 = Scalar::ZERO;
+        assert_eq!(aggregate(&package, &shares, &public_keys).err(), Some(FrostError::InvalidSignatureShares { culprits: vec![2, 4] }));
+
+        shares.remove(&4);
+        assert_eq!(aggregate(&package, &shares, &public_keys).err(), Some(FrostError::MissingShare(4)));
+
+        // nonces committed for another package cannot sign this one
+        let (other_nonces, _) = commit(&keys[&3]);
+        assert_eq!(sign(&package, other_nonces, &keys[&3]).err(), Some(FrostError::CommitmentMismatch));
+        assert!(SigningCommitments::from_bytes(&EdwardsPoint::identity().compress().to_bytes(), &[0x58; 32]).is_none());
+
+        // nor can a coordinator put the identity into a package directly
+        let (_, honest) = commit(&keys[&1]);
+        let identity_binding = SigningCommitments { binding: EdwardsPoint::identity(), ..honest };
+        assert_eq!(
+            SigningPackage::new(BTreeMap::from([(1, honest), (2, identity_binding)]), b"release").err(),
+            Some(FrostError::InvalidCommitment(2))
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Key encapsulation mechanisms behind one trait, so protocols can be written once:
+//! the sender calls `encapsulate(pk)` and transmits `ct`, the receiver calls `decapsulate(sk, ct)`.
+//! - `DhKem<G>`: DHKEM (RFC 9180 §4.1 structure) over any `KeyAgreement` group, with the
+//!   HKDF-SHA3-256 from `crypto::hkdf`. Not wire-compatible with the RFC suites, which use SHA-2.
+//! - `MlKem768`: FIPS 203 ML-KEM-768 from `crypto::mlkem`.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::hkdf;
+use super::key_agreement::{AgreementError, KeyAgreement, PointFormat};
+use super::mlkem::{self, MlKemError};
+use super::secret::SharedSecret;
+
+pub const SHARED_SECRET_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KemError {
+    InvalidPublicKey,
+    /// Wrong length or undecodable encapsulation.
+    InvalidCiphertext,
+    /// A Diffie-Hellman output was all zero (low-order key).
+    NonContributory,
+}
+
+impl fmt::Display for KemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KemError::InvalidPublicKey => write!(f, "invalid KEM public key"),
+            KemError::InvalidCiphertext => write!(f, "invalid KEM ciphertext"),
+            KemError::NonContributory => write!(f, "non-contributory key exchange"),
+        }
+    }
+}
+
+impl std::error::Error for KemError {}
+
+pub trait Kem {
+    const NAME: &'static str;
+    type SecretKey;
+    type PublicKey;
+
+    fn generate() -> (Self::SecretKey, Self::PublicKey);
+    /// Returns (ct, ss): `ct` goes to the owner of `pk`, `ss` stays with the sender.
+    fn encapsulate(pk: &Self::PublicKey) -> Result<(Vec<u8>, SharedSecret<SHARED_SECRET_LEN>), KemError>;
+    fn decapsulate(sk: &Self::SecretKey, ct: &[u8]) -> Result<SharedSecret<SHARED_SECRET_LEN>, KemError>;
+}
+
+/// DHKEM over the group `G`; the ciphertext is the raw-encoded ephemeral public key.
+pub struct DhKem<G>(PhantomData<G>);
+
+/// Recipient key of a `DhKem`: the public key is kept for the KEM context.
+pub struct DhKemSecretKey<G: KeyAgreement> {
+    sk: G::SecretKey,
+    pk: G::PublicKey,
+}
+
+impl From<AgreementError> for KemError {
+    fn from(e: AgreementError) -> Self {
+        match e {
+            AgreementError::NonContributory => KemError::NonContributory,
+            AgreementError::InvalidPublicKey | AgreementError::UnsupportedFormat => KemError::InvalidPublicKey,
+        }
+    }
+}
+
+impl<G: KeyAgreement> DhKem<G> {
+    /// ExtractAndExpand(dh, enc || pkR), domain-separated by the group name.
+    fn extract_and_expand(dh: &[u8], enc: &[u8], pk_r: &G::PublicKey) -> Result<SharedSecret<SHARED_SECRET_LEN>, KemError> {
+        let pk_r = G::encode_public_key(pk_r, PointFormat::Raw)?;
+        let (_, hk) = hkdf::extract(Some(G::NAME.as_bytes()), &[&b"eae_prk"[..], dh].concat());
+        let info = [&b"shared_secret"[..], enc, &pk_r].concat();
+        Ok(SharedSecret::new(hkdf::expand(&hk, &info).expect("32 bytes is a valid HKDF output length")))
+    }
+}
+
+impl<G: KeyAgreement> Kem for DhKem<G> {
+    const NAME: &'static str = G::NAME;
+    type SecretKey = DhKemSecretKey<G>;
+    type PublicKey = G::PublicKey;
+
+    fn generate() -> (Self::SecretKey, Self::PublicKey) {
+        let (sk, pk) = G::generate();
+        (DhKemSecretKey { sk, pk: pk.clone() }, pk)
+    }
+
+    fn encapsulate(pk_r: &Self::PublicKey) -> Result<(Vec<u8>, SharedSecret<SHARED_SECRET_LEN>), KemError> {
+        let (sk_e, pk_e) = G::generate();
+        let dh = G::agree(&sk_e, pk_r)?;
+        let enc = G::encode_public_key(&pk_e, PointFormat::Raw)?;
+        let ss = Self::extract_and_expand(&dh, &enc, pk_r)?;
+        Ok((enc, ss))
+    }
+
+    fn decapsulate(sk: &Self::SecretKey, enc: &[u8]) -> Result<SharedSecret<SHARED_SECRET_LEN>, KemError> {
+        let pk_e = G::decode_public_key(enc).map_err(|_| KemError::InvalidCiphertext)?;
+        let dh = G::agree(&sk.sk, &pk_e)?;
+        Self::extract_and_expand(&dh, enc, &sk.pk)
+    }
+}
+
+pub struct MlKem768;
+
+impl Kem for MlKem768 {
+    const NAME: &'static str = "ML-KEM-768";
+    type SecretKey = mlkem::DecapsulationKey;
+    type PublicKey = mlkem::EncapsulationKey;
+
+    fn generate() -> (Self::SecretKey, Self::PublicKey) {
+        mlkem::generate()
+    }
+
+    fn encapsulate(pk: &Self::PublicKey) -> Result<(Vec<u8>, SharedSecret<SHARED_SECRET_LEN>), KemError> {
+        Ok(pk.encapsulate())
+    }
+
+    fn decapsulate(sk: &Self::SecretKey, ct: &[u8]) -> Result<SharedSecret<SHARED_SECRET_LEN>, KemError> {
+        sk.decapsulate(ct).map_err(|e| match e {
+            MlKemError::WrongCiphertextLength => KemError::InvalidCiphertext,
+            MlKemError::InvalidEncapsulationKey | MlKemError::InvalidDecapsulationKey => KemError::InvalidPublicKey,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::key_agreement::{P256, P384, X25519, X448};
+    use proptest::prelude::*;
+
+    /// Written once against the trait: every KEM must round-trip, give fresh secrets per
+    /// encapsulation, and never hand out the sender's secret for a modified ciphertext.
+    fn check_kem<T: Kem>(flip_at: usize, bit: u8) -> Result<(), TestCaseError> {
+        let (sk, pk) = T::generate();
+        let (ct, ss) = T::encapsulate(&pk).unwrap();
+        prop_assert_eq!(&T::decapsulate(&sk, &ct).unwrap(), &ss, "{}", T::NAME);
+
+        let (ct2, ss2) = T::encapsulate(&pk).unwrap();
+        prop_assert_ne!(&ct, &ct2);
+        prop_assert_ne!(&ss, &ss2);
+
+        let mut tampered = ct.clone();
+        tampered[flip_at % ct.len()] ^= 1 << (bit % 8);
+        if let Ok(other) = T::decapsulate(&sk, &tampered) {
+            prop_assert_ne!(other, ss, "{}", T::NAME);
+        }
+        prop_assert!(T::decapsulate(&sk, &ct[1..]).is_err());
+        Ok(())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn dhkem_x25519_roundtrips(flip_at in any::<usize>(), bit in any::<u8>()) {
+            check_kem::<DhKem<X25519>>(flip_at, bit)?;
+        }
+
+        #[test]
+        fn dhkem_x448_roundtrips(flip_at in any::<usize>(), bit in any::<u8>()) {
+            check_kem::<DhKem<X448>>(flip_at, bit)?;
+        }
+
+        #[test]
+        fn dhkem_nist_roundtrips(flip_at in any::<usize>(), bit in any::<u8>()) {
+            check_kem::<DhKem<P256>>(flip_at, bit)?;
+            check_kem::<DhKem<P384>>(flip_at, bit)?;
+        }
+
+        #[test]
+        fn mlkem768_roundtrips(flip_at in any::<usize>(), bit in any::<u8>()) {
+            check_kem::<MlKem768>(flip_at, bit)?;
+        }
+    }
+
+    #[test]
+    fn dhkem_rejects_low_order_encapsulation() {
+        let (sk, _) = DhKem::<X25519>::generate();
+        assert_eq!(DhKem::<X25519>::decapsulate(&sk, &[0u8; 32]), Err(KemError::NonContributory));
+    }
+}
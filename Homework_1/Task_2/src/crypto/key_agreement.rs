@@ -0,0 +1,243 @@
+//! Uniform interface over the Diffie-Hellman groups we support: X25519, X448, P-256 and P-384.
+//! Public keys can be encoded as:
+//! - `Raw`: the u-coordinate for X25519/X448, x || y (no SEC1 prefix) for the NIST curves;
+//! - `Compressed` / `Uncompressed`: SEC1 point encodings (NIST curves only).
+//!
+//! `decode_public_key` accepts every format the group supports, detected from length and prefix.
+//! The agreed secret is the raw DH output (u-coordinate or x-coordinate); feed it to a KDF.
+
+use std::fmt;
+
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rand::{RngCore, rngs::OsRng};
+use x25519_dalek::StaticSecret;
+use zeroize::Zeroizing;
+
+use super::secret::SecretKey;
+use super::x448 as x448_fn;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointFormat {
+    Raw,
+    Compressed,
+    Uncompressed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgreementError {
+    /// Wrong length, bad SEC1 prefix or point not on the curve.
+    InvalidPublicKey,
+    /// The group has no such encoding (SEC1 formats for X25519/X448).
+    UnsupportedFormat,
+    /// The peer's key has small order and the shared secret is all zero.
+    NonContributory,
+}
+
+impl fmt::Display for AgreementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgreementError::InvalidPublicKey => write!(f, "invalid public key"),
+            AgreementError::UnsupportedFormat => write!(f, "point format not supported by this group"),
+            AgreementError::NonContributory => write!(f, "peer public key has small order (non-contributory exchange)"),
+        }
+    }
+}
+
+impl std::error::Error for AgreementError {}
+
+pub trait KeyAgreement {
+    const NAME: &'static str;
+    /// Length of the agreed secret in bytes.
+    const SHARED_SECRET_LEN: usize;
+    type SecretKey;
+    type PublicKey: Clone + PartialEq + fmt::Debug;
+
+    fn generate() -> (Self::SecretKey, Self::PublicKey);
+    /// DH(sk, pk_peer). The secret key is not consumed; the result is wiped when dropped.
+    fn agree(sk: &Self::SecretKey, pk_peer: &Self::PublicKey) -> Result<Zeroizing<Vec<u8>>, AgreementError>;
+    fn encode_public_key(pk: &Self::PublicKey, format: PointFormat) -> Result<Vec<u8>, AgreementError>;
+    fn decode_public_key(bytes: &[u8]) -> Result<Self::PublicKey, AgreementError>;
+}
+
+pub struct X25519;
+pub struct X448;
+pub struct P256;
+pub struct P384;
+
+impl KeyAgreement for X25519 {
+    const NAME: &'static str = "X25519";
+    const SHARED_SECRET_LEN: usize = 32;
+    type SecretKey = StaticSecret;
+    type PublicKey = x25519_dalek::PublicKey;
+
+    fn generate() -> (Self::SecretKey, Self::PublicKey) {
+        let sk = StaticSecret::random_from_rng(OsRng);
+        let pk = x25519_dalek::PublicKey::from(&sk);
+        (sk, pk)
+    }
+
+    fn agree(sk: &Self::SecretKey, pk_peer: &Self::PublicKey) -> Result<Zeroizing<Vec<u8>>, AgreementError> {
+        let ss = sk.diffie_hellman(pk_peer);
+        if !ss.was_contributory() {
+            return Err(AgreementError::NonContributory);
+        }
+        Ok(Zeroizing::new(ss.to_bytes().to_vec()))
+    }
+
+    fn encode_public_key(pk: &Self::PublicKey, format: PointFormat) -> Result<Vec<u8>, AgreementError> {
+        match format {
+            PointFormat::Raw => Ok(pk.to_bytes().to_vec()),
+            _ => Err(AgreementError::UnsupportedFormat),
+        }
+    }
+
+    fn decode_public_key(bytes: &[u8]) -> Result<Self::PublicKey, AgreementError> {
+        let raw: [u8; 32] = bytes.try_into().map_err(|_| AgreementError::InvalidPublicKey)?;
+        Ok(raw.into())
+    }
+}
+
+impl KeyAgreement for X448 {
+    const NAME: &'static str = "X448";
+    const SHARED_SECRET_LEN: usize = x448_fn::KEY_LEN;
+    type SecretKey = SecretKey<{ x448_fn::KEY_LEN }>;
+    type PublicKey = [u8; x448_fn::KEY_LEN];
+
+    fn generate() -> (Self::SecretKey, Self::PublicKey) {
+        let mut sk = SecretKey::new([0u8; x448_fn::KEY_LEN]);
+        OsRng.fill_bytes(sk.expose_secret_mut());
+        let pk = x448_fn::public_key(sk.expose_secret());
+        (sk, pk)
+    }
+
+    fn agree(sk: &Self::SecretKey, pk_peer: &Self::PublicKey) -> Result<Zeroizing<Vec<u8>>, AgreementError> {
+        let ss = Zeroizing::new(x448_fn::x448(sk.expose_secret(), pk_peer));
+        if ss.iter().fold(0u8, |acc, b| acc | b) == 0 {
+            return Err(AgreementError::NonContributory);
+        }
+        Ok(Zeroizing::new(ss.to_vec()))
+    }
+
+    fn encode_public_key(pk: &Self::PublicKey, format: PointFormat) -> Result<Vec<u8>, AgreementError> {
+        match format {
+            PointFormat::Raw => Ok(pk.to_vec()),
+            _ => Err(AgreementError::UnsupportedFormat),
+        }
+    }
+
+    fn decode_public_key(bytes: &[u8]) -> Result<Self::PublicKey, AgreementError> {
+        bytes.try_into().map_err(|_| AgreementError::InvalidPublicKey)
+    }
+}
+
+/// ECDH over a NIST curve from the RustCrypto elliptic-curve family; `$field` is the coordinate size.
+macro_rules! nist_group {
+    ($group:ident, $name:literal, $curve:ident, $field:literal) => {
+        impl KeyAgreement for $group {
+            const NAME: &'static str = $name;
+            const SHARED_SECRET_LEN: usize = $field;
+            type SecretKey = $curve::SecretKey;
+            type PublicKey = $curve::PublicKey;
+
+            fn generate() -> (Self::SecretKey, Self::PublicKey) {
+                let sk = $curve::SecretKey::random(&mut OsRng);
+                let pk = sk.public_key();
+                (sk, pk)
+            }
+
+            fn agree(sk: &Self::SecretKey, pk_peer: &Self::PublicKey) -> Result<Zeroizing<Vec<u8>>, AgreementError> {
+                // The identity cannot be encoded as a PublicKey, so the result is never all zero.
+                let ss = $curve::ecdh::diffie_hellman(sk.to_nonzero_scalar(), pk_peer.as_affine());
+                Ok(Zeroizing::new(ss.raw_secret_bytes().to_vec()))
+            }
+
+            fn encode_public_key(pk: &Self::PublicKey, format: PointFormat) -> Result<Vec<u8>, AgreementError> {
+                let point = pk.to_encoded_point(format == PointFormat::Compressed);
+                Ok(match format {
+                    PointFormat::Raw => point.as_bytes()[1..].to_vec(),
+                    _ => point.as_bytes().to_vec(),
+                })
+            }
+
+            fn decode_public_key(bytes: &[u8]) -> Result<Self::PublicKey, AgreementError> {
+                let sec1 = if bytes.len() == 2 * $field { [&[0x04][..], bytes].concat() } else { bytes.to_vec() };
+                $curve::PublicKey::from_sec1_bytes(&sec1).map_err(|_| AgreementError::InvalidPublicKey)
+            }
+        }
+    };
+}
+
+nist_group!(P256, "P-256", p256, 32);
+nist_group!(P384, "P-384", p384, 48);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_FORMATS: [PointFormat; 3] = [PointFormat::Raw, PointFormat::Compressed, PointFormat::Uncompressed];
+
+    /// Both parties derive the same secret for every encoding of the public keys the group supports.
+    fn both_sides_agree<G: KeyAgreement>(formats: &[PointFormat]) {
+        let (sk_a, pk_a) = G::generate();
+        let (sk_b, pk_b) = G::generate();
+        for &format in formats {
+            let wire_a = G::encode_public_key(&pk_a, format).unwrap();
+            let wire_b = G::encode_public_key(&pk_b, format).unwrap();
+            assert_eq!(G::decode_public_key(&wire_a).unwrap(), pk_a, "{} {format:?}", G::NAME);
+
+            let ss_a = G::agree(&sk_a, &G::decode_public_key(&wire_b).unwrap()).unwrap();
+            let ss_b = G::agree(&sk_b, &G::decode_public_key(&wire_a).unwrap()).unwrap();
+            assert_eq!(ss_a, ss_b, "{} {format:?}", G::NAME);
+            assert_eq!(ss_a.len(), G::SHARED_SECRET_LEN);
+        }
+        for format in ALL_FORMATS.iter().filter(|f| !formats.contains(f)) {
+            assert_eq!(G::encode_public_key(&pk_a, *format), Err(AgreementError::UnsupportedFormat));
+        }
+    }
+
+    #[test]
+    fn all_groups_agree() {
+        both_sides_agree::<X25519>(&[PointFormat::Raw]);
+        both_sides_agree::<X448>(&[PointFormat::Raw]);
+        both_sides_agree::<P256>(&ALL_FORMATS);
+        both_sides_agree::<P384>(&ALL_FORMATS);
+    }
+
+    #[test]
+    fn known_answers() {
+        // RFC 7748 §6.1
+        let sk: [u8; 32] = hex::decode("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a").unwrap().try_into().unwrap();
+        let pk = X25519::decode_public_key(&hex::decode("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f").unwrap()).unwrap();
+        assert_eq!(hex::encode(X25519::agree(&StaticSecret::from(sk), &pk).unwrap()), "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+
+        // NIST curves: keys generated and shared secrets computed with OpenSSL
+        let sk = p256::SecretKey::from_slice(&hex::decode("563c8cdd986139f385eb427363ef062ddd7187b208612a3477d6306c21ea02f4").unwrap()).unwrap();
+        let pk = P256::decode_public_key(&hex::decode("033602042d0e1ccf165a5ad2b45e79fdaf04dd4e6571f698d79206ffa572d52486").unwrap()).unwrap();
+        assert_eq!(hex::encode(P256::agree(&sk, &pk).unwrap()), "31d4ea6652e6627f2c4c61c60b756c2ac434edbee72a7e98ff29e24df98bdfe3");
+
+        let sk = p384::SecretKey::from_slice(&hex::decode("10d66c844da9d6e91e8dfb7536218b04dcfa9cabbd0457fec818e7de665d383fc0d549284f962f0d1ab43a679469d17b").unwrap()).unwrap();
+        let pk = P384::decode_public_key(&hex::decode("028178699c4ff96c4ef3d542c3b35b6cc344e199e41856431c5f707358969bf2b53d56eec7ab87dbc974e6b17786ae88c0").unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(P384::agree(&sk, &pk).unwrap()),
+            "309163df9d7ee973fabe96623c0dbc63bd4f15a6a987ea1b7c247d22645cb2128513c39ab292e36809e3507b0b91fc69"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_and_low_order_keys() {
+        let (sk, _) = X25519::generate();
+        assert_eq!(X25519::agree(&sk, &[0u8; 32].into()), Err(AgreementError::NonContributory));
+        let (sk, _) = X448::generate();
+        let mut one = [0u8; 56];
+        one[0] = 1;
+        assert_eq!(X448::agree(&sk, &one), Err(AgreementError::NonContributory));
+        assert_eq!(X448::agree(&sk, &[0u8; 56]), Err(AgreementError::NonContributory));
+
+        let (_, pk) = P256::generate();
+        let mut off_curve = P256::encode_public_key(&pk, PointFormat::Uncompressed).unwrap();
+        off_curve[64] ^= 1;
+        assert_eq!(P256::decode_public_key(&off_curve), Err(AgreementError::InvalidPublicKey));
+        assert_eq!(P384::decode_public_key(&[0x04; 65]), Err(AgreementError::InvalidPublicKey));
+        assert_eq!(X25519::decode_public_key(&[0u8; 33]), Err(AgreementError::InvalidPublicKey));
+    }
+}
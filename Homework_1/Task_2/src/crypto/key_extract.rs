@@ -1,6 +1,8 @@
 use sha2::{Sha256, Digest};
 use hkdf::Hkdf;
 
+use super::secret::SecretKey;
+
 pub fn hashValue(plaintext: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
     hasher.update(plaintext);
@@ -8,14 +10,14 @@ pub fn hashValue(plaintext: &[u8]) -> [u8; 32] {
     result.into()
 }
 
-pub fn KeySchedule_1(shared_secret: &[u8]) -> ([u8; 32], [u8; 32]) {
+pub fn KeySchedule_1(shared_secret: &[u8]) -> (SecretKey<32>, SecretKey<32>) {
     let hs = DeriveHS(shared_secret);
     let k_1_c = hkdf_expand(&hs, &hashValue(b"ClientKE"));
     let k_1_s = hkdf_expand(&hs, &hashValue(b"ServerKE"));
     (k_1_c, k_1_s)
 }
 
-pub fn KeySchedule_2(nonce_c: &[u8], pk_A: &[u8], nonce_s: &[u8], pk_B: &[u8], shared_secret: &[u8]) -> ([u8; 32], [u8; 32]) {
+pub fn KeySchedule_2(nonce_c: &[u8], pk_A: &[u8], nonce_s: &[u8], pk_B: &[u8], shared_secret: &[u8]) -> (SecretKey<32>, SecretKey<32>) {
     let hs = DeriveHS(shared_secret);
 
     let client_input = [nonce_c, pk_A, nonce_s, pk_B, b"ClientKC"].concat();
@@ -30,12 +32,12 @@ pub fn KeySchedule_2(nonce_c: &[u8], pk_A: &[u8], nonce_s: &[u8], pk_B: &[u8], s
     (k_2_c, k_2_s)
 }
 
-pub fn KeySchedule_3(nonce_c: &[u8], pk_A: &[u8], nonce_s: &[u8], pk_B: &[u8], shared_secret: &[u8], sigma: &[u8], cert: &[u8], mac_s: &[u8]) -> ([u8; 32], [u8; 32]) {
+pub fn KeySchedule_3(nonce_c: &[u8], pk_A: &[u8], nonce_s: &[u8], pk_B: &[u8], shared_secret: &[u8], sigma: &[u8], cert: &[u8], mac_s: &[u8]) -> (SecretKey<32>, SecretKey<32>) {
     let zeros: [u8; 32] = [0u8; 32];
     let hs = DeriveHS(shared_secret);
 
     let dhs = hkdf_expand(&hs, &hashValue(b"DerivedHS"));
-    let ms = hkdf_extract(&zeros, &dhs);
+    let ms = hkdf_extract(&zeros, dhs.expose_secret());
 
     let client_input = [nonce_c, pk_A, nonce_s, pk_B, sigma, cert, mac_s, b"ClientEnck"].concat();
     let server_input = [nonce_c, pk_A, nonce_s, pk_B, sigma, cert, mac_s, b"ServerEnck"].concat();
@@ -49,24 +51,24 @@ pub fn KeySchedule_3(nonce_c: &[u8], pk_A: &[u8], nonce_s: &[u8], pk_B: &[u8], s
     (k_3_c, k_3_s)
 }
 
-pub fn DeriveHS(shared_secret: &[u8]) -> [u8; 32] {
+pub fn DeriveHS(shared_secret: &[u8]) -> SecretKey<32> {
     let zeros: [u8; 32] = [0u8; 32];
     let es = hkdf_extract(&zeros, &zeros);
     let dES = hkdf_expand(&es, &hashValue(b"DerivedES"));
-    let hs = hkdf_extract(&dES, shared_secret);
+    let hs = hkdf_extract(dES.expose_secret(), shared_secret);
     hs
 }
 
-fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> SecretKey<32> {
     let hkdf = Hkdf::<Sha256>::new(Some(salt), ikm);
-    let mut prk = [0u8; 32];
-    hkdf.expand(&[], &mut prk).expect("HKDF expand failed");
+    let mut prk = SecretKey::new([0u8; 32]);
+    hkdf.expand(&[], prk.expose_secret_mut()).expect("HKDF expand failed");
     prk
 }
 
-fn hkdf_expand(prk: &[u8; 32], info: &[u8]) -> [u8; 32] {
-    let hkdf = Hkdf::<Sha256>::from_prk(prk).expect("Invalid PRK");
-    let mut okm = [0u8; 32];
-    hkdf.expand(info, &mut okm).expect("HKDF expand failed");
+fn hkdf_expand(prk: &SecretKey<32>, info: &[u8]) -> SecretKey<32> {
+    let hkdf = Hkdf::<Sha256>::from_prk(prk.expose_secret()).expect("Invalid PRK");
+    let mut okm = SecretKey::new([0u8; 32]);
+    hkdf.expand(info, okm.expose_secret_mut()).expect("HKDF expand failed");
     okm
 }
\ No newline at end of file
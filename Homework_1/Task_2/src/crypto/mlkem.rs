@@ -0,0 +1,551 @@
+//! ML-KEM-768 (FIPS 203) key encapsulation.
+//! Implemented here because no ML-KEM crate is in our dependency set; the algorithm numbers in
+//! the comments refer to FIPS 203. Parameters: k = 3, eta1 = eta2 = 2, du = 10, dv = 4.
+//! Cross-checked against OpenSSL 3.5 with the vectors in `testdata/mlkem768.kat`; these are our
+//! own OpenSSL outputs, not the NIST/ACVP known-answer vectors.
+//! Arithmetic mod q uses Barrett reduction and compression multiplies and shifts instead of
+//! dividing, so no secret-dependent value goes through a variable-time division (KyberSlash).
+//! Decapsulation uses implicit rejection: a bad ciphertext yields a pseudorandom key, not an error.
+
+use std::fmt;
+
+use rand::{RngCore, rngs::OsRng};
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::{Digest, Sha3_256, Sha3_512, Shake128, Shake256};
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+use zeroize::Zeroizing;
+
+use super::secret::SharedSecret;
+
+const N: usize = 256;
+const Q: u32 = 3329;
+const K: usize = 3;
+const ETA: usize = 2;
+const DU: usize = 10;
+const DV: usize = 4;
+
+pub const ENCAPSULATION_KEY_LEN: usize = 384 * K + 32;
+pub const DECAPSULATION_KEY_LEN: usize = 768 * K + 96;
+pub const CIPHERTEXT_LEN: usize = 32 * (DU * K + DV);
+pub const SHARED_SECRET_LEN: usize = 32;
+/// d || z, as used by `generate_from_seed`.
+pub const SEED_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MlKemError {
+    /// Wrong length, or a coefficient is not reduced mod q (FIPS 203 §7.2 modulus check).
+    InvalidEncapsulationKey,
+    /// Wrong length, or the embedded hash of the encapsulation key does not match (§7.3 hash check).
+    InvalidDecapsulationKey,
+    WrongCiphertextLength,
+}
+
+impl fmt::Display for MlKemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MlKemError::InvalidEncapsulationKey => write!(f, "invalid ML-KEM-768 encapsulation key"),
+            MlKemError::InvalidDecapsulationKey => write!(f, "invalid ML-KEM-768 decapsulation key"),
+            MlKemError::WrongCiphertextLength => write!(f, "ML-KEM-768 ciphertext must be {CIPHERTEXT_LEN} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for MlKemError {}
+
+/// Polynomial in R_q, coefficients in [0, q).
+type Poly = [u16; N];
+
+/// zeta^BitRev7(i) mod q, zeta = 17
+const ZETAS: [u16; 128] = {
+    let mut out = [0u16; 128];
+    let mut i = 0;
+    while i < 128 {
+        out[i] = pow17((i as u8).reverse_bits() as u32 >> 1);
+        i += 1;
+    }
+    out
+};
+
+/// zeta^(2 BitRev7(i) + 1) mod q, for the base case multiplications
+const GAMMAS: [u16; 128] = {
+    let mut out = [0u16; 128];
+    let mut i = 0;
+    while i < 128 {
+        out[i] = pow17(2 * ((i as u8).reverse_bits() as u32 >> 1) + 1);
+        i += 1;
+    }
+    out
+};
+
+const fn pow17(e: u32) -> u16 {
+    let mut r = 1u16;
+    let mut i = 0;
+    while i < e {
+        r = mul(r, 17);
+        i += 1;
+    }
+    r
+}
+
+/// floor(2^36 / q), for Barrett reduction of 32-bit values.
+const BARRETT_MULTIPLIER: u64 = (1 << 36) / Q as u64;
+
+/// a - q if a >= q, for a < 2q, without branching on a.
+const fn csubq(a: u32) -> u16 {
+    let r = a.wrapping_sub(Q);
+    // r >> 31 is 1 exactly when a < q
+    r.wrapping_add(Q & 0u32.wrapping_sub(r >> 31)) as u16
+}
+
+/// x mod q by Barrett reduction: the quotient estimate is at most one short, so x - t q < 2q.
+const fn reduce(x: u32) -> u16 {
+    let t = ((x as u64 * BARRETT_MULTIPLIER) >> 36) as u32;
+    csubq(x - t * Q)
+}
+
+fn add(a: u16, b: u16) -> u16 {
+    csubq(a as u32 + b as u32)
+}
+
+fn sub(a: u16, b: u16) -> u16 {
+    csubq(a as u32 + Q - b as u32)
+}
+
+const fn mul(a: u16, b: u16) -> u16 {
+    reduce(a as u32 * b as u32)
+}
+
+/// Algorithm 9
+fn ntt(f: &mut Poly) {
+    let mut i = 1;
+    let mut len = 128;
+    while len >= 2 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = ZETAS[i];
+            i += 1;
+            for j in start..start + len {
+                let t = mul(zeta, f[j + len]);
+                f[j + len] = sub(f[j], t);
+                f[j] = add(f[j], t);
+            }
+        }
+        len /= 2;
+    }
+}
+
+/// Algorithm 10
+fn ntt_inverse(f: &mut Poly) {
+    let mut i = 127;
+    let mut len = 2;
+    while len <= 128 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = ZETAS[i];
+            i -= 1;
+            for j in start..start + len {
+                let t = f[j];
+                f[j] = add(t, f[j + len]);
+                f[j + len] = mul(zeta, sub(f[j + len], t));
+            }
+        }
+        len *= 2;
+    }
+    for c in f.iter_mut() {
+        *c = mul(*c, 3303); // 128^-1 mod q
+    }
+}
+
+/// Algorithms 11 and 12: f * g in the NTT domain.
+fn multiply_ntts(f: &Poly, g: &Poly) -> Poly {
+    let mut h = [0u16; N];
+    for i in 0..128 {
+        let (a0, a1, b0, b1) = (f[2 * i], f[2 * i + 1], g[2 * i], g[2 * i + 1]);
+        h[2 * i] = add(mul(a0, b0), mul(mul(a1, b1), GAMMAS[i]));
+        h[2 * i + 1] = add(mul(a0, b1), mul(a1, b0));
+    }
+    h
+}
+
+fn poly_add(a: &Poly, b: &Poly) -> Poly {
+    std::array::from_fn(|i| add(a[i], b[i]))
+}
+
+fn poly_sub(a: &Poly, b: &Poly) -> Poly {
+    std::array::from_fn(|i| sub(a[i], b[i]))
+}
+
+/// Algorithm 7: rejection-sample a polynomial in the NTT domain from SHAKE128(rho || j || i).
+fn sample_ntt(rho: &[u8; 32], j: u8, i: u8) -> Poly {
+    let mut xof = Shake128::default();
+    xof.update(rho);
+    xof.update(&[j, i]);
+    let mut reader = xof.finalize_xof();
+
+    let mut a = [0u16; N];
+    let mut filled = 0;
+    let mut c = [0u8; 3];
+    while filled < N {
+        reader.read(&mut c);
+        let d1 = c[0] as u16 | ((c[1] as u16 & 0x0f) << 8);
+        let d2 = (c[1] as u16 >> 4) | ((c[2] as u16) << 4);
+        if (d1 as u32) < Q {
+            a[filled] = d1;
+            filled += 1;
+        }
+        if (d2 as u32) < Q && filled < N {
+            a[filled] = d2;
+            filled += 1;
+        }
+    }
+    a
+}
+
+/// Algorithm 8 with PRF_eta(s, b) = SHAKE256(s || b) (eta = 2).
+fn sample_cbd(seed: &[u8; 32], nonce: u8) -> Poly {
+    let mut xof = Shake256::default();
+    xof.update(seed);
+    xof.update(&[nonce]);
+    let mut bytes = [0u8; 64 * ETA];
+    xof.finalize_xof().read(&mut bytes);
+
+    std::array::from_fn(|i| {
+        // 4 bits per coefficient: x = b0 + b1, y = b2 + b3
+        let bits = (bytes[i / 2] >> (4 * (i % 2))) & 0x0f;
+        let x = (bits & 1) + ((bits >> 1) & 1);
+        let y = ((bits >> 2) & 1) + ((bits >> 3) & 1);
+        sub(x as u16, y as u16)
+    })
+}
+
+/// Algorithm 5: pack d-bit coefficients, least significant bit first.
+fn byte_encode(f: &Poly, d: usize, out: &mut [u8]) {
+    debug_assert_eq!(out.len(), 32 * d);
+    let mut acc = 0u32;
+    let mut acc_bits = 0;
+    let mut pos = 0;
+    for &c in f {
+        acc |= (c as u32) << acc_bits;
+        acc_bits += d;
+        while acc_bits >= 8 {
+            out[pos] = acc as u8;
+            pos += 1;
+            acc >>= 8;
+            acc_bits -= 8;
+        }
+    }
+}
+
+/// Algorithm 6. For d = 12 values are reduced mod q.
+fn byte_decode(bytes: &[u8], d: usize) -> Poly {
+    let mut f = [0u16; N];
+    let mut acc = 0u32;
+    let mut acc_bits = 0;
+    let mut pos = 0;
+    for c in f.iter_mut() {
+        while acc_bits < d {
+            acc |= (bytes[pos] as u32) << acc_bits;
+            pos += 1;
+            acc_bits += 8;
+        }
+        let v = acc & ((1 << d) - 1);
+        acc >>= d;
+        acc_bits -= d;
+        *c = if d == 12 { csubq(v) } else { v as u16 };
+    }
+    f
+}
+
+/// ceil(2^40 / q): for n < 2^22, (n * COMPRESS_MULTIPLIER) >> 40 = floor(n / q).
+const COMPRESS_MULTIPLIER: u64 = (1 << 40) / Q as u64 + 1;
+
+/// round(2^d / q * x) mod 2^d, dividing by multiply-and-shift: a `/ Q` may compile to a
+/// variable-time division (KyberSlash).
+fn compress(f: &Poly, d: usize) -> Poly {
+    std::array::from_fn(|i| {
+        let n = ((f[i] as u64) << d) + (Q / 2) as u64;
+        (((n * COMPRESS_MULTIPLIER) >> 40) & ((1 << d) - 1)) as u16
+    })
+}
+
+/// round(q / 2^d * y)
+fn decompress(f: &Poly, d: usize) -> Poly {
+    std::array::from_fn(|i| ((f[i] as u32 * Q + (1 << (d - 1))) >> d) as u16)
+}
+
+fn g(parts: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    let mut h = Sha3_512::new();
+    for p in parts {
+        Digest::update(&mut h, p);
+    }
+    let out = h.finalize();
+    (out[..32].try_into().unwrap(), out[32..].try_into().unwrap())
+}
+
+fn h(bytes: &[u8]) -> [u8; 32] {
+    Sha3_256::digest(bytes).into()
+}
+
+/// J(z || c) = SHAKE256(z || c, 32): the implicit-rejection key.
+fn j(z: &[u8], c: &[u8]) -> [u8; 32] {
+    let mut xof = Shake256::default();
+    xof.update(z);
+    xof.update(c);
+    let mut out = [0u8; 32];
+    xof.finalize_xof().read(&mut out);
+    out
+}
+
+fn matrix(rho: &[u8; 32]) -> [[Poly; K]; K] {
+    std::array::from_fn(|i| std::array::from_fn(|j| sample_ntt(rho, j as u8, i as u8)))
+}
+
+/// Algorithm 13: returns (ek, dk_pke).
+fn pke_keygen(d: &[u8; 32]) -> (Vec<u8>, Vec<u8>) {
+    let (rho, sigma) = g(&[d, &[K as u8]]);
+    let a = matrix(&rho);
+    let mut s: [Poly; K] = std::array::from_fn(|i| sample_cbd(&sigma, i as u8));
+    let mut e: [Poly; K] = std::array::from_fn(|i| sample_cbd(&sigma, (K + i) as u8));
+    s.iter_mut().for_each(ntt);
+    e.iter_mut().for_each(ntt);
+
+    let mut ek = vec![0u8; ENCAPSULATION_KEY_LEN];
+    let mut dk = vec![0u8; 384 * K];
+    for i in 0..K {
+        let t = (0..K).fold(e[i], |acc, j| poly_add(&acc, &multiply_ntts(&a[i][j], &s[j])));
+        byte_encode(&t, 12, &mut ek[384 * i..384 * (i + 1)]);
+        byte_encode(&s[i], 12, &mut dk[384 * i..384 * (i + 1)]);
+    }
+    ek[384 * K..].copy_from_slice(&rho);
+    (ek, dk)
+}
+
+/// Algorithm 14
+fn pke_encrypt(ek: &[u8], m: &[u8; 32], r: &[u8; 32]) -> Vec<u8> {
+    let t: [Poly; K] = std::array::from_fn(|i| byte_decode(&ek[384 * i..384 * (i + 1)], 12));
+    let rho: [u8; 32] = ek[384 * K..].try_into().unwrap();
+    let a = matrix(&rho);
+
+    let mut y: [Poly; K] = std::array::from_fn(|i| sample_cbd(r, i as u8));
+    let e1: [Poly; K] = std::array::from_fn(|i| sample_cbd(r, (K + i) as u8));
+    let e2 = sample_cbd(r, 2 * K as u8);
+    y.iter_mut().for_each(ntt);
+
+    let mut c = vec![0u8; CIPHERTEXT_LEN];
+    for i in 0..K {
+        let mut u = (0..K).fold([0u16; N], |acc, j| poly_add(&acc, &multiply_ntts(&a[j][i], &y[j])));
+        ntt_inverse(&mut u);
+        let u = poly_add(&u, &e1[i]);
+        byte_encode(&compress(&u, DU), DU, &mut c[32 * DU * i..32 * DU * (i + 1)]);
+    }
+
+    let mut v = (0..K).fold([0u16; N], |acc, i| poly_add(&acc, &multiply_ntts(&t[i], &y[i])));
+    ntt_inverse(&mut v);
+    let mu = decompress(&byte_decode(m, 1), 1);
+    let v = poly_add(&poly_add(&v, &e2), &mu);
+    byte_encode(&compress(&v, DV), DV, &mut c[32 * DU * K..]);
+    c
+}
+
+/// Algorithm 15
+fn pke_decrypt(dk_pke: &[u8], c: &[u8]) -> [u8; 32] {
+    let mut w = decompress(&byte_decode(&c[32 * DU * K..], DV), DV);
+    let mut acc = [0u16; N];
+    for i in 0..K {
+        let mut u = decompress(&byte_decode(&c[32 * DU * i..32 * DU * (i + 1)], DU), DU);
+        ntt(&mut u);
+        let s = byte_decode(&dk_pke[384 * i..384 * (i + 1)], 12);
+        acc = poly_add(&acc, &multiply_ntts(&s, &u));
+    }
+    ntt_inverse(&mut acc);
+    w = poly_sub(&w, &acc);
+
+    let mut m = [0u8; 32];
+    byte_encode(&compress(&w, 1), 1, &mut m);
+    m
+}
+
+/// Public encapsulation key (1184 bytes).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EncapsulationKey(Vec<u8>);
+
+/// Private decapsulation key: dk_pke || ek || H(ek) || z (2400 bytes).
+#[derive(Clone)]
+pub struct DecapsulationKey(Zeroizing<Vec<u8>>);
+
+/// Fresh key pair from the OS RNG.
+pub fn generate() -> (DecapsulationKey, EncapsulationKey) {
+    let mut seed = [0u8; SEED_LEN];
+    OsRng.fill_bytes(&mut seed);
+    generate_from_seed(&seed)
+}
+
+/// Algorithm 16 (ML-KEM.KeyGen_internal) with seed = d || z.
+pub fn generate_from_seed(seed: &[u8; SEED_LEN]) -> (DecapsulationKey, EncapsulationKey) {
+    let (d, z) = seed.split_at(32);
+    let (ek, dk_pke) = pke_keygen(d.try_into().unwrap());
+    let dk = Zeroizing::new([&dk_pke[..], &ek, &h(&ek), z].concat());
+    (DecapsulationKey(dk), EncapsulationKey(ek))
+}
+
+impl EncapsulationKey {
+    /// Parse and run the modulus check.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MlKemError> {
+        if bytes.len() != ENCAPSULATION_KEY_LEN {
+            return Err(MlKemError::InvalidEncapsulationKey);
+        }
+        let mut reencoded = [0u8; 384];
+        for chunk in bytes[..384 * K].chunks(384) {
+            byte_encode(&byte_decode(chunk, 12), 12, &mut reencoded);
+            if reencoded[..] != *chunk {
+                return Err(MlKemError::InvalidEncapsulationKey);
+            }
+        }
+        Ok(Self(bytes.to_vec()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns (ciphertext, shared secret).
+    pub fn encapsulate(&self) -> (Vec<u8>, SharedSecret<SHARED_SECRET_LEN>) {
+        let mut m = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(&mut *m);
+        self.encapsulate_deterministic(&m)
+    }
+
+    /// Algorithm 17 (ML-KEM.Encaps_internal); `m` must be fresh randomness outside of tests.
+    fn encapsulate_deterministic(&self, m: &[u8; 32]) -> (Vec<u8>, SharedSecret<SHARED_SECRET_LEN>) {
+        let (shared, r) = g(&[m, &h(&self.0)]);
+        (pke_encrypt(&self.0, m, &Zeroizing::new(r)), SharedSecret::new(shared))
+    }
+}
+
+impl DecapsulationKey {
+    /// Parse and run the hash check on the embedded encapsulation key.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MlKemError> {
+        if bytes.len() != DECAPSULATION_KEY_LEN {
+            return Err(MlKemError::InvalidDecapsulationKey);
+        }
+        let ek = &bytes[384 * K..768 * K + 32];
+        if h(ek) != bytes[768 * K + 32..768 * K + 64] {
+            return Err(MlKemError::InvalidDecapsulationKey);
+        }
+        Ok(Self(Zeroizing::new(bytes.to_vec())))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn encapsulation_key(&self) -> EncapsulationKey {
+        EncapsulationKey(self.0[384 * K..768 * K + 32].to_vec())
+    }
+
+    /// Algorithm 18 (ML-KEM.Decaps_internal). Only the length is checked; a forged or corrupted
+    /// ciphertext decapsulates to an unrelated key and is caught by the protocol's key confirmation.
+    pub fn decapsulate(&self, c: &[u8]) -> Result<SharedSecret<SHARED_SECRET_LEN>, MlKemError> {
+        if c.len() != CIPHERTEXT_LEN {
+            return Err(MlKemError::WrongCiphertextLength);
+        }
+        let dk_pke = &self.0[..384 * K];
+        let ek = &self.0[384 * K..768 * K + 32];
+        let hash = &self.0[768 * K + 32..768 * K + 64];
+        let z = &self.0[768 * K + 64..];
+
+        let m = Zeroizing::new(pke_decrypt(dk_pke, c));
+        let (shared, r) = g(&[&*m, hash]);
+        let (mut shared, r) = (SharedSecret::new(shared), Zeroizing::new(r));
+        let rejected = Zeroizing::new(j(z, c));
+        let matches = pke_encrypt(ek, &m, &r).ct_eq(c);
+        for (k, bar) in shared.expose_secret_mut().iter_mut().zip(rejected.iter()) {
+            k.conditional_assign(bar, !matches);
+        }
+        Ok(shared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        hex::decode(s.trim()).unwrap()
+    }
+
+    /// Vectors generated with OpenSSL 3.5.6 (an independent implementation, not NIST's):
+    ///   openssl genpkey -algorithm ML-KEM-768 -pkeyopt hexseed:<seed> -out dk.pem
+    ///   openssl pkey -in dk.pem -pubout -out ek.pem
+    ///   openssl pkeyutl -encap -pubin -inkey ek.pem -pkeyopt hexikme:<m> -out ct -secret ss
+    ///   openssl pkeyutl -decap -inkey dk.pem -in ct_with_last_byte_flipped   (ss_rejected)
+    /// Keys and ciphertexts are stored as their SHA3-256 digests to keep the file small.
+    #[test]
+    fn openssl_cross_check() {
+        let kat = include_str!("testdata/mlkem768.kat");
+        let mut count = 0;
+        for block in kat.split("\n\n").filter(|b| b.starts_with("count = ")) {
+            let field = |name: &str| {
+                let line = block.lines().find(|l| l.starts_with(&format!("{name} = "))).unwrap();
+                unhex(&line[name.len() + 3..])
+            };
+            let seed: [u8; SEED_LEN] = field("seed").try_into().unwrap();
+            let m: [u8; 32] = field("m").try_into().unwrap();
+
+            let (dk, ek) = generate_from_seed(&seed);
+            assert_eq!(h(ek.as_bytes()).to_vec(), field("ek_sha3_256"));
+            assert_eq!(h(dk.as_bytes()).to_vec(), field("dk_sha3_256"));
+
+            let (ct, ss) = ek.encapsulate_deterministic(&m);
+            assert_eq!(h(&ct).to_vec(), field("ct_sha3_256"));
+            assert_eq!(ss.expose_secret().to_vec(), field("ss"));
+            assert_eq!(dk.decapsulate(&ct).unwrap(), ss);
+
+            let mut bad = ct.clone();
+            bad[CIPHERTEXT_LEN - 1] ^= 1;
+            assert_eq!(dk.decapsulate(&bad).unwrap().expose_secret().to_vec(), field("ss_rejected"));
+            count += 1;
+        }
+        assert!(count >= 10);
+    }
+
+    #[test]
+    fn reduction_and_compression_match_division() {
+        for x in (0..Q * Q).step_by(7).chain([Q * Q - 1, u32::MAX]) {
+            assert_eq!(reduce(x) as u32, x % Q, "{x}");
+        }
+        for x in 0..2 * Q {
+            assert_eq!(csubq(x) as u32, x % Q);
+        }
+        for d in [1, DV, DU] {
+            for start in (0..Q).step_by(N) {
+                let f: Poly = std::array::from_fn(|i| (start + i as u32).min(Q - 1) as u16);
+                for (&x, &y) in f.iter().zip(compress(&f, d).iter()) {
+                    let x = x as u32;
+                    assert_eq!(y as u32, (((x << d) + Q / 2) / Q) & ((1 << d) - 1), "d = {d}, x = {x}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrip_and_key_checks() {
+        let (dk, ek) = generate();
+        let (ct, ss) = ek.encapsulate();
+        assert_eq!(dk.decapsulate(&ct).unwrap(), ss);
+        assert_eq!(dk.encapsulation_key(), ek);
+
+        let restored = DecapsulationKey::from_bytes(dk.as_bytes()).unwrap();
+        assert_eq!(restored.decapsulate(&ct).unwrap(), ss);
+        assert_eq!(EncapsulationKey::from_bytes(ek.as_bytes()).unwrap(), ek);
+
+        // coefficient 0xfff >= q fails the modulus check
+        let mut unreduced = ek.as_bytes().to_vec();
+        unreduced[0] = 0xff;
+        unreduced[1] |= 0x0f;
+        assert_eq!(EncapsulationKey::from_bytes(&unreduced), Err(MlKemError::InvalidEncapsulationKey));
+
+        let mut tampered = dk.as_bytes().to_vec();
+        tampered[384 * K] ^= 1;
+        assert!(matches!(DecapsulationKey::from_bytes(&tampered), Err(MlKemError::InvalidDecapsulationKey)));
+        assert_eq!(dk.decapsulate(&ct[1..]), Err(MlKemError::WrongCiphertextLength));
+    }
+}
@@ -1,7 +1,15 @@
+pub mod secret;
 pub mod dhke;
+pub mod x448;
+pub mod key_agreement;
+pub mod mlkem;
+pub mod kem;
 pub mod hkdf;
 pub mod aead;
 pub mod signdemo;
+pub mod signature_scheme;
+pub mod frost;
 pub mod key_extract;
 pub mod hmac;
-pub mod vec_bytes;
\ No newline at end of file
+pub mod envelope;
+pub mod aead_key;
\ No newline at end of file
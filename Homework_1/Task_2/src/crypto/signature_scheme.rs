@@ -0,0 +1,424 @@
+//! One interface over the signature algorithms we support, so the handshake can pick one at runtime.
+//! - `Ed25519`: RFC 8032 PureEdDSA (`signdemo::sign`, checked with `signdemo::verify_strict`).
+//! - `Ed25519ph`: RFC 8032 HashEdDSA, Ed25519 over SHA-512(msg) with an empty context (`signdemo::sign_prehashed`).
+//! - `EcdsaSecp256k1`: ECDSA over secp256k1 with SHA-256 (the teaching implementation in Task_3's `ecdsademo`).
+//! - `EcdsaP256`: ECDSA over P-256 with SHA-256 and RFC 6979 nonces.
+//!
+//! Encodings: signatures are fixed-size 64 bytes (r || s for ECDSA), verifying keys are 32 bytes for
+//! Ed25519 and SEC1 compressed points for ECDSA, signing keys are the 32-byte seed / scalar.
+
+use std::fmt;
+
+use ecdsademo::crypto::ecdsademo as secp256k1_demo;
+use ed25519_dalek::{Signature as Ed25519Signature, SigningKey, VerifyingKey};
+use p256::ecdsa::signature::{Signer, Verifier};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha512};
+
+use super::secret::SecretKey;
+use super::signdemo;
+
+pub const SIGNATURE_LEN: usize = 64;
+pub const SIGNING_KEY_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    InvalidSigningKey,
+    InvalidVerifyingKey,
+    /// Wrong length, or r / s out of range.
+    MalformedSignature,
+    /// Well-formed, but not a signature on this message under this key.
+    VerificationFailed,
+    /// The signer hit a degenerate nonce (r = 0 or s = 0); signing again with a fresh nonce succeeds.
+    SigningFailed,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureError::InvalidSigningKey => write!(f, "invalid signing key"),
+            SignatureError::InvalidVerifyingKey => write!(f, "invalid verifying key"),
+            SignatureError::MalformedSignature => write!(f, "malformed signature"),
+            SignatureError::VerificationFailed => write!(f, "signature verification failed"),
+            SignatureError::SigningFailed => write!(f, "signing failed"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+pub trait SignatureScheme {
+    /// Which `SignatureAlgorithm` this is.
+    const ALGORITHM: SignatureAlgorithm;
+    type SigningKey;
+    type VerifyingKey: Clone + PartialEq + fmt::Debug;
+
+    fn generate() -> (Self::SigningKey, Self::VerifyingKey);
+    fn verifying_key(sk: &Self::SigningKey) -> Self::VerifyingKey;
+    fn sign(sk: &Self::SigningKey, msg: &[u8]) -> Result<[u8; SIGNATURE_LEN], SignatureError>;
+    fn verify(pk: &Self::VerifyingKey, msg: &[u8], sig: &[u8]) -> Result<(), SignatureError>;
+
+    fn encode_signing_key(sk: &Self::SigningKey) -> SecretKey<SIGNING_KEY_LEN>;
+    fn decode_signing_key(bytes: &[u8]) -> Result<Self::SigningKey, SignatureError>;
+    fn encode_verifying_key(pk: &Self::VerifyingKey) -> Vec<u8>;
+    fn decode_verifying_key(bytes: &[u8]) -> Result<Self::VerifyingKey, SignatureError>;
+}
+
+pub struct Ed25519;
+pub struct Ed25519ph;
+pub struct EcdsaSecp256k1;
+pub struct EcdsaP256;
+
+fn ed25519_signature(sig: &[u8]) -> Result<Ed25519Signature, SignatureError> {
+    Ed25519Signature::from_slice(sig).map_err(|_| SignatureError::MalformedSignature)
+}
+
+/// Key handling shared by Ed25519 and Ed25519ph.
+macro_rules! ed25519_keys {
+    () => {
+        type SigningKey = SigningKey;
+        type VerifyingKey = VerifyingKey;
+
+        fn generate() -> (Self::SigningKey, Self::VerifyingKey) {
+            let kp = signdemo::keygen();
+            (kp.sk, kp.pk)
+        }
+
+        fn verifying_key(sk: &Self::SigningKey) -> Self::VerifyingKey {
+            sk.verifying_key()
+        }
+
+        fn encode_signing_key(sk: &Self::SigningKey) -> SecretKey<SIGNING_KEY_LEN> {
+            SecretKey::new(sk.to_bytes())
+        }
+
+        fn decode_signing_key(bytes: &[u8]) -> Result<Self::SigningKey, SignatureError> {
+            let seed = SecretKey::<SIGNING_KEY_LEN>::from_slice(bytes).ok_or(SignatureError::InvalidSigningKey)?;
+            Ok(SigningKey::from_bytes(seed.expose_secret()))
+        }
+
+        fn encode_verifying_key(pk: &Self::VerifyingKey) -> Vec<u8> {
+            pk.to_bytes().to_vec()
+        }
+
+        fn decode_verifying_key(bytes: &[u8]) -> Result<Self::VerifyingKey, SignatureError> {
+            let bytes: [u8; 32] = bytes.try_into().map_err(|_| SignatureError::InvalidVerifyingKey)?;
+            VerifyingKey::from_bytes(&bytes).map_err(|_| SignatureError::InvalidVerifyingKey)
+        }
+    };
+}
+
+impl SignatureScheme for Ed25519 {
+    const ALGORITHM: SignatureAlgorithm = SignatureAlgorithm::Ed25519;
+    ed25519_keys!();
+
+    fn sign(sk: &Self::SigningKey, msg: &[u8]) -> Result<[u8; SIGNATURE_LEN], SignatureError> {
+        Ok(signdemo::sign(sk, msg).to_bytes())
+    }
+
+    fn verify(pk: &Self::VerifyingKey, msg: &[u8], sig: &[u8]) -> Result<(), SignatureError> {
+        match signdemo::verify_strict(pk, msg, &ed25519_signature(sig)?) {
+            true => Ok(()),
+            false => Err(SignatureError::VerificationFailed),
+        }
+    }
+}
+
+impl SignatureScheme for Ed25519ph {
+    const ALGORITHM: SignatureAlgorithm = SignatureAlgorithm::Ed25519ph;
+    ed25519_keys!();
+
+    fn sign(sk: &Self::SigningKey, msg: &[u8]) -> Result<[u8; SIGNATURE_LEN], SignatureError> {
+        let sig = signdemo::sign_prehashed(sk, Sha512::new().chain_update(msg), b"").map_err(|_| SignatureError::SigningFailed)?;
+        Ok(sig.to_bytes())
+    }
+
+    fn verify(pk: &Self::VerifyingKey, msg: &[u8], sig: &[u8]) -> Result<(), SignatureError> {
+        match signdemo::verify_prehashed(pk, Sha512::new().chain_update(msg), b"", &ed25519_signature(sig)?) {
+            true => Ok(()),
+            false => Err(SignatureError::VerificationFailed),
+        }
+    }
+}
+
+impl SignatureScheme for EcdsaSecp256k1 {
+    const ALGORITHM: SignatureAlgorithm = SignatureAlgorithm::EcdsaSecp256k1;
+    type SigningKey = secp256k1_demo::Keypair;
+    type VerifyingKey = k256::AffinePoint;
+
+    fn generate() -> (Self::SigningKey, Self::VerifyingKey) {
+        let kp = secp256k1_demo::Keypair::generate();
+        let q = kp.q;
+        (kp, q)
+    }
+
+    fn verifying_key(sk: &Self::SigningKey) -> Self::VerifyingKey {
+        sk.q
+    }
+
+    fn sign(sk: &Self::SigningKey, msg: &[u8]) -> Result<[u8; SIGNATURE_LEN], SignatureError> {
+        let (r, s) = secp256k1_demo::sign(&sk.d, msg).map_err(|_| SignatureError::SigningFailed)?;
+        let mut sig = [0u8; SIGNATURE_LEN];
+        sig[..32].copy_from_slice(&r);
+        sig[32..].copy_from_slice(&s);
+        Ok(sig)
+    }
+
+    fn verify(pk: &Self::VerifyingKey, msg: &[u8], sig: &[u8]) -> Result<(), SignatureError> {
+        let sig: &[u8; SIGNATURE_LEN] = sig.try_into().map_err(|_| SignatureError::MalformedSignature)?;
+        let (r, s) = sig.split_at(32);
+        match secp256k1_demo::verify(pk, msg, r.try_into().unwrap(), s.try_into().unwrap()) {
+            true => Ok(()),
+            false => Err(SignatureError::VerificationFailed),
+        }
+    }
+
+    fn encode_signing_key(sk: &Self::SigningKey) -> SecretKey<SIGNING_KEY_LEN> {
+        SecretKey::new(*sk.private_bytes().expose_secret())
+    }
+
+    fn decode_signing_key(bytes: &[u8]) -> Result<Self::SigningKey, SignatureError> {
+        let sk = k256::SecretKey::from_slice(bytes).map_err(|_| SignatureError::InvalidSigningKey)?;
+        let q = *sk.public_key().as_affine();
+        Ok(secp256k1_demo::Keypair { d: *sk.to_nonzero_scalar(), q })
+    }
+
+    fn encode_verifying_key(pk: &Self::VerifyingKey) -> Vec<u8> {
+        pk.to_encoded_point(true).as_bytes().to_vec()
+    }
+
+    fn decode_verifying_key(bytes: &[u8]) -> Result<Self::VerifyingKey, SignatureError> {
+        let pk = k256::PublicKey::from_sec1_bytes(bytes).map_err(|_| SignatureError::InvalidVerifyingKey)?;
+        Ok(*pk.as_affine())
+    }
+}
+
+impl SignatureScheme for EcdsaP256 {
+    const ALGORITHM: SignatureAlgorithm = SignatureAlgorithm::EcdsaP256;
+    type SigningKey = p256::ecdsa::SigningKey;
+    type VerifyingKey = p256::ecdsa::VerifyingKey;
+
+    fn generate() -> (Self::SigningKey, Self::VerifyingKey) {
+        let sk = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let pk = *sk.verifying_key();
+        (sk, pk)
+    }
+
+    fn verifying_key(sk: &Self::SigningKey) -> Self::VerifyingKey {
+        *sk.verifying_key()
+    }
+
+    fn sign(sk: &Self::SigningKey, msg: &[u8]) -> Result<[u8; SIGNATURE_LEN], SignatureError> {
+        let sig: p256::ecdsa::Signature = sk.try_sign(msg).map_err(|_| SignatureError::SigningFailed)?;
+        Ok(sig.to_bytes().into())
+    }
+
+    fn verify(pk: &Self::VerifyingKey, msg: &[u8], sig: &[u8]) -> Result<(), SignatureError> {
+        let sig = p256::ecdsa::Signature::from_slice(sig).map_err(|_| SignatureError::MalformedSignature)?;
+        pk.verify(msg, &sig).map_err(|_| SignatureError::VerificationFailed)
+    }
+
+    fn encode_signing_key(sk: &Self::SigningKey) -> SecretKey<SIGNING_KEY_LEN> {
+        SecretKey::new(sk.to_bytes().into())
+    }
+
+    fn decode_signing_key(bytes: &[u8]) -> Result<Self::SigningKey, SignatureError> {
+        p256::ecdsa::SigningKey::from_slice(bytes).map_err(|_| SignatureError::InvalidSigningKey)
+    }
+
+    fn encode_verifying_key(pk: &Self::VerifyingKey) -> Vec<u8> {
+        pk.to_encoded_point(true).as_bytes().to_vec()
+    }
+
+    fn decode_verifying_key(bytes: &[u8]) -> Result<Self::VerifyingKey, SignatureError> {
+        p256::ecdsa::VerifyingKey::from_sec1_bytes(bytes).map_err(|_| SignatureError::InvalidVerifyingKey)
+    }
+}
+
+/// Runtime selector for a `SignatureScheme`, e.g. from the command line or a handshake message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    Ed25519,
+    Ed25519ph,
+    EcdsaSecp256k1,
+    EcdsaP256,
+}
+
+/// Dispatch `$body` with `S` bound to the `SignatureScheme` type of `$alg`.
+macro_rules! with_scheme {
+    ($alg:expr, $s:ident => $body:expr) => {
+        match $alg {
+            SignatureAlgorithm::Ed25519 => { type $s = Ed25519; $body }
+            SignatureAlgorithm::Ed25519ph => { type $s = Ed25519ph; $body }
+            SignatureAlgorithm::EcdsaSecp256k1 => { type $s = EcdsaSecp256k1; $body }
+            SignatureAlgorithm::EcdsaP256 => { type $s = EcdsaP256; $body }
+        }
+    };
+}
+
+impl SignatureAlgorithm {
+    pub const ALL: [SignatureAlgorithm; 4] = [
+        SignatureAlgorithm::Ed25519,
+        SignatureAlgorithm::Ed25519ph,
+        SignatureAlgorithm::EcdsaSecp256k1,
+        SignatureAlgorithm::EcdsaP256,
+    ];
+
+    /// 2-byte identifier used on the wire: the TLS SignatureScheme code point where one exists,
+    /// otherwise a value from the private-use range.
+    pub fn id(self) -> u16 {
+        match self {
+            SignatureAlgorithm::Ed25519 => 0x0807,
+            SignatureAlgorithm::Ed25519ph => 0xfe01,
+            SignatureAlgorithm::EcdsaSecp256k1 => 0xfe02,
+            SignatureAlgorithm::EcdsaP256 => 0x0403,
+        }
+    }
+
+    pub fn from_id(id: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.id() == id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SignatureAlgorithm::Ed25519 => "ed25519",
+            SignatureAlgorithm::Ed25519ph => "ed25519ph",
+            SignatureAlgorithm::EcdsaSecp256k1 => "ecdsa-secp256k1",
+            SignatureAlgorithm::EcdsaP256 => "ecdsa-p256",
+        }
+    }
+
+    /// Case-insensitive lookup by `name()`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Fresh key pair as (signing key, encoded verifying key).
+    pub fn generate(self) -> (SecretKey<SIGNING_KEY_LEN>, Vec<u8>) {
+        with_scheme!(self, S => {
+            let (sk, pk) = S::generate();
+            (S::encode_signing_key(&sk), S::encode_verifying_key(&pk))
+        })
+    }
+
+    pub fn verifying_key(self, sk: &[u8]) -> Result<Vec<u8>, SignatureError> {
+        with_scheme!(self, S => Ok(S::encode_verifying_key(&S::verifying_key(&S::decode_signing_key(sk)?))))
+    }
+
+    pub fn sign(self, sk: &[u8], msg: &[u8]) -> Result<[u8; SIGNATURE_LEN], SignatureError> {
+        with_scheme!(self, S => S::sign(&S::decode_signing_key(sk)?, msg))
+    }
+
+    pub fn verify(self, pk: &[u8], msg: &[u8], sig: &[u8]) -> Result<(), SignatureError> {
+        with_scheme!(self, S => S::verify(&S::decode_verifying_key(pk)?, msg, sig))
+    }
+}
+
+impl fmt::Display for SignatureAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    /// Written once against the trait: sign / verify, key encodings and rejection of wrong inputs.
+    fn check_scheme<S: SignatureScheme>() {
+        let (sk, pk) = S::generate();
+        assert_eq!(S::verifying_key(&sk), pk);
+        let sig = S::sign(&sk, b"transcript").unwrap();
+        assert_eq!(S::verify(&pk, b"transcript", &sig), Ok(()));
+        assert_eq!(S::verify(&pk, b"transcripT", &sig), Err(SignatureError::VerificationFailed));
+        assert_eq!(S::verify(&pk, b"transcript", &sig[1..]), Err(SignatureError::MalformedSignature));
+
+        let (_, other_pk) = S::generate();
+        assert_eq!(S::verify(&other_pk, b"transcript", &sig), Err(SignatureError::VerificationFailed));
+
+        let restored = S::decode_signing_key(S::encode_signing_key(&sk).expose_secret()).unwrap();
+        assert_eq!(S::verifying_key(&restored), pk);
+        assert_eq!(S::decode_verifying_key(&S::encode_verifying_key(&pk)).unwrap(), pk);
+        assert!(S::decode_verifying_key(&[0xff; 33]).is_err());
+    }
+
+    #[test]
+    fn all_schemes_sign_and_verify() {
+        check_scheme::<Ed25519>();
+        check_scheme::<Ed25519ph>();
+        check_scheme::<EcdsaSecp256k1>();
+        check_scheme::<EcdsaP256>();
+    }
+
+    #[test]
+    fn runtime_selection() {
+        for alg in SignatureAlgorithm::ALL {
+            assert_eq!(SignatureAlgorithm::from_id(alg.id()), Some(alg));
+            assert_eq!(SignatureAlgorithm::from_name(&alg.name().to_uppercase()), Some(alg));
+
+            let (sk, pk) = alg.generate();
+            assert_eq!(alg.verifying_key(sk.expose_secret()).unwrap(), pk);
+            let sig = alg.sign(sk.expose_secret(), b"msg").unwrap();
+            assert_eq!(alg.verify(&pk, b"msg", &sig), Ok(()), "{alg}");
+            // a signature from one scheme never verifies under another
+            for other in SignatureAlgorithm::ALL.into_iter().filter(|&o| o != alg) {
+                if let Ok(other_pk) = other.verifying_key(sk.expose_secret()) {
+                    assert!(other.verify(&other_pk, b"msg", &sig).is_err(), "{alg} sig under {other}");
+                }
+            }
+        }
+        assert_eq!(SignatureAlgorithm::from_name("rsa"), None);
+    }
+
+    /// The identity key with R = identity, s = 0 verifies any message under plain RFC 8032.
+    #[test]
+    fn ed25519_schemes_reject_small_order_keys() {
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        let sig = [identity, [0; 32]].concat();
+        let pk = Ed25519::decode_verifying_key(&identity).unwrap();
+        assert_eq!(Ed25519::verify(&pk, b"any message", &sig), Err(SignatureError::VerificationFailed));
+        assert_eq!(Ed25519ph::verify(&pk, b"any message", &sig), Err(SignatureError::VerificationFailed));
+    }
+
+    #[test]
+    fn known_answers() {
+        // RFC 8032 §7.1 TEST 1 and §7.3 (Ed25519ph, "abc")
+        let sk = Ed25519::decode_signing_key(&h("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60")).unwrap();
+        assert_eq!(Ed25519::encode_verifying_key(&Ed25519::verifying_key(&sk)), h("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"));
+        assert_eq!(
+            Ed25519::sign(&sk, b"").unwrap().to_vec(),
+            h("e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b")
+        );
+        let sk = Ed25519ph::decode_signing_key(&h("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42")).unwrap();
+        assert_eq!(
+            Ed25519ph::sign(&sk, b"abc").unwrap().to_vec(),
+            h("98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406")
+        );
+
+        // RFC 6979 A.2.5: P-256, SHA-256, message "sample"
+        let sk = EcdsaP256::decode_signing_key(&h("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721")).unwrap();
+        assert_eq!(
+            EcdsaP256::sign(&sk, b"sample").unwrap().to_vec(),
+            h("efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8")
+        );
+    }
+
+    #[test]
+    fn secp256k1_interoperates_with_k256() {
+        use k256::ecdsa::signature::Signer as _;
+
+        let signing_key = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let sig: k256::ecdsa::Signature = signing_key.sign(b"cross-check");
+        let pk = EcdsaSecp256k1::decode_verifying_key(signing_key.verifying_key().to_encoded_point(true).as_bytes()).unwrap();
+        assert_eq!(EcdsaSecp256k1::verify(&pk, b"cross-check", &sig.to_bytes()), Ok(()));
+
+        let sk = EcdsaSecp256k1::decode_signing_key(&signing_key.to_bytes()).unwrap();
+        assert_eq!(EcdsaSecp256k1::verifying_key(&sk), pk);
+    }
+}
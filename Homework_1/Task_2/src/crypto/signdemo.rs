@@ -1,5 +1,15 @@
+use std::fmt;
+
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::{Scalar, clamp_integer};
+use curve25519_dalek::traits::IsIdentity;
 use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier};
 use rand::rngs::OsRng;
+use sha2::{Digest, Sha512};
+use zeroize::Zeroizing;
+
+/// Prefix of dom2(phflag, context), RFC 8032 §5.1.
+const DOM2_PREFIX: &[u8] = b"SigEd25519 no Ed25519 collisions";
 
 /// Ed25519 keypair (secret/public)
 pub struct Keypair {
@@ -25,9 +35,151 @@ pub fn verify(pk: &VerifyingKey, msg: &[u8], sig: &Signature) -> bool {
     pk.verify(msg, sig).is_ok()
 }
 
+/// Like `verify`, but also rejects what RFC 8032 allows an implementation to accept:
+/// small-order keys or R (a small-order key has signatures valid for every message),
+/// non-canonical R and s >= l. Uses the cofactorless equation.
+pub fn verify_strict(pk: &VerifyingKey, msg: &[u8], sig: &Signature) -> bool {
+    pk.verify_strict(msg, sig).is_ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContextError {
+    /// Ed25519ctx needs a non-empty context (RFC 8032 §5.1).
+    Empty,
+    /// Contexts are at most 255 bytes.
+    TooLong(usize),
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContextError::Empty => write!(f, "Ed25519ctx requires a non-empty context"),
+            ContextError::TooLong(len) => write!(f, "context of {len} bytes exceeds 255"),
+        }
+    }
+}
+
+impl std::error::Error for ContextError {}
+
+/// Ed25519ctx: a signature bound to `context` (e.g. "audit-log v1"), so it does not verify
+/// as plain Ed25519 or under any other context. ed25519-dalek has no Ed25519ctx, so this signs
+/// RFC 8032 §5.1.6 with dom2(0, context) itself.
+pub fn sign_ctx(sk: &SigningKey, context: &[u8], msg: &[u8]) -> Result<Signature, ContextError> {
+    let dom = dom2_ctx(context)?;
+    let h = Zeroizing::new(<[u8; 64]>::from(Sha512::digest(sk.as_bytes())));
+    let a = Zeroizing::new(Scalar::from_bytes_mod_order(clamp_integer(h[..32].try_into().unwrap())));
+    let nonce = Sha512::new().chain_update(&dom).chain_update(&h[32..]).chain_update(msg).finalize();
+    let r = Zeroizing::new(Scalar::from_bytes_mod_order_wide(&nonce.into()));
+    let big_r = EdwardsPoint::mul_base(&r).compress().to_bytes();
+    let k = challenge(&dom, &big_r, &sk.verifying_key(), msg);
+    Ok(Signature::from_components(big_r, (*r + k * *a).to_bytes()))
+}
+
+/// Verify an Ed25519ctx signature, with the checks of `verify_strict`.
+pub fn verify_ctx(pk: &VerifyingKey, context: &[u8], msg: &[u8], sig: &Signature) -> bool {
+    let Ok(dom) = dom2_ctx(context) else {
+        return false;
+    };
+    let (Some(a), Some(r)) = (strict_point(pk.as_bytes()), strict_point(sig.r_bytes())) else {
+        return false;
+    };
+    let Some(s) = Option::<Scalar>::from(Scalar::from_canonical_bytes(*sig.s_bytes())) else {
+        return false;
+    };
+    let k = challenge(&dom, sig.r_bytes(), pk, msg);
+    EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &-a, &s) == r
+}
+
+/// The single-signature check of the `verify_batch` policy.
+fn cofactored_equation_holds(pk: &VerifyingKey, msg: &[u8], sig: &Signature) -> bool {
+    let Some(r) = CompressedEdwardsY(*sig.r_bytes()).decompress() else {
+        return false;
+    };
+    let Some(s) = Option::<Scalar>::from(Scalar::from_canonical_bytes(*sig.s_bytes())) else {
+        return false;
+    };
+    let k = challenge(&[], sig.r_bytes(), pk, msg);
+    (EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &-pk.to_edwards(), &s) - r).mul_by_cofactor().is_identity()
+}
+
+/// dom2(0, context) for Ed25519ctx.
+fn dom2_ctx(context: &[u8]) -> Result<Vec<u8>, ContextError> {
+    if context.is_empty() {
+        return Err(ContextError::Empty);
+    }
+    let len = u8::try_from(context.len()).map_err(|_| ContextError::TooLong(context.len()))?;
+    Ok([DOM2_PREFIX, &[0, len], context].concat())
+}
+
+/// k = SHA-512(dom || R || A || msg) mod l
+fn challenge(dom: &[u8], r: &[u8; 32], pk: &VerifyingKey, msg: &[u8]) -> Scalar {
+    let h = Sha512::new().chain_update(dom).chain_update(r).chain_update(pk.as_bytes()).chain_update(msg).finalize();
+    Scalar::from_bytes_mod_order_wide(&h.into())
+}
+
+/// A canonically encoded point that is not of small order.
+fn strict_point(bytes: &[u8; 32]) -> Option<EdwardsPoint> {
+    let compressed = CompressedEdwardsY(*bytes);
+    compressed.decompress().filter(|p| p.compress() == compressed && !p.is_small_order())
+}
+
+/// Ed25519ph: signs SHA-512(msg), where the caller has fed the message into `prehash` as it
+/// streamed by. A non-empty `context` (e.g. "audit-log v1") binds the signature to it, so it
+/// does not verify under any other context.
+pub fn sign_prehashed(sk: &SigningKey, prehash: Sha512, context: &[u8]) -> Result<Signature, ContextError> {
+    sk.sign_prehashed(prehash, Some(context)).map_err(|_| ContextError::TooLong(context.len()))
+}
+
+/// Verify an Ed25519ph signature, with the checks of `verify_strict`.
+pub fn verify_prehashed(pk: &VerifyingKey, prehash: Sha512, context: &[u8], sig: &Signature) -> bool {
+    context.len() <= 255 && pk.verify_prehashed_strict(prehash, Some(context), sig).is_ok()
+}
+
+/// A failed `verify_batch`: the positions of the signatures whose cofactored equation fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchError {
+    pub invalid: Vec<usize>,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid signature(s) in batch at indices {:?}", self.invalid.len(), self.invalid)
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// Verify many (pk, msg, sig) triples at once with `ed25519_dalek::verify_batch`: one
+/// multiscalar multiplication with random 128-bit weights, which a forged signature passes
+/// with probability about 2^-128.
+///
+/// Policy: a batch is accepted iff every signature has s < l and satisfies the cofactored
+/// equation [8][s]B == [8]R + [8][k]A. This is weaker than `verify_strict`: small-order or
+/// mixed-order keys and R are accepted whenever their torsion drops out under the cofactor,
+/// which is what the random weights of the batch cannot tell apart. Use `verify_strict` per
+/// signature where those must be rejected; honest signers never produce them.
+///
+/// If the batch check fails, each signature is checked against the same equation and the
+/// indices of those that fail are returned.
+pub fn verify_batch(items: &[(VerifyingKey, &[u8], Signature)]) -> Result<(), BatchError> {
+    let messages: Vec<&[u8]> = items.iter().map(|(_, msg, _)| *msg).collect();
+    let signatures: Vec<Signature> = items.iter().map(|(_, _, sig)| *sig).collect();
+    let keys: Vec<VerifyingKey> = items.iter().map(|(pk, _, _)| *pk).collect();
+    if ed25519_dalek::verify_batch(&messages, &signatures, &keys).is_ok() {
+        return Ok(());
+    }
+    let invalid: Vec<usize> = items.iter()
+        .enumerate()
+        .filter(|(_, (pk, msg, sig))| !cofactored_equation_holds(pk, msg, sig))
+        .map(|(i, _)| i)
+        .collect();
+    if invalid.is_empty() { Ok(()) } else { Err(BatchError { invalid }) }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
     #[test]
     fn sign_then_verify() {
         let kp = keygen();
@@ -36,4 +188,205 @@ mod tests {
         assert!(verify(&kp.pk, msg, &sig));
         assert!(!verify(&kp.pk, b"random message", &sig));
     }
+
+    fn signed_batch(n: usize) -> Vec<(VerifyingKey, Vec<u8>, Signature)> {
+        (0..n).map(|i| {
+            let kp = keygen();
+            let msg = format!("audit log entry {i}").into_bytes();
+            let sig = sign(&kp.sk, &msg);
+            (kp.pk, msg, sig)
+        }).collect()
+    }
+
+    fn as_items(batch: &[(VerifyingKey, Vec<u8>, Signature)]) -> Vec<(VerifyingKey, &[u8], Signature)> {
+        batch.iter().map(|(pk, msg, sig)| (*pk, msg.as_slice(), *sig)).collect()
+    }
+
+    #[test]
+    fn batch_accepts_valid_signatures() {
+        let batch = signed_batch(40);
+        assert_eq!(verify_batch(&as_items(&batch)), Ok(()));
+        assert_eq!(verify_batch(&as_items(&batch[..1])), Ok(()));
+        assert_eq!(verify_batch(&[]), Ok(()));
+    }
+
+    #[test]
+    fn batch_reports_offending_indices() {
+        let mut batch = signed_batch(40);
+        batch[3].1[0] ^= 1; // tampered message
+        batch[17].0 = keygen().pk; // wrong key
+        let mut s = batch[30].2.to_bytes();
+        s[63] |= 0xf0; // s >= l
+        batch[30].2 = Signature::from_bytes(&s);
+        assert_eq!(verify_batch(&as_items(&batch)), Err(BatchError { invalid: vec![3, 17, 30] }));
+    }
+
+    fn h(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    fn signing_key(seed: &str) -> SigningKey {
+        SigningKey::from_bytes(&h(seed).try_into().unwrap())
+    }
+
+    fn signature(hex: &str) -> Signature {
+        Signature::from_slice(&h(hex)).unwrap()
+    }
+
+    /// RFC 8032 §7.1 TEST 1 - 3 pass the strict checks.
+    #[test]
+    fn rfc8032_ed25519_verifies_strictly() {
+        for (seed, msg, sig) in [
+            ("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60", "",
+             "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"),
+            ("4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb", "72",
+             "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"),
+            ("c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7", "af82",
+             "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a"),
+        ] {
+            let sk = signing_key(seed);
+            assert_eq!(sign(&sk, &h(msg)), signature(sig));
+            assert!(verify_strict(&sk.verifying_key(), &h(msg), &signature(sig)));
+        }
+    }
+
+    /// RFC 8032 §7.2 (Ed25519ctx).
+    #[test]
+    fn rfc8032_ed25519ctx() {
+        for (seed, msg, ctx, sig) in [
+            ("0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6", "f726936d19c800494e3fdaff20b276a8", "666f6f",
+             "55a4cc2f70a54e04288c5f4cd1e45a7bb520b36292911876cada7323198dd87a8b36950b95130022907a7fb7c4e9b2d5f6cca685a587b4b21f4b888e4e7edb0d"),
+            ("0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6", "f726936d19c800494e3fdaff20b276a8", "626172",
+             "fc60d5872fc46b3aa69f8b5b4351d5808f92bcc044606db097abab6dbcb1aee3216c48e8b3b66431b5b186d1d28f8ee15a5ca2df6668346291c2043d4eb3e90d"),
+            ("0305334e381af78f141cb666f6199f57bc3495335a256a95bd2a55bf546663f6", "508e9e6882b979fea900f62adceaca35", "666f6f",
+             "8b70c1cc8310e1de20ac53ce28ae6e7207f33c3295e03bb5c0732a1d20dc64908922a8b052cf99b7c4fe107a5abb5b2c4085ae75890d02df26269d8945f84b0b"),
+            ("ab9c2853ce297ddab85c993b3ae14bcad39b2c682beabc27d6d4eb20711d6560", "f726936d19c800494e3fdaff20b276a8", "666f6f",
+             "21655b5f1aa965996b3f97b3c849eafba922a0a62992f73b3d1b73106a84ad85e9b86a7b6005ea868337ff2d20a7f5fbd4cd10b0be49a68da2b2e0dc0ad8960f"),
+        ] {
+            let (sk, msg, sig) = (signing_key(seed), h(msg), signature(sig));
+            let pk = sk.verifying_key();
+            assert_eq!(sign_ctx(&sk, &h(ctx), &msg).unwrap(), sig);
+            assert!(verify_ctx(&pk, &h(ctx), &msg, &sig));
+            assert!(!verify_ctx(&pk, b"baz", &msg, &sig));
+            assert!(!verify(&pk, &msg, &sig));
+        }
+
+        let sk = keygen().sk;
+        assert_eq!(sign_ctx(&sk, b"", b"m"), Err(ContextError::Empty));
+        assert_eq!(sign_ctx(&sk, &[0u8; 256], b"m"), Err(ContextError::TooLong(256)));
+        assert!(sign_ctx(&sk, &[0u8; 255], b"m").is_ok());
+        assert!(!verify_ctx(&sk.verifying_key(), b"", b"m", &sign(&sk, b"m")));
+        let sig = sign_ctx(&sk, &[0u8; 255], b"m").unwrap();
+        assert!(!verify_ctx(&sk.verifying_key(), &[0u8; 256], b"m", &sig));
+    }
+
+    /// RFC 8032 §7.3 (Ed25519ph, "abc"), fed to the hash in pieces; a context binds the signature.
+    #[test]
+    fn rfc8032_ed25519ph_streams() {
+        let sk = signing_key("833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42");
+        let expected = signature("98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae4131f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406");
+        let streamed = Sha512::new().chain_update(b"a").chain_update(b"bc");
+        assert_eq!(sign_prehashed(&sk, streamed.clone(), b"").unwrap(), expected);
+        assert!(verify_prehashed(&sk.verifying_key(), streamed, b"", &expected));
+        assert!(!verify(&sk.verifying_key(), b"abc", &expected));
+
+        let large = vec![0x5a; 1 << 20];
+        let mut prehash = Sha512::new();
+        for chunk in large.chunks(4096) {
+            prehash.update(chunk);
+        }
+        let sig = sign_prehashed(&sk, prehash.clone(), b"backup").unwrap();
+        assert!(verify_prehashed(&sk.verifying_key(), prehash.clone(), b"backup", &sig));
+        assert!(!verify_prehashed(&sk.verifying_key(), prehash.clone(), b"", &sig));
+        assert!(!verify_prehashed(&sk.verifying_key(), prehash.clone(), &[0u8; 256], &sig));
+        assert_eq!(sign_prehashed(&sk, prehash, &[0u8; 256]), Err(ContextError::TooLong(256)));
+    }
+
+    /// Sign under an arbitrary key point `pk` = [a]B + torsion and nonce point `r` = [nonce]B + torsion.
+    fn forge(a: Scalar, pk: [u8; 32], nonce: Scalar, r: [u8; 32], msg: &[u8]) -> (VerifyingKey, Signature) {
+        let pk = VerifyingKey::from_bytes(&pk).unwrap();
+        let k = challenge(&[], &r, &pk, msg);
+        (pk, Signature::from_components(r, (nonce + k * a).to_bytes()))
+    }
+
+    /// The edge cases of "Taming the many EdDSAs" (Chalkias, Garillot, Nikolaenko; SSR 2020),
+    /// rebuilt from their definitions: small-order A or R, mixed-order A, s >= l, and
+    /// non-canonical A or R. `verify` is the permissive RFC 8032 check; `verify_strict` rejects
+    /// all of them except a mixed-order key whose cofactorless equation happens to hold.
+    #[test]
+    fn taming_edge_cases() {
+        use curve25519_dalek::constants::EIGHT_TORSION;
+
+        let identity = EdwardsPoint::default().compress().to_bytes();
+        let mut non_canonical_identity = [0xff; 32]; // y = p + 1
+        non_canonical_identity[0] = 0xee;
+        non_canonical_identity[31] = 0x7f;
+        let a = Scalar::from_bytes_mod_order([7; 32]);
+        let honest_pk = EdwardsPoint::mul_base(&a).compress().to_bytes();
+        let nonce = Scalar::from_bytes_mod_order([9; 32]);
+        let honest_r = EdwardsPoint::mul_base(&nonce).compress().to_bytes();
+        let zero = Scalar::ZERO;
+
+        // small-order A and R, s = 0: valid for every message
+        let (pk, sig) = forge(zero, identity, zero, identity, b"any message");
+        assert!(verify(&pk, b"any message", &sig) && verify(&pk, b"another", &sig));
+        assert!(!verify_strict(&pk, b"any message", &sig));
+        assert_eq!(verify_batch(&[(pk, &b"another"[..], sig)]), Ok(())); // the batch policy is cofactored
+
+        // small-order R with an honest key
+        let (pk, sig) = forge(a, honest_pk, zero, identity, b"msg");
+        assert!(verify(&pk, b"msg", &sig));
+        assert!(!verify_strict(&pk, b"msg", &sig));
+
+        // mixed-order A = [a]B + T: the cofactorless equation holds iff [k]T = 0, the cofactored
+        // one of `verify_batch` always
+        let mixed_pk = (EdwardsPoint::mul_base(&a) + EIGHT_TORSION[1]).compress().to_bytes();
+        let (mut holds, mut fails) = (false, false);
+        for i in 0u32.. {
+            let msg = i.to_le_bytes();
+            let (pk, sig) = forge(a, mixed_pk, nonce, honest_r, &msg);
+            let cofactorless = (challenge(&[], &honest_r, &pk, &msg) * EIGHT_TORSION[1]).is_identity();
+            assert_eq!(verify_strict(&pk, &msg, &sig), cofactorless);
+            assert_eq!(verify(&pk, &msg, &sig), cofactorless);
+            assert_eq!(verify_batch(&[(pk, &msg[..], sig)]), Ok(()));
+            assert_eq!(verify_batch(&as_items(&[(pk, msg.to_vec(), sig), (pk, b"other".to_vec(), sig)])), Err(BatchError { invalid: vec![1] }));
+            holds |= cofactorless;
+            fails |= !cofactorless;
+            if holds && fails {
+                break;
+            }
+        }
+
+        // s >= l
+        let (pk, sig) = forge(a, honest_pk, nonce, honest_r, b"msg");
+        assert!(verify_strict(&pk, b"msg", &sig));
+        let mut s = Scalar::from_canonical_bytes(*sig.s_bytes()).unwrap().to_bytes();
+        add_group_order(&mut s);
+        let sig = Signature::from_components(*sig.r_bytes(), s);
+        assert!(!verify(&pk, b"msg", &sig) && !verify_strict(&pk, b"msg", &sig));
+        assert_eq!(verify_batch(&[(pk, &b"msg"[..], sig)]), Err(BatchError { invalid: vec![0] }));
+
+        // non-canonical R (the identity as y = p + 1)
+        let (pk, sig) = forge(a, honest_pk, zero, non_canonical_identity, b"msg");
+        assert!(!verify(&pk, b"msg", &sig) && !verify_strict(&pk, b"msg", &sig));
+
+        // non-canonical A (the identity as y = p + 1), s = 0
+        let (pk, sig) = forge(zero, non_canonical_identity, zero, identity, b"msg");
+        assert!(verify(&pk, b"msg", &sig));
+        assert!(!verify_strict(&pk, b"msg", &sig));
+    }
+
+    /// s += l (little-endian), for building a non-canonical s.
+    fn add_group_order(s: &mut [u8; 32]) {
+        const L: [u8; 32] = [
+            0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+        ];
+        let mut carry = 0u16;
+        for (byte, l) in s.iter_mut().zip(L) {
+            let sum = *byte as u16 + l as u16 + carry;
+            *byte = sum as u8;
+            carry = sum >> 8;
+        }
+    }
 }
\ No newline at end of file
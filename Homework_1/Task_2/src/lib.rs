@@ -1,3 +1,4 @@
 pub mod crypto;
 pub mod io;
-pub mod encode;
\ No newline at end of file
+pub mod encode;
+pub mod protocol;
\ No newline at end of file
@@ -0,0 +1,154 @@
+//! Issuing X.509 certificates for handshake keys with `rcgen`: a root CA, intermediates and server
+//! certificates, for `hs-server keygen` and the tests. `CertificateKey` plugs our own signature
+//! schemes into `rcgen`, so the key a certificate names is exactly the one the server signs the
+//! handshake with.
+
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, Issuer, KeyUsagePurpose, PublicKeyData, SigningKey,
+};
+use time::{Duration, OffsetDateTime};
+
+use crate::crypto::secret::SecretKey;
+use crate::crypto::signature_scheme::{SIGNING_KEY_LEN, SignatureAlgorithm};
+
+use super::x509::KeyType;
+
+const CA_VALIDITY_DAYS: i64 = 3650;
+const SERVER_VALIDITY_DAYS: i64 = 365;
+
+/// A signing key in the `SignatureAlgorithm` encoding, usable as a certificate's subject key and as
+/// an issuer's key. Certificates are signed with plain Ed25519 for Ed25519ph keys.
+pub struct CertificateKey {
+    algorithm: SignatureAlgorithm,
+    key_type: KeyType,
+    signing_key: SecretKey<SIGNING_KEY_LEN>,
+    /// Contents of the subjectPublicKey bit string: uncompressed SEC1 for P-256.
+    public_key: Vec<u8>,
+}
+
+impl CertificateKey {
+    /// Fails with `UnsupportedSignatureAlgorithm` for secp256k1, which `rcgen` cannot certify.
+    pub fn new(algorithm: SignatureAlgorithm, signing_key: SecretKey<SIGNING_KEY_LEN>) -> Result<Self, rcgen::Error> {
+        let key_type = KeyType::for_algorithm(algorithm).ok_or(rcgen::Error::UnsupportedSignatureAlgorithm)?;
+        let encoded = algorithm.verifying_key(signing_key.expose_secret()).map_err(|_| rcgen::Error::CouldNotParseKeyPair)?;
+        let public_key = match key_type {
+            KeyType::Ed25519 => encoded,
+            KeyType::P256 => p256::PublicKey::from_sec1_bytes(&encoded).unwrap().to_encoded_point(false).as_bytes().to_vec(),
+        };
+        Ok(Self { algorithm, key_type, signing_key, public_key })
+    }
+
+    pub fn generate(algorithm: SignatureAlgorithm) -> Result<Self, rcgen::Error> {
+        Self::new(algorithm, algorithm.generate().0)
+    }
+
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    pub fn signing_key(&self) -> &SecretKey<SIGNING_KEY_LEN> {
+        &self.signing_key
+    }
+}
+
+impl PublicKeyData for CertificateKey {
+    fn der_bytes(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn algorithm(&self) -> &'static rcgen::SignatureAlgorithm {
+        match self.key_type {
+            KeyType::Ed25519 => &rcgen::PKCS_ED25519,
+            KeyType::P256 => &rcgen::PKCS_ECDSA_P256_SHA256,
+        }
+    }
+}
+
+impl SigningKey for CertificateKey {
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, rcgen::Error> {
+        let sk = self.signing_key.expose_secret();
+        match self.key_type {
+            KeyType::Ed25519 => Ok(SignatureAlgorithm::Ed25519.sign(sk, msg).map_err(|_| rcgen::Error::RemoteKeyError)?.to_vec()),
+            // X.509 wants the DER ECDSA-Sig-Value, not r || s
+            KeyType::P256 => {
+                let sig = SignatureAlgorithm::EcdsaP256.sign(sk, msg).map_err(|_| rcgen::Error::RemoteKeyError)?;
+                let sig = p256::ecdsa::Signature::from_slice(&sig).map_err(|_| rcgen::Error::RemoteKeyError)?;
+                Ok(sig.to_der().as_bytes().to_vec())
+            }
+        }
+    }
+}
+
+fn validity(params: &mut CertificateParams, days: i64) {
+    let now = OffsetDateTime::now_utc();
+    // a day of slack for clocks behind ours
+    params.not_before = now - Duration::days(1);
+    params.not_after = now + Duration::days(days);
+}
+
+/// A CA certificate named `name`, allowing `path_len` CAs below it (any number if `None`).
+pub fn ca_params(name: &str, path_len: Option<u8>) -> CertificateParams {
+    let mut params = CertificateParams::default();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.is_ca = IsCa::Ca(path_len.map_or(BasicConstraints::Unconstrained, BasicConstraints::Constrained));
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    validity(&mut params, CA_VALIDITY_DAYS);
+    params
+}
+
+/// A server certificate for `server_name`, a DNS name or IP address.
+pub fn server_params(server_name: &str) -> Result<CertificateParams, rcgen::Error> {
+    let mut params = CertificateParams::new(vec![server_name.to_string()])?;
+    params.distinguished_name.remove(DnType::CommonName);
+    params.distinguished_name.push(DnType::CommonName, server_name);
+    params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    params.use_authority_key_identifier_extension = true;
+    validity(&mut params, SERVER_VALIDITY_DAYS);
+    Ok(params)
+}
+
+pub struct CertificateAuthority {
+    certificate: Vec<u8>,
+    issuer: Issuer<'static, CertificateKey>,
+}
+
+impl CertificateAuthority {
+    /// Self-signed root; `params` usually come from `ca_params`.
+    pub fn new(params: CertificateParams, key: CertificateKey) -> Result<Self, rcgen::Error> {
+        let certificate = params.self_signed(&key)?.der().to_vec();
+        Ok(Self { certificate, issuer: Issuer::new(params, key) })
+    }
+
+    /// Root with a fresh Ed25519 key.
+    pub fn generate(name: &str) -> Result<Self, rcgen::Error> {
+        Self::new(ca_params(name, None), CertificateKey::generate(SignatureAlgorithm::Ed25519)?)
+    }
+
+    /// The CA's own certificate (DER), what a trust store holds for a root.
+    pub fn certificate(&self) -> &[u8] {
+        &self.certificate
+    }
+
+    pub fn key(&self) -> &CertificateKey {
+        self.issuer.key()
+    }
+
+    /// Certificate (DER) for `key` with the subject and extensions of `params`, signed by this CA.
+    pub fn issue(&self, params: &CertificateParams, key: &CertificateKey) -> Result<Vec<u8>, rcgen::Error> {
+        Ok(params.signed_by(key, &self.issuer)?.der().to_vec())
+    }
+
+    /// CA below this one.
+    pub fn intermediate(&self, params: CertificateParams, key: CertificateKey) -> Result<Self, rcgen::Error> {
+        let certificate = self.issue(&params, &key)?;
+        Ok(Self { certificate, issuer: Issuer::new(params, key) })
+    }
+
+    /// Fresh `algorithm` key and its server certificate for `server_name`.
+    pub fn issue_server(&self, server_name: &str, algorithm: SignatureAlgorithm) -> Result<(Vec<u8>, CertificateKey), rcgen::Error> {
+        let key = CertificateKey::generate(algorithm)?;
+        Ok((self.issue(&server_params(server_name)?, &key)?, key))
+    }
+}
@@ -0,0 +1,646 @@
+//! The SIGMA / TLS-1.3-style handshake as two state machines, one per side, so client and server
+//! can run in separate processes. Each side consumes the peer's messages and returns its own:
+//!
+//! ```text
+//! Client                                            Server
+//! start()          -- ClientHello -->               handle(): Start -> WaitFinished
+//!                  <-- ServerHello, ServerFlight --
+//! handle(ServerHello):  WaitServerHello -> WaitFinished
+//! handle(ServerFlight): WaitFinished -> Connected
+//!                  -- ClientFinished -->            handle(): WaitFinished -> Connected
+//! ```
+//!
+//! ServerFlight is ServerCert (X.509 chain, signature over the key exchange and the chain) and
+//! ServerFinished (HMAC under k_2_s), sealed under k_1_s; ClientFinished is an HMAC under k_2_c,
+//! sealed under k_1_c. The client validates the chain against its trust store (`x509`) and checks
+//! the signature under the key of the server's certificate.
+//! The byte encoding of every message is in `wire`.
+//! Any error moves the machine to `Failed`, after which it rejects every further call.
+
+use std::fmt;
+use std::time::SystemTime;
+
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::crypto::aead::CipherSuite;
+use crate::crypto::dhke::{self, DHKeyError};
+use crate::crypto::envelope::{self, Envelope};
+use crate::crypto::key_extract::{self, hashValue};
+use crate::crypto::secret::{SecretKey, SharedSecret};
+use crate::crypto::signature_scheme::{SIGNATURE_LEN, SIGNING_KEY_LEN, SignatureAlgorithm, SignatureError};
+use crate::crypto::hmac;
+
+use super::wire::{self, WireError};
+use super::x509::{CertificateError, TrustStore};
+
+pub const NONCE_LEN: usize = 32;
+pub const KEY_SHARE_LEN: usize = 32;
+pub const MAC_LEN: usize = 32;
+
+const SERVER_FLIGHT_KEY_ID: &[u8] = b"k_1_s";
+const CLIENT_FINISHED_KEY_ID: &[u8] = b"k_1_c";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandshakeState {
+    /// Client: `start` not called yet. Server: waiting for ClientHello.
+    Start,
+    WaitServerHello,
+    /// Client: waiting for the server's encrypted flight. Server: waiting for ClientFinished.
+    WaitFinished,
+    Connected,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeError {
+    /// A message that is not valid in the current state.
+    UnexpectedMessage { state: HandshakeState, message: &'static str },
+    /// An earlier step failed; the handshake cannot continue.
+    AlreadyFailed,
+    NoCommonCipherSuite,
+    /// The peer's key share has small order.
+    InvalidKeyShare,
+    /// A sealed flight did not decrypt under the expected key.
+    Decryption,
+    Encryption,
+    /// A decrypted flight does not parse.
+    Wire(WireError),
+    /// The server's certificate chain does not validate against the trust store.
+    Certificate(CertificateError),
+    /// The server signed with an algorithm its certified key is not for.
+    SignatureAlgorithmMismatch(SignatureAlgorithm),
+    /// The server's signature over the key exchange does not verify under the certified key.
+    BadSignature(SignatureError),
+    /// A Finished MAC does not match the transcript.
+    BadFinished,
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeError::UnexpectedMessage { state, message } => write!(f, "unexpected {message} in state {state:?}"),
+            HandshakeError::AlreadyFailed => write!(f, "handshake already failed"),
+            HandshakeError::NoCommonCipherSuite => write!(f, "no common cipher suite"),
+            HandshakeError::InvalidKeyShare => write!(f, "peer key share rejected (small order)"),
+            HandshakeError::Decryption => write!(f, "could not decrypt handshake flight"),
+            HandshakeError::Encryption => write!(f, "could not encrypt handshake flight"),
+            HandshakeError::Wire(e) => write!(f, "malformed handshake message: {e}"),
+            HandshakeError::Certificate(e) => write!(f, "server certificate rejected: {e}"),
+            HandshakeError::SignatureAlgorithmMismatch(alg) => write!(f, "server signed with {alg}, which its certified key is not for"),
+            HandshakeError::BadSignature(e) => write!(f, "server signature rejected: {e}"),
+            HandshakeError::BadFinished => write!(f, "Finished MAC mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+impl From<WireError> for HandshakeError {
+    fn from(e: WireError) -> Self {
+        HandshakeError::Wire(e)
+    }
+}
+
+impl From<CertificateError> for HandshakeError {
+    fn from(e: CertificateError) -> Self {
+        HandshakeError::Certificate(e)
+    }
+}
+
+impl From<DHKeyError> for HandshakeError {
+    fn from(_: DHKeyError) -> Self {
+        HandshakeError::InvalidKeyShare
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientHello {
+    pub nonce: [u8; NONCE_LEN],
+    pub key_share: [u8; KEY_SHARE_LEN],
+    /// In the client's order of preference.
+    pub cipher_suites: Vec<CipherSuite>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerHello {
+    pub nonce: [u8; NONCE_LEN],
+    pub key_share: [u8; KEY_SHARE_LEN],
+    pub cipher_suite: CipherSuite,
+}
+
+/// Decrypted contents of the server's flight.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerCert {
+    pub algorithm: SignatureAlgorithm,
+    /// DER X.509 certificates: the server's own first, then each one's issuer.
+    pub certificate_chain: Vec<Vec<u8>>,
+    /// Signature of the server certificate's key over H(ClientHello | ServerHello | certificate),
+    /// with certificate = `wire::encode_certificate` of the two fields above.
+    pub signature: [u8; SIGNATURE_LEN],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerFinished {
+    pub mac: [u8; MAC_LEN],
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientFinished {
+    pub mac: [u8; MAC_LEN],
+}
+
+/// What travels between the two machines.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    ClientHello(ClientHello),
+    ServerHello(ServerHello),
+    /// ServerCert and ServerFinished, sealed under k_1_s.
+    ServerFlight(Envelope),
+    /// ClientFinished, sealed under k_1_c.
+    ClientFinished(Envelope),
+}
+
+impl Message {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Message::ClientHello(_) => "ClientHello",
+            Message::ServerHello(_) => "ServerHello",
+            Message::ServerFlight(_) => "ServerFlight",
+            Message::ClientFinished(_) => "ClientFinished",
+        }
+    }
+}
+
+/// Application traffic keys (k_3) and the negotiated suite, available once `Connected`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TrafficKeys {
+    pub suite: CipherSuite,
+    pub client_write: SecretKey<32>,
+    pub server_write: SecretKey<32>,
+}
+
+pub struct ClientConfig {
+    /// Roots the server's certificate chain must lead to.
+    pub trust_store: TrustStore,
+    /// Name the server's certificate must be issued for: a DNS name or an IP address.
+    pub server_name: String,
+    /// In order of preference.
+    pub cipher_suites: Vec<CipherSuite>,
+}
+
+#[derive(Clone)]
+pub struct ServerConfig {
+    /// DER X.509 certificates: the server's own first, then each one's issuer.
+    pub certificate_chain: Vec<Vec<u8>>,
+    /// Must be one the key of the server's certificate is for.
+    pub signature_algorithm: SignatureAlgorithm,
+    /// Signing key for the key of the server's certificate.
+    pub signing_key: SecretKey<SIGNING_KEY_LEN>,
+    /// In order of preference; the first one the client also offers is chosen.
+    pub cipher_suites: Vec<CipherSuite>,
+}
+
+/// Key-exchange state both sides hold after the hellos.
+struct Exchange {
+    nonce_c: [u8; NONCE_LEN],
+    key_share_c: [u8; KEY_SHARE_LEN],
+    nonce_s: [u8; NONCE_LEN],
+    key_share_s: [u8; KEY_SHARE_LEN],
+    suite: CipherSuite,
+    shared_secret: SharedSecret,
+    /// `wire::encode_hellos` of the two hellos as sent.
+    hellos: Vec<u8>,
+}
+
+impl Exchange {
+    /// Both hello bodies (nonces, key shares, the offered suites and the chosen one).
+    fn hello_transcript(&self) -> &[u8] {
+        &self.hellos
+    }
+
+    /// H(ClientHello | ServerHello | cert), what the server signs.
+    fn signed_hash(&self, cert: &[u8]) -> [u8; 32] {
+        hashValue(&[self.hello_transcript(), cert].concat())
+    }
+
+    /// H(ClientHello | ServerHello | sigma | cert | label), the input of the Finished MACs.
+    fn finished_hash(&self, signature: &[u8], cert: &[u8], label: &[u8]) -> [u8; 32] {
+        hashValue(&[self.hello_transcript(), signature, cert, label].concat())
+    }
+
+    fn k_1(&self) -> (SecretKey<32>, SecretKey<32>) {
+        key_extract::KeySchedule_1(self.shared_secret.expose_secret())
+    }
+
+    fn k_2(&self) -> (SecretKey<32>, SecretKey<32>) {
+        key_extract::KeySchedule_2(&self.nonce_c, &self.key_share_c, &self.nonce_s, &self.key_share_s, self.shared_secret.expose_secret())
+    }
+
+    fn traffic_keys(&self, signature: &[u8], cert: &[u8], mac_s: &[u8]) -> TrafficKeys {
+        let (client_write, server_write) = key_extract::KeySchedule_3(
+            &self.nonce_c, &self.key_share_c, &self.nonce_s, &self.key_share_s,
+            self.shared_secret.expose_secret(), signature, cert, mac_s,
+        );
+        TrafficKeys { suite: self.suite, client_write, server_write }
+    }
+}
+
+fn unexpected(state: HandshakeState, message: &Message) -> HandshakeError {
+    HandshakeError::UnexpectedMessage { state, message: message.name() }
+}
+
+pub struct ClientHandshake {
+    config: ClientConfig,
+    state: HandshakeState,
+    nonce: [u8; NONCE_LEN],
+    secret: Option<EphemeralSecret>,
+    key_share: [u8; KEY_SHARE_LEN],
+    exchange: Option<Exchange>,
+    keys: Option<TrafficKeys>,
+}
+
+impl ClientHandshake {
+    pub fn new(config: ClientConfig) -> Self {
+        let dh = dhke::DHkeypair::keygen();
+        Self {
+            config,
+            state: HandshakeState::Start,
+            nonce: rand::random(),
+            key_share: dh.pk.to_bytes(),
+            secret: Some(dh.sk),
+            exchange: None,
+            keys: None,
+        }
+    }
+
+    pub fn state(&self) -> HandshakeState {
+        self.state
+    }
+
+    /// k_3 keys once `Connected`.
+    pub fn traffic_keys(&self) -> Option<&TrafficKeys> {
+        self.keys.as_ref()
+    }
+
+    /// Move the k_3 keys out once `Connected`, e.g. into a record layer.
+    pub fn take_traffic_keys(&mut self) -> Option<TrafficKeys> {
+        self.keys.take()
+    }
+
+    /// Start -> WaitServerHello, returning the ClientHello.
+    pub fn start(&mut self) -> Result<Message, HandshakeError> {
+        match self.state {
+            HandshakeState::Start => {
+                self.state = HandshakeState::WaitServerHello;
+                Ok(Message::ClientHello(self.hello()))
+            }
+            HandshakeState::Failed => Err(HandshakeError::AlreadyFailed),
+            state => Err(HandshakeError::UnexpectedMessage { state, message: "start" }),
+        }
+    }
+
+    fn hello(&self) -> ClientHello {
+        ClientHello { nonce: self.nonce, key_share: self.key_share, cipher_suites: self.config.cipher_suites.clone() }
+    }
+
+    /// Consume a server message; returns ClientFinished once the server's flight checks out.
+    pub fn handle(&mut self, message: Message) -> Result<Option<Message>, HandshakeError> {
+        let result = match (self.state, message) {
+            (HandshakeState::Failed, _) => return Err(HandshakeError::AlreadyFailed),
+            (HandshakeState::WaitServerHello, Message::ServerHello(hello)) => self.on_server_hello(hello).map(|()| None),
+            (HandshakeState::WaitFinished, Message::ServerFlight(flight)) => self.on_server_flight(&flight).map(Some),
+            (state, other) => Err(unexpected(state, &other)),
+        };
+        if result.is_err() {
+            self.state = HandshakeState::Failed;
+        }
+        result
+    }
+
+    fn on_server_hello(&mut self, hello: ServerHello) -> Result<(), HandshakeError> {
+        if !self.config.cipher_suites.contains(&hello.cipher_suite) {
+            return Err(HandshakeError::NoCommonCipherSuite);
+        }
+        let secret = self.secret.take().expect("the secret is only taken here, once");
+        let shared_secret = dhke::checked_shared_secret(secret, &PublicKey::from(hello.key_share))?; // X^y
+        self.exchange = Some(Exchange {
+            nonce_c: self.nonce,
+            key_share_c: self.key_share,
+            nonce_s: hello.nonce,
+            key_share_s: hello.key_share,
+            suite: hello.cipher_suite,
+            shared_secret,
+            hellos: wire::encode_hellos(&self.hello(), &hello)?,
+        });
+        self.state = HandshakeState::WaitFinished;
+        Ok(())
+    }
+
+    fn on_server_flight(&mut self, flight: &Envelope) -> Result<Message, HandshakeError> {
+        let ex = self.exchange.as_ref().expect("set before WaitFinished");
+        let (k_1_c, k_1_s) = ex.k_1();
+        let (k_2_c, k_2_s) = ex.k_2();
+
+        if flight.key_id() != SERVER_FLIGHT_KEY_ID || flight.suite != ex.suite {
+            return Err(HandshakeError::Decryption);
+        }
+        let plaintext = envelope::open(flight, k_1_s.expose_secret(), b"").map_err(|_| HandshakeError::Decryption)?;
+        let (server_cert, finished) = wire::decode_server_flight(&plaintext)?;
+        let chain = &server_cert.certificate_chain;
+        let key = self.config.trust_store.verify_server(chain, &self.config.server_name, SystemTime::now())?;
+        if !key.supports(server_cert.algorithm) {
+            return Err(HandshakeError::SignatureAlgorithmMismatch(server_cert.algorithm));
+        }
+        let cert_bytes = wire::encode_certificate(server_cert.algorithm, chain)?;
+        let server_sha = ex.signed_hash(&cert_bytes);
+        server_cert.algorithm.verify(&key.public_key, &server_sha, &server_cert.signature).map_err(HandshakeError::BadSignature)?;
+
+        let hash_server = ex.finished_hash(&server_cert.signature, &cert_bytes, b"ServerMAC");
+        if !hmac::verify_hmac_sha256(k_2_s.expose_secret(), &hash_server, &finished.mac) {
+            return Err(HandshakeError::BadFinished);
+        }
+
+        let hash_client = ex.finished_hash(&server_cert.signature, &cert_bytes, b"ClientMAC");
+        let client_finished = ClientFinished { mac: hmac::compute_hmac_sha256(k_2_c.expose_secret(), &hash_client) };
+        let plaintext = wire::encode_client_finished(&client_finished)?;
+        let sealed = envelope::seal(ex.suite, CLIENT_FINISHED_KEY_ID, k_1_c.expose_secret(), &plaintext, b"")
+            .map_err(|_| HandshakeError::Encryption)?;
+
+        self.keys = Some(ex.traffic_keys(&server_cert.signature, &cert_bytes, &finished.mac));
+        self.state = HandshakeState::Connected;
+        Ok(Message::ClientFinished(sealed))
+    }
+}
+
+pub struct ServerHandshake {
+    config: ServerConfig,
+    state: HandshakeState,
+    exchange: Option<Exchange>,
+    /// ClientFinished MAC the client must send.
+    expected_mac: Option<[u8; MAC_LEN]>,
+    /// Held back until ClientFinished checks out.
+    pending_keys: Option<TrafficKeys>,
+    keys: Option<TrafficKeys>,
+}
+
+impl ServerHandshake {
+    pub fn new(config: ServerConfig) -> Self {
+        Self { config, state: HandshakeState::Start, exchange: None, expected_mac: None, pending_keys: None, keys: None }
+    }
+
+    pub fn state(&self) -> HandshakeState {
+        self.state
+    }
+
+    /// k_3 keys once `Connected`.
+    pub fn traffic_keys(&self) -> Option<&TrafficKeys> {
+        self.keys.as_ref()
+    }
+
+    /// Move the k_3 keys out once `Connected`, e.g. into a record layer.
+    pub fn take_traffic_keys(&mut self) -> Option<TrafficKeys> {
+        self.keys.take()
+    }
+
+    /// Consume a client message; ClientHello is answered with ServerHello and ServerFlight.
+    pub fn handle(&mut self, message: Message) -> Result<Vec<Message>, HandshakeError> {
+        let result = match (self.state, message) {
+            (HandshakeState::Failed, _) => return Err(HandshakeError::AlreadyFailed),
+            (HandshakeState::Start, Message::ClientHello(hello)) => self.on_client_hello(hello),
+            (HandshakeState::WaitFinished, Message::ClientFinished(finished)) => self.on_client_finished(&finished).map(|()| vec![]),
+            (state, other) => Err(unexpected(state, &other)),
+        };
+        if result.is_err() {
+            self.state = HandshakeState::Failed;
+        }
+        result
+    }
+
+    fn on_client_hello(&mut self, hello: ClientHello) -> Result<Vec<Message>, HandshakeError> {
+        let suite = CipherSuite::negotiate(&hello.cipher_suites, &self.config.cipher_suites).ok_or(HandshakeError::NoCommonCipherSuite)?;
+        let dh = dhke::DHkeypair::keygen(); // Y = g^y
+        let key_share_s = dh.pk.to_bytes();
+        let shared_secret = dhke::checked_shared_secret(dh.sk, &PublicKey::from(hello.key_share))?; // Y^x
+        let nonce_s = rand::random();
+        let hello_out = ServerHello { nonce: nonce_s, key_share: key_share_s, cipher_suite: suite };
+        let hellos = wire::encode_hellos(&hello, &hello_out)?;
+        let ex = Exchange { nonce_c: hello.nonce, key_share_c: hello.key_share, nonce_s, key_share_s, suite, shared_secret, hellos };
+        let (_, k_1_s) = ex.k_1();
+        let (k_2_c, k_2_s) = ex.k_2();
+
+        let config = &self.config;
+        let cert_bytes = wire::encode_certificate(config.signature_algorithm, &config.certificate_chain)?;
+        let server_sha = ex.signed_hash(&cert_bytes);
+        let signature = config.signature_algorithm.sign(config.signing_key.expose_secret(), &server_sha).map_err(HandshakeError::BadSignature)?;
+        let mac_s = hmac::compute_hmac_sha256(k_2_s.expose_secret(), &ex.finished_hash(&signature, &cert_bytes, b"ServerMAC"));
+
+        let server_cert = ServerCert { algorithm: config.signature_algorithm, certificate_chain: config.certificate_chain.clone(), signature };
+        let plaintext = wire::encode_server_flight(&server_cert, &ServerFinished { mac: mac_s })?;
+        let flight = envelope::seal(suite, SERVER_FLIGHT_KEY_ID, k_1_s.expose_secret(), &plaintext, b"")
+            .map_err(|_| HandshakeError::Encryption)?;
+
+        self.expected_mac = Some(hmac::compute_hmac_sha256(k_2_c.expose_secret(), &ex.finished_hash(&signature, &cert_bytes, b"ClientMAC")));
+        self.pending_keys = Some(ex.traffic_keys(&signature, &cert_bytes, &mac_s));
+        self.exchange = Some(ex);
+        self.state = HandshakeState::WaitFinished;
+        Ok(vec![Message::ServerHello(hello_out), Message::ServerFlight(flight)])
+    }
+
+    fn on_client_finished(&mut self, sealed: &Envelope) -> Result<(), HandshakeError> {
+        let ex = self.exchange.as_ref().expect("set before WaitFinished");
+        let (k_1_c, _) = ex.k_1();
+        if sealed.key_id() != CLIENT_FINISHED_KEY_ID || sealed.suite != ex.suite {
+            return Err(HandshakeError::Decryption);
+        }
+        let plaintext = envelope::open(sealed, k_1_c.expose_secret(), b"").map_err(|_| HandshakeError::Decryption)?;
+        let finished = wire::decode_client_finished(&plaintext)?;
+        let expected = self.expected_mac.expect("set before WaitFinished");
+        if !bool::from(subtle::ConstantTimeEq::ct_eq(&finished.mac[..], &expected[..])) {
+            return Err(HandshakeError::BadFinished);
+        }
+        self.keys = self.pending_keys.take();
+        self.state = HandshakeState::Connected;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ca::CertificateAuthority;
+    use crate::protocol::x509::KeyType;
+
+    /// Schemes whose keys a certificate can carry.
+    fn certifiable() -> impl Iterator<Item = SignatureAlgorithm> {
+        SignatureAlgorithm::ALL.into_iter().filter(|alg| KeyType::for_algorithm(*alg).is_some())
+    }
+
+    fn configs(algorithm: SignatureAlgorithm) -> (ClientConfig, ServerConfig) {
+        let ca = CertificateAuthority::generate("Test CA").unwrap();
+        let (certificate, key) = ca.issue_server("server.test", algorithm).unwrap();
+        let client = ClientConfig {
+            trust_store: TrustStore::new([ca.certificate().to_vec()]).unwrap(),
+            server_name: "server.test".to_string(),
+            cipher_suites: vec![CipherSuite::ChaCha20Poly1305, CipherSuite::Aes256Gcm],
+        };
+        let server = ServerConfig {
+            certificate_chain: vec![certificate],
+            signature_algorithm: algorithm,
+            signing_key: key.signing_key().clone(),
+            cipher_suites: CipherSuite::ALL.to_vec(),
+        };
+        (client, server)
+    }
+
+    /// Runs the client up to the server's flight and returns its verdict on it.
+    fn client_verdict(client_config: ClientConfig, server_config: ServerConfig) -> Result<Option<Message>, HandshakeError> {
+        let mut client = ClientHandshake::new(client_config);
+        let mut server = ServerHandshake::new(server_config);
+        let (server_hello, server_flight) = hellos(&mut client, &mut server);
+        client.handle(server_hello).unwrap();
+        client.handle(server_flight)
+    }
+
+    /// Returns the server's answer to the ClientHello: [ServerHello, ServerFlight].
+    fn hellos(client: &mut ClientHandshake, server: &mut ServerHandshake) -> (Message, Message) {
+        let mut flight = server.handle(client.start().unwrap()).unwrap();
+        assert_eq!(flight.len(), 2);
+        let server_flight = flight.pop().unwrap();
+        (flight.pop().unwrap(), server_flight)
+    }
+
+    #[test]
+    fn handshake_connects_with_every_certifiable_scheme() {
+        assert_eq!(certifiable().count(), 3);
+        for algorithm in certifiable() {
+            let (client_config, server_config) = configs(algorithm);
+            let mut client = ClientHandshake::new(client_config);
+            let mut server = ServerHandshake::new(server_config);
+
+            let (server_hello, server_flight) = hellos(&mut client, &mut server);
+            assert_eq!(server.state(), HandshakeState::WaitFinished);
+            assert_eq!(client.handle(server_hello).unwrap(), None);
+            assert_eq!(client.state(), HandshakeState::WaitFinished);
+            let finished = client.handle(server_flight).unwrap().unwrap();
+            assert_eq!(client.state(), HandshakeState::Connected);
+            assert!(server.traffic_keys().is_none());
+            assert_eq!(server.handle(finished).unwrap(), vec![]);
+            assert_eq!(server.state(), HandshakeState::Connected);
+
+            let keys = client.traffic_keys().unwrap();
+            assert_eq!(keys, server.traffic_keys().unwrap(), "{algorithm}");
+            assert_eq!(keys.suite, CipherSuite::Aes256Gcm); // server preference wins
+            assert_ne!(keys.client_write, keys.server_write);
+        }
+    }
+
+    #[test]
+    fn untrusted_certificate_fails_the_client() {
+        let (client_config, _) = configs(SignatureAlgorithm::Ed25519);
+        let (_, server_config) = configs(SignatureAlgorithm::Ed25519); // issued by another CA of the same name
+        let mut client = ClientHandshake::new(client_config);
+        let mut server = ServerHandshake::new(server_config);
+        let (server_hello, server_flight) = hellos(&mut client, &mut server);
+        client.handle(server_hello).unwrap();
+        assert_eq!(client.handle(server_flight.clone()), Err(HandshakeError::Certificate(CertificateError::BadSignature)));
+        assert_eq!(client.state(), HandshakeState::Failed);
+        assert_eq!(client.handle(server_flight), Err(HandshakeError::AlreadyFailed));
+        assert!(client.traffic_keys().is_none());
+
+        let (mut client_config, server_config) = configs(SignatureAlgorithm::Ed25519);
+        client_config.server_name = "other.test".to_string();
+        let expected = CertificateError::NameMismatch("other.test".to_string());
+        assert_eq!(client_verdict(client_config, server_config), Err(HandshakeError::Certificate(expected)));
+    }
+
+    #[test]
+    fn only_the_certified_key_can_sign() {
+        // another key of the certified type
+        let (client_config, mut server_config) = configs(SignatureAlgorithm::EcdsaP256);
+        server_config.signing_key = SignatureAlgorithm::EcdsaP256.generate().0;
+        let rejected = Err(HandshakeError::BadSignature(SignatureError::VerificationFailed));
+        assert_eq!(client_verdict(client_config, server_config), rejected);
+
+        // a key of another type, announced as such
+        let (client_config, mut server_config) = configs(SignatureAlgorithm::EcdsaP256);
+        server_config.signature_algorithm = SignatureAlgorithm::Ed25519;
+        server_config.signing_key = SignatureAlgorithm::Ed25519.generate().0;
+        let rejected = Err(HandshakeError::SignatureAlgorithmMismatch(SignatureAlgorithm::Ed25519));
+        assert_eq!(client_verdict(client_config, server_config), rejected);
+
+        // Ed25519ph is the same key type as Ed25519
+        let (client_config, mut server_config) = configs(SignatureAlgorithm::Ed25519);
+        server_config.signature_algorithm = SignatureAlgorithm::Ed25519ph;
+        assert!(client_verdict(client_config, server_config).is_ok());
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let (client_config, server_config) = configs(SignatureAlgorithm::EcdsaP256);
+        let mut client = ClientHandshake::new(client_config);
+        let mut server = ServerHandshake::new(server_config);
+        let (server_hello, server_flight) = hellos(&mut client, &mut server);
+        client.handle(server_hello).unwrap();
+        let Message::ServerFlight(mut env) = server_flight else { unreachable!() };
+        env.ciphertext[0] ^= 1;
+        assert_eq!(client.handle(Message::ServerFlight(env)), Err(HandshakeError::Decryption));
+
+        // a ClientFinished from a different handshake
+        let (client_config, server_config) = configs(SignatureAlgorithm::Ed25519);
+        let mut client = ClientHandshake::new(client_config);
+        let mut server = ServerHandshake::new(server_config);
+        let (server_hello, server_flight) = hellos(&mut client, &mut server);
+        client.handle(server_hello).unwrap();
+        client.handle(server_flight).unwrap();
+        let (other_client_config, other_server_config) = configs(SignatureAlgorithm::Ed25519);
+        let mut other_client = ClientHandshake::new(other_client_config);
+        let mut other_server = ServerHandshake::new(other_server_config);
+        let (other_hello, other_flight) = hellos(&mut other_client, &mut other_server);
+        other_client.handle(other_hello).unwrap();
+        let other_finished = other_client.handle(other_flight).unwrap().unwrap();
+        assert_eq!(server.handle(other_finished), Err(HandshakeError::Decryption));
+        assert_eq!(server.state(), HandshakeState::Failed);
+    }
+
+    #[test]
+    fn tampered_suite_list_fails_the_handshake() {
+        // a man in the middle drops AES-256-GCM from the offer, so the server settles for ChaCha20
+        let (client_config, server_config) = configs(SignatureAlgorithm::Ed25519);
+        let mut client = ClientHandshake::new(client_config);
+        let mut server = ServerHandshake::new(server_config);
+        let Message::ClientHello(mut hello) = client.start().unwrap() else { unreachable!() };
+        hello.cipher_suites = vec![CipherSuite::ChaCha20Poly1305];
+        let mut flight = server.handle(Message::ClientHello(hello)).unwrap();
+        let server_flight = flight.pop().unwrap();
+        let Message::ServerHello(server_hello) = flight.pop().unwrap() else { unreachable!() };
+        assert_eq!(server_hello.cipher_suite, CipherSuite::ChaCha20Poly1305);
+
+        client.handle(Message::ServerHello(server_hello)).unwrap();
+        let rejected = Err(HandshakeError::BadSignature(SignatureError::VerificationFailed));
+        assert_eq!(client.handle(server_flight), rejected);
+        assert!(client.traffic_keys().is_none());
+    }
+
+    #[test]
+    fn protocol_violations_are_typed_errors() {
+        let (client_config, mut server_config) = configs(SignatureAlgorithm::Ed25519);
+        let mut client = ClientHandshake::new(client_config);
+        let hello = client.start().unwrap();
+        assert_eq!(client.start(), Err(HandshakeError::UnexpectedMessage { state: HandshakeState::WaitServerHello, message: "start" }));
+        assert_eq!(
+            client.handle(hello.clone()),
+            Err(HandshakeError::UnexpectedMessage { state: HandshakeState::WaitServerHello, message: "ClientHello" })
+        );
+
+        server_config.cipher_suites = vec![CipherSuite::Aes256GcmSiv];
+        let mut server = ServerHandshake::new(server_config);
+        assert_eq!(server.handle(hello), Err(HandshakeError::NoCommonCipherSuite));
+
+        let (client_config, server_config) = configs(SignatureAlgorithm::Ed25519);
+        let mut server = ServerHandshake::new(server_config);
+        let Message::ClientHello(mut hello) = ClientHandshake::new(client_config).start().unwrap() else { unreachable!() };
+        hello.key_share = [0u8; KEY_SHARE_LEN]; // low-order point
+        assert_eq!(server.handle(Message::ClientHello(hello)), Err(HandshakeError::InvalidKeyShare));
+        assert_eq!(server.state(), HandshakeState::Failed);
+    }
+}
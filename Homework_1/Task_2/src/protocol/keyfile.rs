@@ -0,0 +1,160 @@
+//! PEM files for the handshake's certificates and long-term keys, as read by `hs-server` and `hs-client`:
+//! - "CERTIFICATE": a DER X.509 certificate per block; a server's chain file lists its own
+//!   certificate first, then each one's issuer, and a client's trust store file lists roots
+//! - "HANDSHAKE PRIVATE KEY": signature algorithm id (2) | 32-byte signing key of a certified key
+
+use std::fmt;
+
+use pkcs8::LineEnding;
+use pkcs8::der::pem;
+use zeroize::Zeroizing;
+
+use crate::crypto::secret::SecretKey;
+use crate::crypto::signature_scheme::{SIGNING_KEY_LEN, SignatureAlgorithm};
+
+use super::x509::{self, CertificateError, TrustStore};
+
+pub const CERTIFICATE_LABEL: &str = "CERTIFICATE";
+pub const PRIVATE_KEY_LABEL: &str = "HANDSHAKE PRIVATE KEY";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyFileError {
+    /// Not PEM, or invalid Base64.
+    Malformed,
+    WrongLabel { expected: &'static str, found: String },
+    BadLength,
+    UnknownSignatureAlgorithm(u16),
+    Certificate(CertificateError),
+    /// The private key does not belong to the certified public key.
+    KeyMismatch,
+}
+
+impl fmt::Display for KeyFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyFileError::Malformed => write!(f, "malformed PEM"),
+            KeyFileError::WrongLabel { expected, found } => write!(f, "expected a {expected} PEM block, found {found}"),
+            KeyFileError::BadLength => write!(f, "key has the wrong length"),
+            KeyFileError::UnknownSignatureAlgorithm(id) => write!(f, "unknown signature algorithm 0x{id:04x}"),
+            KeyFileError::Certificate(e) => write!(f, "bad certificate: {e}"),
+            KeyFileError::KeyMismatch => write!(f, "private key does not match the certificate"),
+        }
+    }
+}
+
+impl std::error::Error for KeyFileError {}
+
+fn encode(label: &str, bytes: &[u8]) -> String {
+    pem::encode_string(label, LineEnding::LF, bytes).unwrap()
+}
+
+fn decode(text: &str, expected: &'static str) -> Result<Zeroizing<Vec<u8>>, KeyFileError> {
+    let (label, bytes) = pem::decode_vec(text.trim().as_bytes()).map_err(|_| KeyFileError::Malformed)?;
+    let bytes = Zeroizing::new(bytes);
+    if label != expected {
+        return Err(KeyFileError::WrongLabel { expected, found: label.to_string() });
+    }
+    Ok(bytes)
+}
+
+/// The PEM blocks of `text`, each from its BEGIN line to its END line.
+fn blocks(text: &str) -> Result<Vec<&str>, KeyFileError> {
+    let mut blocks = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let end = rest.find("-----END ").ok_or(KeyFileError::Malformed)? + "-----END ".len();
+        let end = end + rest[end..].find("-----").ok_or(KeyFileError::Malformed)? + "-----".len();
+        blocks.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Ok(blocks)
+}
+
+pub fn certificates_to_pem(chain: &[Vec<u8>]) -> String {
+    chain.iter().map(|der| encode(CERTIFICATE_LABEL, der)).collect()
+}
+
+/// At least one certificate, in file order.
+pub fn certificates_from_pem(text: &str) -> Result<Vec<Vec<u8>>, KeyFileError> {
+    let chain = blocks(text)?.into_iter().map(|block| Ok(decode(block, CERTIFICATE_LABEL)?.to_vec())).collect::<Result<Vec<_>, _>>()?;
+    if chain.is_empty() {
+        return Err(KeyFileError::Malformed);
+    }
+    Ok(chain)
+}
+
+pub fn trust_store_from_pem(text: &str) -> Result<TrustStore, KeyFileError> {
+    TrustStore::new(certificates_from_pem(text)?).map_err(KeyFileError::Certificate)
+}
+
+pub fn private_key_to_pem(algorithm: SignatureAlgorithm, sk: &SecretKey<SIGNING_KEY_LEN>) -> Zeroizing<String> {
+    let bytes = Zeroizing::new([&algorithm.id().to_be_bytes()[..], sk.expose_secret()].concat());
+    Zeroizing::new(encode(PRIVATE_KEY_LABEL, &bytes))
+}
+
+pub fn private_key_from_pem(text: &str) -> Result<(SignatureAlgorithm, SecretKey<SIGNING_KEY_LEN>), KeyFileError> {
+    let bytes = decode(text, PRIVATE_KEY_LABEL)?;
+    if bytes.len() != 2 + SIGNING_KEY_LEN {
+        return Err(KeyFileError::BadLength);
+    }
+    let id = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let algorithm = SignatureAlgorithm::from_id(id).ok_or(KeyFileError::UnknownSignatureAlgorithm(id))?;
+    Ok((algorithm, SecretKey::from_slice(&bytes[2..]).unwrap()))
+}
+
+/// Certificate chain and private key as a server loads them, checked to belong together.
+pub fn server_credentials_from_pem(chain: &str, key: &str) -> Result<(Vec<Vec<u8>>, SignatureAlgorithm, SecretKey<SIGNING_KEY_LEN>), KeyFileError> {
+    let chain = certificates_from_pem(chain)?;
+    let (algorithm, sk) = private_key_from_pem(key)?;
+    let certified = x509::certified_key(&chain[0]).map_err(KeyFileError::Certificate)?;
+    if !certified.supports(algorithm) || algorithm.verifying_key(sk.expose_secret()).ok() != Some(certified.public_key) {
+        return Err(KeyFileError::KeyMismatch);
+    }
+    Ok((chain, algorithm, sk))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ca::CertificateAuthority;
+
+    #[test]
+    fn files_roundtrip_and_belong_together() {
+        let ca = CertificateAuthority::generate("Test CA").unwrap();
+        let store = trust_store_from_pem(&certificates_to_pem(&[ca.certificate().to_vec()])).unwrap();
+        assert_eq!(store.len(), 1);
+
+        for algorithm in [SignatureAlgorithm::Ed25519, SignatureAlgorithm::Ed25519ph, SignatureAlgorithm::EcdsaP256] {
+            let (cert, key) = ca.issue_server("server.test", algorithm).unwrap();
+            let chain = vec![cert, ca.certificate().to_vec()];
+            let (chain_pem, key_pem) = (certificates_to_pem(&chain), private_key_to_pem(algorithm, key.signing_key()));
+            assert!(chain_pem.starts_with("-----BEGIN CERTIFICATE-----\n"));
+            let loaded = server_credentials_from_pem(&chain_pem, &key_pem).unwrap();
+            assert_eq!(loaded, (chain, algorithm, key.signing_key().clone()));
+
+            let (other_sk, _) = algorithm.generate();
+            let other_key = private_key_to_pem(algorithm, &other_sk);
+            assert_eq!(server_credentials_from_pem(&chain_pem, &other_key), Err(KeyFileError::KeyMismatch));
+        }
+
+        // the certified key under another scheme
+        let (cert, key) = ca.issue_server("server.test", SignatureAlgorithm::EcdsaP256).unwrap();
+        let key_pem = private_key_to_pem(SignatureAlgorithm::EcdsaSecp256k1, key.signing_key());
+        assert_eq!(server_credentials_from_pem(&certificates_to_pem(&[cert]), &key_pem), Err(KeyFileError::KeyMismatch));
+    }
+
+    #[test]
+    fn rejects_wrong_files() {
+        let (sk, _) = SignatureAlgorithm::Ed25519.generate();
+        let private = private_key_to_pem(SignatureAlgorithm::Ed25519, &sk);
+        assert_eq!(
+            certificates_from_pem(&private),
+            Err(KeyFileError::WrongLabel { expected: CERTIFICATE_LABEL, found: PRIVATE_KEY_LABEL.to_string() })
+        );
+        assert_eq!(certificates_from_pem("not pem"), Err(KeyFileError::Malformed));
+        assert_eq!(certificates_from_pem(""), Err(KeyFileError::Malformed));
+        assert_eq!(private_key_from_pem(&encode(PRIVATE_KEY_LABEL, &[1; 33])), Err(KeyFileError::BadLength));
+        assert_eq!(private_key_from_pem(&encode(PRIVATE_KEY_LABEL, &[0x99; 34])), Err(KeyFileError::UnknownSignatureAlgorithm(0x9999)));
+        assert_eq!(trust_store_from_pem(&encode(CERTIFICATE_LABEL, &[0; 3])).unwrap_err(), KeyFileError::Certificate(CertificateError::Malformed));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod handshake;
+pub mod wire;
+pub mod keyfile;
+pub mod transport;
+pub mod record;
+pub mod x509;
+pub mod ca;
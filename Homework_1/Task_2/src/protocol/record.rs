@@ -0,0 +1,397 @@
+//! Record layer for application data after the handshake, modelled on TLS 1.3 (RFC 8446 §5).
+//!
+//! Record (integers big-endian):
+//!   record type 24 (1) | version (2) | length (2) | AEAD(content | content type (1) | zero padding)
+//!
+//! The 5-byte header is the associated data. Each direction has its own key and IV, expanded from
+//! k_3_c or k_3_s with HKDF-SHA-256 ("key", "iv"), and record n is sealed under nonce IV XOR n
+//! (`aead_key::CounterNonces`), so reordered, replayed or dropped records fail to decrypt. The real
+//! content type travels inside the ciphertext: application data, or an alert such as close_notify.
+//! A stream that ends without close_notify is reported as truncated, not as a clean EOF.
+//!
+//! Unlike TLS, the record type is not 23: that byte already starts a `SealedClientFinished` handshake
+//! frame, and the two layers share one stream. Every first byte belongs to at most one of them.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::crypto::aead_key::{CounterNonces, KeyError, OpeningKey, SealingKey};
+use crate::crypto::secret::SecretKey;
+
+use super::handshake::TrafficKeys;
+use super::wire::PROTOCOL_VERSION;
+
+pub const RECORD_HEADER_LEN: usize = 5;
+/// Largest amount of application data in one record.
+pub const MAX_FRAGMENT_LEN: usize = 1 << 14;
+/// Largest record body: content type, padding and tag may add at most 256 bytes (RFC 8446 §5.2).
+pub const MAX_CIPHERTEXT_LEN: usize = MAX_FRAGMENT_LEN + 256;
+
+/// Outer record type, distinct from every `wire::MessageType`; the inner `ContentType` is encrypted.
+const RECORD_TYPE: u8 = 24;
+const ALERT_LEVEL_WARNING: u8 = 1;
+pub const ALERT_CLOSE_NOTIFY: u8 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentType {
+    Alert = 21,
+    ApplicationData = 23,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    /// Sealing failed or the sequence number is exhausted; the connection must be rekeyed.
+    Key(KeyError),
+    /// Wrong key, modified, reordered or replayed record.
+    Decryption,
+    BadRecordType(u8),
+    UnsupportedVersion(u16),
+    /// Record body over `MAX_CIPHERTEXT_LEN`, or decrypted content over `MAX_FRAGMENT_LEN`.
+    RecordTooLong(usize),
+    /// Decrypted record is all zeros.
+    MissingContentType,
+    UnknownContentType(u8),
+    MalformedAlert,
+    /// The peer sent an alert other than close_notify.
+    Alert(u8),
+    /// The stream ended without close_notify.
+    Truncated,
+    /// Write after `close`.
+    Closed,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Key(e) => write!(f, "record key: {e}"),
+            RecordError::Decryption => write!(f, "record failed to decrypt"),
+            RecordError::BadRecordType(t) => write!(f, "bad record type {t}"),
+            RecordError::UnsupportedVersion(v) => write!(f, "unsupported record version {v}"),
+            RecordError::RecordTooLong(len) => write!(f, "record of {len} bytes is too long"),
+            RecordError::MissingContentType => write!(f, "record has no content type"),
+            RecordError::UnknownContentType(t) => write!(f, "unknown content type {t}"),
+            RecordError::MalformedAlert => write!(f, "malformed alert"),
+            RecordError::Alert(d) => write!(f, "peer sent alert {d}"),
+            RecordError::Truncated => write!(f, "connection closed without close_notify"),
+            RecordError::Closed => write!(f, "channel already closed for writing"),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<RecordError> for io::Error {
+    fn from(e: RecordError) -> Self {
+        let kind = match e {
+            RecordError::Truncated => io::ErrorKind::UnexpectedEof,
+            RecordError::Closed => io::ErrorKind::BrokenPipe,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+impl From<KeyError> for RecordError {
+    fn from(e: KeyError) -> Self {
+        match e {
+            KeyError::Decryption => RecordError::Decryption,
+            e => RecordError::Key(e),
+        }
+    }
+}
+
+/// Which side of the handshake this end was; picks the write and read keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+/// Per-direction record key and IV from a k_3 traffic secret.
+fn expand_traffic_secret(secret: &SecretKey<32>, iv_len: usize) -> (SecretKey<32>, Zeroizing<Vec<u8>>) {
+    let hkdf = Hkdf::<Sha256>::from_prk(secret.expose_secret()).expect("32-byte PRK");
+    let mut key = SecretKey::new([0u8; 32]);
+    let mut iv = Zeroizing::new(vec![0u8; iv_len]);
+    hkdf.expand(b"key", key.expose_secret_mut()).expect("HKDF expand failed");
+    hkdf.expand(b"iv", &mut iv).expect("HKDF expand failed");
+    (key, iv)
+}
+
+fn header(len: usize) -> [u8; RECORD_HEADER_LEN] {
+    let [v0, v1] = PROTOCOL_VERSION.to_be_bytes();
+    let [l0, l1] = (len as u16).to_be_bytes();
+    [RECORD_TYPE, v0, v1, l0, l1]
+}
+
+/// Encrypted, authenticated stream over `S`, keyed by a completed handshake.
+pub struct SecureChannel<S> {
+    stream: S,
+    sealing: SealingKey<CounterNonces>,
+    opening: OpeningKey,
+    tag_len: usize,
+    /// Inner plaintexts are zero-padded to a multiple of this many bytes (1 = no padding).
+    padding: usize,
+    /// Decrypted application data not yet returned by `read`.
+    pending: Zeroizing<Vec<u8>>,
+    pending_pos: usize,
+    received_close: bool,
+    sent_close: bool,
+}
+
+impl<S: Read + Write> SecureChannel<S> {
+    pub fn new(stream: S, keys: TrafficKeys, role: Role) -> Result<Self, RecordError> {
+        let (write_secret, read_secret) = match role {
+            Role::Client => (&keys.client_write, &keys.server_write),
+            Role::Server => (&keys.server_write, &keys.client_write),
+        };
+        let (write_key, write_iv) = expand_traffic_secret(write_secret, keys.suite.nonce_len());
+        let (read_key, read_iv) = expand_traffic_secret(read_secret, keys.suite.nonce_len());
+        Ok(Self {
+            stream,
+            sealing: SealingKey::counter(keys.suite, write_key.expose_secret(), &write_iv)?,
+            opening: OpeningKey::counter(keys.suite, read_key.expose_secret(), &read_iv)?,
+            tag_len: keys.suite.tag_len(),
+            padding: 1,
+            pending: Zeroizing::new(Vec::new()),
+            pending_pos: 0,
+            received_close: false,
+            sent_close: false,
+        })
+    }
+
+    /// Pad every record to a multiple of `block` bytes to hide exact message lengths.
+    pub fn with_padding(mut self, block: usize) -> Self {
+        self.padding = block.max(1);
+        self
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Send close_notify. Reading stays possible until the peer's close_notify arrives.
+    pub fn close(&mut self) -> io::Result<()> {
+        if self.sent_close {
+            return Ok(());
+        }
+        self.send_record(ContentType::Alert, &[ALERT_LEVEL_WARNING, ALERT_CLOSE_NOTIFY])?;
+        self.sent_close = true;
+        self.stream.flush()
+    }
+
+    fn send_record(&mut self, content_type: ContentType, content: &[u8]) -> io::Result<()> {
+        if self.sent_close {
+            return Err(RecordError::Closed.into());
+        }
+        debug_assert!(content.len() <= MAX_FRAGMENT_LEN);
+        let inner_len = (content.len() + 1).next_multiple_of(self.padding).min(MAX_FRAGMENT_LEN + 1);
+        let mut inner = Zeroizing::new(vec![0u8; inner_len]);
+        inner[..content.len()].copy_from_slice(content);
+        inner[content.len()] = content_type as u8;
+
+        let header = header(inner_len + self.tag_len);
+        let body = self.sealing.seal(&inner, &header).map_err(RecordError::from)?;
+        self.stream.write_all(&[&header[..], &body].concat())
+    }
+
+    /// Read and decrypt one record. `Ok(None)` is a clean end of stream (close_notify).
+    fn read_record(&mut self) -> io::Result<Option<Zeroizing<Vec<u8>>>> {
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        match self.stream.read_exact(&mut header) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(RecordError::Truncated.into()),
+            result => result?,
+        }
+        if header[0] != RECORD_TYPE {
+            return Err(RecordError::BadRecordType(header[0]).into());
+        }
+        let version = u16::from_be_bytes([header[1], header[2]]);
+        if version != PROTOCOL_VERSION {
+            return Err(RecordError::UnsupportedVersion(version).into());
+        }
+        let len = u16::from_be_bytes([header[3], header[4]]) as usize;
+        if len > MAX_CIPHERTEXT_LEN {
+            return Err(RecordError::RecordTooLong(len).into());
+        }
+        let mut body = vec![0u8; len];
+        match self.stream.read_exact(&mut body) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(RecordError::Truncated.into()),
+            result => result?,
+        }
+
+        let mut inner = Zeroizing::new(self.opening.open(&body, &header).map_err(RecordError::from)?);
+        let type_at = inner.iter().rposition(|&b| b != 0).ok_or(RecordError::MissingContentType)?;
+        if type_at > MAX_FRAGMENT_LEN {
+            return Err(RecordError::RecordTooLong(type_at).into());
+        }
+        let content_type = inner[type_at];
+        inner.truncate(type_at);
+        match content_type {
+            t if t == ContentType::ApplicationData as u8 => Ok(Some(inner)),
+            t if t == ContentType::Alert as u8 => match inner[..] {
+                [_, ALERT_CLOSE_NOTIFY] => Ok(None),
+                [_, description] => Err(RecordError::Alert(description).into()),
+                _ => Err(RecordError::MalformedAlert.into()),
+            },
+            t => Err(RecordError::UnknownContentType(t).into()),
+        }
+    }
+}
+
+impl<S: Read + Write> Read for SecureChannel<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending_pos == self.pending.len() {
+            if self.received_close || buf.is_empty() {
+                return Ok(0);
+            }
+            match self.read_record()? {
+                Some(data) => {
+                    self.pending = data;
+                    self.pending_pos = 0;
+                }
+                None => self.received_close = true,
+            }
+        }
+        let n = buf.len().min(self.pending.len() - self.pending_pos);
+        buf[..n].copy_from_slice(&self.pending[self.pending_pos..self.pending_pos + n]);
+        self.pending_pos += n;
+        Ok(n)
+    }
+}
+
+impl<S: Read + Write> Write for SecureChannel<S> {
+    /// Seals up to `MAX_FRAGMENT_LEN` bytes of `buf` into one record.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let n = buf.len().min(MAX_FRAGMENT_LEN);
+        self.send_record(ContentType::ApplicationData, &buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::aead::CipherSuite;
+    use crate::protocol::handshake::Message;
+    use crate::protocol::wire::{MessageType, WireError};
+    use std::io::Cursor;
+
+    /// The same traffic keys for both ends, as a completed handshake would give them.
+    fn keys(suite: CipherSuite) -> (TrafficKeys, TrafficKeys) {
+        let (c, s): ([u8; 32], [u8; 32]) = (rand::random(), rand::random());
+        let make = || TrafficKeys { suite, client_write: SecretKey::new(c), server_write: SecretKey::new(s) };
+        (make(), make())
+    }
+
+    /// Records a client sends for `writes`, followed by close_notify.
+    fn client_records(keys: TrafficKeys, writes: &[&[u8]], padding: usize) -> Vec<u8> {
+        let mut client = SecureChannel::new(Cursor::new(Vec::new()), keys, Role::Client).unwrap().with_padding(padding);
+        for data in writes {
+            client.write_all(data).unwrap();
+        }
+        client.close().unwrap();
+        client.into_inner().into_inner()
+    }
+
+    fn server_reads(keys: TrafficKeys, records: Vec<u8>) -> io::Result<Vec<u8>> {
+        let mut server = SecureChannel::new(Cursor::new(records), keys, Role::Server).unwrap();
+        let mut out = Vec::new();
+        server.read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    fn record_error(e: io::Error) -> RecordError {
+        *e.into_inner().unwrap().downcast::<RecordError>().unwrap()
+    }
+
+    #[test]
+    fn roundtrip_in_every_suite() {
+        let big: Vec<u8> = (0..40_000u32).map(|i| i as u8).collect();
+        for suite in CipherSuite::ALL {
+            let (client_keys, server_keys) = keys(suite);
+            let records = client_records(client_keys, &[b"hello", &big, b""], 1);
+            // "hello", three records for 40000 bytes (16384 + 16384 + 7232), close_notify
+            let overhead = 5 * RECORD_HEADER_LEN + 5 * (1 + suite.tag_len()) + 2;
+            assert_eq!(records.len(), 5 + big.len() + overhead, "{suite:?}");
+            assert_eq!(server_reads(server_keys, records).unwrap(), [&b"hello"[..], &big].concat());
+        }
+    }
+
+    #[test]
+    fn directions_and_order_are_bound() {
+        let (client_keys, server_keys) = keys(CipherSuite::ChaCha20Poly1305);
+        let records = client_records(client_keys, &[b"one", b"two"], 1);
+        let record_len = RECORD_HEADER_LEN + 4 + 16;
+
+        // a client cannot read what a client wrote (reflection)
+        let mut reflected = SecureChannel::new(Cursor::new(records.clone()), keys(CipherSuite::ChaCha20Poly1305).0, Role::Client).unwrap();
+        assert_eq!(record_error(reflected.read(&mut [0; 8]).unwrap_err()), RecordError::Decryption);
+
+        let swapped = [&records[record_len..2 * record_len], &records[..record_len], &records[2 * record_len..]].concat();
+        assert_eq!(record_error(server_reads(keys(CipherSuite::ChaCha20Poly1305).1, swapped).unwrap_err()), RecordError::Decryption);
+
+        let mut flipped = records.clone();
+        flipped[RECORD_HEADER_LEN] ^= 1;
+        let (_, fresh) = keys(CipherSuite::ChaCha20Poly1305);
+        assert_eq!(record_error(server_reads(fresh, flipped).unwrap_err()), RecordError::Decryption);
+
+        // dropping close_notify is a truncation, not a clean end of stream
+        let truncated = records[..2 * record_len].to_vec();
+        let err = server_reads(server_keys, truncated).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(record_error(err), RecordError::Truncated);
+    }
+
+    #[test]
+    fn padding_hides_lengths() {
+        let (client_keys, server_keys) = keys(CipherSuite::Aes256Gcm);
+        let records = client_records(client_keys, &[b"hi", b"a much longer message"], 64);
+        // both application records and close_notify are padded to 64 bytes before the tag
+        assert_eq!(records.len(), 3 * (RECORD_HEADER_LEN + 64 + 16));
+        assert_eq!(u16::from_be_bytes([records[3], records[4]]), 64 + 16);
+        assert_eq!(server_reads(server_keys, records).unwrap(), b"hia much longer message");
+    }
+
+    #[test]
+    fn rejects_bad_records_and_alerts() {
+        let (client_keys, server_keys) = keys(CipherSuite::Aes256Gcm);
+        let mut client = SecureChannel::new(Cursor::new(Vec::new()), client_keys, Role::Client).unwrap();
+        client.send_record(ContentType::Alert, &[2, 40]).unwrap(); // fatal handshake_failure
+        client.close().unwrap();
+        client.close().unwrap();
+        assert_eq!(client.write(b"late").unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+        let records = client.into_inner().into_inner();
+        assert_eq!(record_error(server_reads(server_keys, records).unwrap_err()), RecordError::Alert(40));
+
+        let bad = |bytes: &[u8]| record_error(server_reads(keys(CipherSuite::Aes256Gcm).1, bytes.to_vec()).unwrap_err());
+        assert_eq!(bad(&[22, 0, 1, 0, 0]), RecordError::BadRecordType(22));
+        assert_eq!(bad(&[24, 3, 3, 0, 0]), RecordError::UnsupportedVersion(0x0303));
+        assert_eq!(bad(&[24, 0, 1, 0x41, 0x01]), RecordError::RecordTooLong(0x4101));
+        assert_eq!(bad(&[24, 0, 1, 0, 20, 0]), RecordError::Truncated);
+    }
+
+    #[test]
+    fn records_and_handshake_frames_are_told_apart() {
+        let [v0, v1] = PROTOCOL_VERSION.to_be_bytes();
+        for ty in MessageType::ALL {
+            let bad = record_error(server_reads(keys(CipherSuite::Aes256Gcm).1, vec![ty as u8, v0, v1, 0, 0]).unwrap_err());
+            assert_eq!(bad, RecordError::BadRecordType(ty as u8));
+        }
+        let records = client_records(keys(CipherSuite::Aes256Gcm).0, &[b"hi"], 0);
+        assert_eq!(Message::from_bytes(&records), Err(WireError::UnknownMessageType(RECORD_TYPE)));
+    }
+}
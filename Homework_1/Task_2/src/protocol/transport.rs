@@ -0,0 +1,215 @@
+//! Runs the handshake over a byte stream, one `wire` frame per message. `client_handshake` and
+//! `server_handshake` work on any `Read + Write`; `connect` and `accept` add TCP and timeouts.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use super::handshake::{ClientConfig, ClientHandshake, HandshakeError, Message, ServerConfig, ServerHandshake, TrafficKeys};
+use super::wire::{self, HEADER_LEN, WireError};
+
+#[derive(Debug)]
+pub enum TransportError {
+    Io(io::Error),
+    /// The peer did not send or accept data within the configured timeout.
+    Timeout,
+    /// The peer closed the connection before the handshake finished.
+    Closed,
+    Wire(WireError),
+    Handshake(HandshakeError),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Io(e) => write!(f, "I/O error: {e}"),
+            TransportError::Timeout => write!(f, "timed out waiting for the peer"),
+            TransportError::Closed => write!(f, "connection closed during the handshake"),
+            TransportError::Wire(e) => write!(f, "malformed message from peer: {e}"),
+            TransportError::Handshake(e) => write!(f, "handshake failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+impl From<io::Error> for TransportError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            // a read or write timeout shows up as WouldBlock on Unix, TimedOut on Windows
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => TransportError::Timeout,
+            io::ErrorKind::UnexpectedEof => TransportError::Closed,
+            _ => TransportError::Io(e),
+        }
+    }
+}
+
+impl From<WireError> for TransportError {
+    fn from(e: WireError) -> Self {
+        TransportError::Wire(e)
+    }
+}
+
+impl From<HandshakeError> for TransportError {
+    fn from(e: HandshakeError) -> Self {
+        TransportError::Handshake(e)
+    }
+}
+
+pub fn write_message<W: Write>(stream: &mut W, message: &Message) -> Result<(), TransportError> {
+    stream.write_all(&message.to_bytes()?)?;
+    Ok(stream.flush()?)
+}
+
+/// Read one frame: the header first, so an oversized length is rejected before reading the body.
+pub fn read_message<R: Read>(stream: &mut R) -> Result<Message, TransportError> {
+    let mut header = [0u8; HEADER_LEN];
+    stream.read_exact(&mut header)?;
+    let (ty, len) = wire::parse_header(&header)?;
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body)?;
+    Ok(Message::from_body(ty, &body)?)
+}
+
+pub fn client_handshake<S: Read + Write>(stream: &mut S, config: ClientConfig) -> Result<TrafficKeys, TransportError> {
+    let mut client = ClientHandshake::new(config);
+    write_message(stream, &client.start()?)?;
+    loop {
+        if let Some(reply) = client.handle(read_message(stream)?)? {
+            write_message(stream, &reply)?;
+        }
+        if let Some(keys) = client.take_traffic_keys() {
+            return Ok(keys);
+        }
+    }
+}
+
+pub fn server_handshake<S: Read + Write>(stream: &mut S, config: ServerConfig) -> Result<TrafficKeys, TransportError> {
+    let mut server = ServerHandshake::new(config);
+    loop {
+        for reply in server.handle(read_message(stream)?)? {
+            write_message(stream, &reply)?;
+        }
+        if let Some(keys) = server.take_traffic_keys() {
+            return Ok(keys);
+        }
+    }
+}
+
+fn set_timeouts(stream: &TcpStream, timeout: Duration) -> Result<(), TransportError> {
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    Ok(())
+}
+
+/// Connect to the first address of `addr` that answers within `timeout`, then run the client side;
+/// `timeout` also bounds every read and write of the handshake.
+pub fn connect<A: ToSocketAddrs>(addr: A, config: ClientConfig, timeout: Duration) -> Result<(TcpStream, TrafficKeys), TransportError> {
+    let mut last = io::Error::new(io::ErrorKind::InvalidInput, "address resolved to nothing");
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(mut stream) => {
+                set_timeouts(&stream, timeout)?;
+                let keys = client_handshake(&mut stream, config)?;
+                return Ok((stream, keys));
+            }
+            Err(e) => last = e,
+        }
+    }
+    Err(last.into())
+}
+
+/// Accept one connection and run the server side, with `timeout` on every read and write.
+pub fn accept(listener: &TcpListener, config: ServerConfig, timeout: Duration) -> Result<(TcpStream, TrafficKeys), TransportError> {
+    let (mut stream, _) = listener.accept()?;
+    set_timeouts(&stream, timeout)?;
+    let keys = server_handshake(&mut stream, config)?;
+    Ok((stream, keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::aead::CipherSuite;
+    use crate::crypto::signature_scheme::SignatureAlgorithm;
+    use crate::protocol::ca::CertificateAuthority;
+    use crate::protocol::x509::TrustStore;
+    use std::thread;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn configs() -> (ClientConfig, ServerConfig) {
+        let ca = CertificateAuthority::generate("Test CA").unwrap();
+        let (certificate, key) = ca.issue_server("127.0.0.1", SignatureAlgorithm::EcdsaP256).unwrap();
+        let client = ClientConfig {
+            trust_store: TrustStore::new([ca.certificate().to_vec()]).unwrap(),
+            server_name: "127.0.0.1".to_string(),
+            cipher_suites: vec![CipherSuite::XChaCha20Poly1305],
+        };
+        let server = ServerConfig {
+            certificate_chain: vec![certificate],
+            signature_algorithm: SignatureAlgorithm::EcdsaP256,
+            signing_key: key.signing_key().clone(),
+            cipher_suites: CipherSuite::ALL.to_vec(),
+        };
+        (client, server)
+    }
+
+    #[test]
+    fn handshake_over_localhost() {
+        let (client_config, server_config) = configs();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || accept(&listener, server_config, TIMEOUT).map(|(_, keys)| keys));
+
+        let (_, client_keys) = connect(addr, client_config, TIMEOUT).unwrap();
+        let server_keys = server.join().unwrap().unwrap();
+        assert_eq!(client_keys, server_keys);
+        assert_eq!(client_keys.suite, CipherSuite::XChaCha20Poly1305);
+    }
+
+    #[test]
+    fn silent_or_hostile_peers_fail_cleanly() {
+        // a server that accepts but never answers
+        let (client_config, _) = configs();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let err = connect(addr, client_config, Duration::from_millis(200)).unwrap_err();
+        assert!(matches!(err, TransportError::Timeout), "{err}");
+        drop(listener);
+
+        // a client announcing a huge frame, then hanging up
+        let (_, server_config) = configs();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peer = thread::spawn(move || TcpStream::connect(addr).unwrap().write_all(&[1, 0, 1, 0xff, 0xff, 0xff]).unwrap());
+        let err = accept(&listener, server_config, TIMEOUT).unwrap_err();
+        assert!(matches!(err, TransportError::Wire(WireError::TooLong(0xffffff))), "{err}");
+        peer.join().unwrap();
+
+        let (_, server_config) = configs();
+        let mut truncated: &[u8] = &[1, 0, 1, 0, 0, 69, 0];
+        let err = server_handshake(&mut ReadOnly(&mut truncated), server_config).unwrap_err();
+        assert!(matches!(err, TransportError::Closed), "{err}");
+    }
+
+    /// In-memory stream for feeding a handshake canned bytes.
+    struct ReadOnly<'a, 'b>(&'a mut &'b [u8]);
+
+    impl Read for ReadOnly<'_, '_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for ReadOnly<'_, '_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
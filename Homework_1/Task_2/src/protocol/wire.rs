@@ -0,0 +1,494 @@
+//! Binary encoding of the handshake messages. Every message is one frame (integers big-endian):
+//!   type (1) | version (2) | body length (3) | body
+//!
+//! Bodies:
+//!   ClientHello          nonce (32) | key share (32) | suites length (1) | suite ids (2 each)
+//!   ServerHello          nonce (32) | key share (32) | suite id (2)
+//!   ServerCert           certificate | signature (64)
+//!   ServerFinished       MAC (32)
+//!   ClientFinished       MAC (32)
+//!   SealedServerFlight   envelope sealing the ServerCert frame followed by the ServerFinished frame
+//!   SealedClientFinished envelope sealing the ClientFinished frame
+//!
+//! The certificate part is signature algorithm id (2) | chain length (1) | per certificate: length (2)
+//! | DER, the server's X.509 certificate first; the server's signature covers it (see `x509` for
+//! what makes a chain valid). Parsing is strict: unknown types, versions, suites and algorithms,
+//! bodies over `MAX_BODY_LEN`, fields of the wrong length and trailing bytes are errors.
+
+use std::fmt;
+
+use crate::crypto::aead::CipherSuite;
+use crate::crypto::envelope::{Envelope, EnvelopeError};
+use crate::crypto::signature_scheme::{SIGNATURE_LEN, SignatureAlgorithm};
+
+use super::handshake::{ClientFinished, ClientHello, KEY_SHARE_LEN, MAC_LEN, Message, NONCE_LEN, ServerCert, ServerFinished, ServerHello};
+
+pub const PROTOCOL_VERSION: u16 = 1;
+pub const HEADER_LEN: usize = 6;
+/// Largest body a frame may carry; far above any honest handshake message.
+pub const MAX_BODY_LEN: usize = 1 << 14;
+/// Largest total size of the DER certificates in a ServerCert, so the sealed flight stays under
+/// `MAX_BODY_LEN`.
+pub const MAX_CERTIFICATE_CHAIN_LEN: usize = 12 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageType {
+    ClientHello = 1,
+    ServerHello = 2,
+    ServerCert = 11,
+    ServerFinished = 20,
+    ClientFinished = 21,
+    SealedServerFlight = 22,
+    SealedClientFinished = 23,
+}
+
+impl MessageType {
+    pub const ALL: [MessageType; 7] = [
+        MessageType::ClientHello,
+        MessageType::ServerHello,
+        MessageType::ServerCert,
+        MessageType::ServerFinished,
+        MessageType::ClientFinished,
+        MessageType::SealedServerFlight,
+        MessageType::SealedClientFinished,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| *t as u8 == byte)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    /// Input ended inside the named field.
+    Truncated(&'static str),
+    UnknownMessageType(u8),
+    UnsupportedVersion(u16),
+    /// Body length over `MAX_BODY_LEN`.
+    TooLong(usize),
+    /// A frame of another type than the protocol allows at this point.
+    UnexpectedType { expected: MessageType, got: MessageType },
+    /// Bytes left over after the named message or field.
+    TrailingData(&'static str),
+    /// A length field with a value the field cannot have.
+    InvalidLength { field: &'static str, len: usize },
+    UnknownCipherSuite(u16),
+    UnknownSignatureAlgorithm(u16),
+    Envelope(EnvelopeError),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::Truncated(field) => write!(f, "input ends inside {field}"),
+            WireError::UnknownMessageType(t) => write!(f, "unknown message type {t}"),
+            WireError::UnsupportedVersion(v) => write!(f, "unsupported protocol version {v}"),
+            WireError::TooLong(len) => write!(f, "message body of {len} bytes exceeds {MAX_BODY_LEN}"),
+            WireError::UnexpectedType { expected, got } => write!(f, "expected {expected:?}, got {got:?}"),
+            WireError::TrailingData(what) => write!(f, "trailing bytes after {what}"),
+            WireError::InvalidLength { field, len } => write!(f, "invalid length {len} for {field}"),
+            WireError::UnknownCipherSuite(id) => write!(f, "unknown cipher suite 0x{id:04x}"),
+            WireError::UnknownSignatureAlgorithm(id) => write!(f, "unknown signature algorithm 0x{id:04x}"),
+            WireError::Envelope(e) => write!(f, "bad envelope: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl From<EnvelopeError> for WireError {
+    fn from(e: EnvelopeError) -> Self {
+        WireError::Envelope(e)
+    }
+}
+
+/// Type and body length from a frame header, so a stream reader knows how much to read next.
+pub fn parse_header(header: &[u8; HEADER_LEN]) -> Result<(MessageType, usize), WireError> {
+    let ty = MessageType::from_byte(header[0]).ok_or(WireError::UnknownMessageType(header[0]))?;
+    let version = u16::from_be_bytes([header[1], header[2]]);
+    if version != PROTOCOL_VERSION {
+        return Err(WireError::UnsupportedVersion(version));
+    }
+    let len = u32::from_be_bytes([0, header[3], header[4], header[5]]) as usize;
+    if len > MAX_BODY_LEN {
+        return Err(WireError::TooLong(len));
+    }
+    Ok((ty, len))
+}
+
+/// Bodies over `MAX_BODY_LEN` cannot be sent.
+fn frame(ty: MessageType, body: &[u8]) -> Result<Vec<u8>, WireError> {
+    if body.len() > MAX_BODY_LEN {
+        return Err(WireError::TooLong(body.len()));
+    }
+    let len = (body.len() as u32).to_be_bytes();
+    Ok([&[ty as u8][..], &PROTOCOL_VERSION.to_be_bytes(), &len[1..], body].concat())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize, field: &'static str) -> Result<&'a [u8], WireError> {
+        if self.0.len() < n {
+            return Err(WireError::Truncated(field));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self, field: &'static str) -> Result<[u8; N], WireError> {
+        Ok(self.take(N, field)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self, field: &'static str) -> Result<u8, WireError> {
+        Ok(self.take(1, field)?[0])
+    }
+
+    fn u16(&mut self, field: &'static str) -> Result<u16, WireError> {
+        Ok(u16::from_be_bytes(self.array(field)?))
+    }
+
+    /// Next frame, which must be of type `expected`; returns its body.
+    fn frame(&mut self, expected: MessageType) -> Result<&'a [u8], WireError> {
+        let (ty, len) = parse_header(&self.array("frame header")?)?;
+        if ty != expected {
+            return Err(WireError::UnexpectedType { expected, got: ty });
+        }
+        self.take(len, "frame body")
+    }
+
+    fn finish(&self, what: &'static str) -> Result<(), WireError> {
+        if self.0.is_empty() { Ok(()) } else { Err(WireError::TrailingData(what)) }
+    }
+}
+
+fn cipher_suite(id: u16) -> Result<CipherSuite, WireError> {
+    CipherSuite::from_id(id).ok_or(WireError::UnknownCipherSuite(id))
+}
+
+impl ClientHello {
+    /// Between 1 and 127 cipher suites can be offered.
+    fn encode_body(&self) -> Result<Vec<u8>, WireError> {
+        let len = 2 * self.cipher_suites.len();
+        let suites_len = u8::try_from(len).ok().filter(|&l| l > 0).ok_or(WireError::InvalidLength { field: "cipher suites", len })?;
+        let mut out = [&self.nonce[..], &self.key_share, &[suites_len]].concat();
+        for suite in &self.cipher_suites {
+            out.extend_from_slice(&suite.id().to_be_bytes());
+        }
+        Ok(out)
+    }
+
+    fn decode_body(body: &[u8]) -> Result<Self, WireError> {
+        let mut r = Reader(body);
+        let nonce = r.array::<NONCE_LEN>("client nonce")?;
+        let key_share = r.array::<KEY_SHARE_LEN>("client key share")?;
+        let len = r.u8("cipher suites length")? as usize;
+        if len == 0 || !len.is_multiple_of(2) {
+            return Err(WireError::InvalidLength { field: "cipher suites", len });
+        }
+        let mut suites = Reader(r.take(len, "cipher suites")?);
+        let cipher_suites = (0..len / 2).map(|_| cipher_suite(suites.u16("cipher suite")?)).collect::<Result<_, _>>()?;
+        r.finish("ClientHello")?;
+        Ok(Self { nonce, key_share, cipher_suites })
+    }
+}
+
+impl ServerHello {
+    fn encode_body(&self) -> Vec<u8> {
+        [&self.nonce[..], &self.key_share, &self.cipher_suite.id().to_be_bytes()].concat()
+    }
+
+    fn decode_body(body: &[u8]) -> Result<Self, WireError> {
+        let mut r = Reader(body);
+        let nonce = r.array::<NONCE_LEN>("server nonce")?;
+        let key_share = r.array::<KEY_SHARE_LEN>("server key share")?;
+        let cipher_suite = cipher_suite(r.u16("cipher suite")?)?;
+        r.finish("ServerHello")?;
+        Ok(Self { nonce, key_share, cipher_suite })
+    }
+}
+
+/// The ClientHello body followed by the ServerHello body: the hellos as the transcript hashes
+/// cover them, so the offered and the chosen suite are authenticated. Both bodies are
+/// self-delimiting.
+pub fn encode_hellos(client: &ClientHello, server: &ServerHello) -> Result<Vec<u8>, WireError> {
+    Ok([client.encode_body()?, server.encode_body()].concat())
+}
+
+/// Empty chains, empty certificates and chains over `MAX_CERTIFICATE_CHAIN_LEN` cannot be sent.
+fn check_chain(chain: &[Vec<u8>]) -> Result<(), WireError> {
+    let total = chain.iter().map(Vec::len).sum();
+    if chain.is_empty() || chain.len() > u8::MAX as usize || total > MAX_CERTIFICATE_CHAIN_LEN {
+        return Err(WireError::InvalidLength { field: "certificate chain", len: total });
+    }
+    match chain.iter().find(|der| der.is_empty()) {
+        Some(_) => Err(WireError::InvalidLength { field: "certificate", len: 0 }),
+        None => Ok(()),
+    }
+}
+
+/// The certificate part of a ServerCert: what the server's signature covers besides the hellos.
+pub fn encode_certificate(algorithm: SignatureAlgorithm, chain: &[Vec<u8>]) -> Result<Vec<u8>, WireError> {
+    check_chain(chain)?;
+    let mut out = [&algorithm.id().to_be_bytes()[..], &[chain.len() as u8]].concat();
+    for der in chain {
+        out.extend_from_slice(&(der.len() as u16).to_be_bytes());
+        out.extend_from_slice(der);
+    }
+    Ok(out)
+}
+
+impl ServerCert {
+    fn encode_body(&self) -> Result<Vec<u8>, WireError> {
+        let cert = encode_certificate(self.algorithm, &self.certificate_chain)?;
+        Ok([&cert[..], &self.signature].concat())
+    }
+
+    fn decode_body(body: &[u8]) -> Result<Self, WireError> {
+        let mut r = Reader(body);
+        let id = r.u16("signature algorithm")?;
+        let algorithm = SignatureAlgorithm::from_id(id).ok_or(WireError::UnknownSignatureAlgorithm(id))?;
+        let count = r.u8("chain length")?;
+        let certificate_chain = (0..count)
+            .map(|_| {
+                let len = r.u16("certificate length")? as usize;
+                Ok(r.take(len, "certificate")?.to_vec())
+            })
+            .collect::<Result<Vec<_>, WireError>>()?;
+        check_chain(&certificate_chain)?;
+        let signature = r.array::<SIGNATURE_LEN>("server signature")?;
+        r.finish("ServerCert")?;
+        Ok(Self { algorithm, certificate_chain, signature })
+    }
+}
+
+fn decode_mac(body: &[u8], what: &'static str) -> Result<[u8; MAC_LEN], WireError> {
+    let mut r = Reader(body);
+    let mac = r.array::<MAC_LEN>("MAC")?;
+    r.finish(what)?;
+    Ok(mac)
+}
+
+/// Plaintext of the server's sealed flight: the ServerCert frame, then the ServerFinished frame.
+pub fn encode_server_flight(cert: &ServerCert, finished: &ServerFinished) -> Result<Vec<u8>, WireError> {
+    Ok([frame(MessageType::ServerCert, &cert.encode_body()?)?, frame(MessageType::ServerFinished, &finished.mac)?].concat())
+}
+
+pub fn decode_server_flight(plaintext: &[u8]) -> Result<(ServerCert, ServerFinished), WireError> {
+    let mut r = Reader(plaintext);
+    let cert = ServerCert::decode_body(r.frame(MessageType::ServerCert)?)?;
+    let finished = ServerFinished { mac: decode_mac(r.frame(MessageType::ServerFinished)?, "ServerFinished")? };
+    r.finish("server flight")?;
+    Ok((cert, finished))
+}
+
+/// Plaintext of the client's sealed flight: the ClientFinished frame.
+pub fn encode_client_finished(finished: &ClientFinished) -> Result<Vec<u8>, WireError> {
+    frame(MessageType::ClientFinished, &finished.mac)
+}
+
+pub fn decode_client_finished(plaintext: &[u8]) -> Result<ClientFinished, WireError> {
+    let mut r = Reader(plaintext);
+    let mac = decode_mac(r.frame(MessageType::ClientFinished)?, "ClientFinished")?;
+    r.finish("client flight")?;
+    Ok(ClientFinished { mac })
+}
+
+impl Message {
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            Message::ClientHello(_) => MessageType::ClientHello,
+            Message::ServerHello(_) => MessageType::ServerHello,
+            Message::ServerFlight(_) => MessageType::SealedServerFlight,
+            Message::ClientFinished(_) => MessageType::SealedClientFinished,
+        }
+    }
+
+    /// One frame; fails for messages the parser would reject as too long.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, WireError> {
+        let body = match self {
+            Message::ClientHello(hello) => hello.encode_body()?,
+            Message::ServerHello(hello) => hello.encode_body(),
+            Message::ServerFlight(env) | Message::ClientFinished(env) => env.to_bytes(),
+        };
+        frame(self.message_type(), &body)
+    }
+
+    /// Parse exactly one frame. The plaintext-only types (ServerCert, ...) are rejected here: they
+    /// only ever travel inside a sealed flight.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WireError> {
+        let mut r = Reader(bytes);
+        let (ty, len) = parse_header(&r.array("frame header")?)?;
+        let message = Self::from_body(ty, r.take(len, "frame body")?)?;
+        r.finish("frame")?;
+        Ok(message)
+    }
+
+    /// Parse a body whose header was already read with `parse_header`.
+    pub fn from_body(ty: MessageType, body: &[u8]) -> Result<Self, WireError> {
+        match ty {
+            MessageType::ClientHello => Ok(Message::ClientHello(ClientHello::decode_body(body)?)),
+            MessageType::ServerHello => Ok(Message::ServerHello(ServerHello::decode_body(body)?)),
+            MessageType::SealedServerFlight => Ok(Message::ServerFlight(Envelope::from_bytes(body)?)),
+            MessageType::SealedClientFinished => Ok(Message::ClientFinished(Envelope::from_bytes(body)?)),
+            MessageType::ServerCert | MessageType::ServerFinished | MessageType::ClientFinished => {
+                Err(WireError::UnknownMessageType(ty as u8))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::envelope;
+    use proptest::prelude::*;
+
+    fn client_hello() -> Message {
+        Message::ClientHello(ClientHello {
+            nonce: [1; NONCE_LEN],
+            key_share: [2; KEY_SHARE_LEN],
+            cipher_suites: vec![CipherSuite::ChaCha20Poly1305, CipherSuite::Aes256Gcm],
+        })
+    }
+
+    fn server_cert() -> ServerCert {
+        ServerCert { algorithm: SignatureAlgorithm::EcdsaP256, certificate_chain: vec![vec![3; 40], vec![4; 20]], signature: [5; SIGNATURE_LEN] }
+    }
+
+    #[test]
+    fn messages_roundtrip() {
+        let sealed = envelope::seal(CipherSuite::Aes256Gcm, b"k_1_s", &[0; 32], b"flight", b"").unwrap();
+        let messages = [
+            client_hello(),
+            Message::ServerHello(ServerHello { nonce: [6; NONCE_LEN], key_share: [7; KEY_SHARE_LEN], cipher_suite: CipherSuite::Aes256GcmSiv }),
+            Message::ServerFlight(sealed.clone()),
+            Message::ClientFinished(sealed),
+        ];
+        for message in messages {
+            let bytes = message.to_bytes().unwrap();
+            assert_eq!(bytes[0], message.message_type() as u8);
+            assert_eq!(parse_header(bytes[..HEADER_LEN].try_into().unwrap()).unwrap().1, bytes.len() - HEADER_LEN);
+            assert_eq!(Message::from_bytes(&bytes).unwrap(), message);
+        }
+
+        let finished = ServerFinished { mac: [8; MAC_LEN] };
+        let flight = encode_server_flight(&server_cert(), &finished).unwrap();
+        assert_eq!(decode_server_flight(&flight).unwrap(), (server_cert(), finished));
+        let client_finished = ClientFinished { mac: [9; MAC_LEN] };
+        assert_eq!(decode_client_finished(&encode_client_finished(&client_finished).unwrap()).unwrap(), client_finished);
+    }
+
+    #[test]
+    fn client_hello_layout() {
+        let bytes = client_hello().to_bytes().unwrap();
+        assert_eq!(&bytes[..HEADER_LEN], &[1, 0, 1, 0, 0, 69]);
+        assert_eq!(&bytes[HEADER_LEN + 64..], &[4, 0x00, 0x02, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn rejects_malformed_frames() {
+        let good = client_hello().to_bytes().unwrap();
+        let with = |i: usize, b: u8| {
+            let mut v = good.clone();
+            v[i] = b;
+            Message::from_bytes(&v)
+        };
+        assert_eq!(Message::from_bytes(&good[..3]), Err(WireError::Truncated("frame header")));
+        assert_eq!(Message::from_bytes(&good[..good.len() - 1]), Err(WireError::Truncated("frame body")));
+        assert_eq!(Message::from_bytes(&[&good[..], &[0]].concat()), Err(WireError::TrailingData("frame")));
+        assert_eq!(with(0, 99), Err(WireError::UnknownMessageType(99)));
+        assert_eq!(with(0, MessageType::ServerCert as u8), Err(WireError::UnknownMessageType(11)));
+        assert_eq!(with(2, 2), Err(WireError::UnsupportedVersion(2)));
+        assert_eq!(with(3, 1), Err(WireError::TooLong(0x010045)));
+
+        // body length stays consistent with the frame, fields inside do not
+        assert_eq!(with(HEADER_LEN + 64, 3), Err(WireError::InvalidLength { field: "cipher suites", len: 3 }));
+        assert_eq!(with(HEADER_LEN + 64, 0), Err(WireError::InvalidLength { field: "cipher suites", len: 0 }));
+        assert_eq!(with(HEADER_LEN + 64, 2), Err(WireError::TrailingData("ClientHello")));
+        assert_eq!(with(HEADER_LEN + 64, 6), Err(WireError::Truncated("cipher suites")));
+        assert_eq!(with(HEADER_LEN + 65, 9), Err(WireError::UnknownCipherSuite(0x0902)));
+        assert_eq!(Message::from_bytes(&frame(MessageType::ServerHello, &[0; 65]).unwrap()), Err(WireError::Truncated("cipher suite")));
+        assert_eq!(Message::from_bytes(&frame(MessageType::SealedServerFlight, b"ENVX").unwrap()), Err(WireError::Envelope(EnvelopeError::BadMagic)));
+    }
+
+    #[test]
+    fn rejects_malformed_flights() {
+        let cert = server_cert();
+        let finished = ServerFinished { mac: [8; MAC_LEN] };
+        let flight = encode_server_flight(&cert, &finished).unwrap();
+
+        // frames swapped, missing or duplicated
+        let swapped = [frame(MessageType::ServerFinished, &finished.mac).unwrap(), frame(MessageType::ServerCert, &cert.encode_body().unwrap()).unwrap()].concat();
+        assert_eq!(
+            decode_server_flight(&swapped),
+            Err(WireError::UnexpectedType { expected: MessageType::ServerCert, got: MessageType::ServerFinished })
+        );
+        assert_eq!(decode_server_flight(&frame(MessageType::ServerCert, &cert.encode_body().unwrap()).unwrap()), Err(WireError::Truncated("frame header")));
+        assert_eq!(decode_server_flight(&[&flight[..], &flight].concat()), Err(WireError::TrailingData("server flight")));
+
+        let with = |i: usize, b: u8| {
+            let mut body = cert.encode_body().unwrap();
+            body[i] = b;
+            decode_server_flight(&[frame(MessageType::ServerCert, &body).unwrap(), frame(MessageType::ServerFinished, &finished.mac).unwrap()].concat())
+        };
+        assert_eq!(with(0, 0x99), Err(WireError::UnknownSignatureAlgorithm(0x9903)));
+        assert_eq!(with(2, 0), Err(WireError::InvalidLength { field: "certificate chain", len: 0 }));
+        assert_eq!(with(2, 3), Err(WireError::Truncated("certificate"))); // third length read from the signature
+        assert_eq!(with(46, 21), Err(WireError::Truncated("server signature"))); // second certificate length
+        assert_eq!(with(46, 19), Err(WireError::TrailingData("ServerCert")));
+
+        // a server cannot send what a client would reject
+        let alg = SignatureAlgorithm::Ed25519;
+        assert_eq!(encode_certificate(alg, &[]), Err(WireError::InvalidLength { field: "certificate chain", len: 0 }));
+        assert_eq!(encode_certificate(alg, &[vec![1], vec![]]), Err(WireError::InvalidLength { field: "certificate", len: 0 }));
+        let too_long = vec![vec![0; MAX_CERTIFICATE_CHAIN_LEN / 2 + 1]; 2];
+        assert_eq!(encode_certificate(alg, &too_long), Err(WireError::InvalidLength { field: "certificate chain", len: MAX_CERTIFICATE_CHAIN_LEN + 2 }));
+
+        let short_mac = frame(MessageType::ClientFinished, &[0; MAC_LEN - 1]).unwrap();
+        assert_eq!(decode_client_finished(&short_mac), Err(WireError::Truncated("MAC")));
+        let long_mac = frame(MessageType::ClientFinished, &[0; MAC_LEN + 1]).unwrap();
+        assert_eq!(decode_client_finished(&long_mac), Err(WireError::TrailingData("ClientFinished")));
+    }
+
+    #[test]
+    fn encoders_reject_unsendable_messages() {
+        let hello = |n: usize| Message::ClientHello(ClientHello { nonce: [1; NONCE_LEN], key_share: [2; KEY_SHARE_LEN], cipher_suites: vec![CipherSuite::Aes256Gcm; n] });
+        assert!(hello(127).to_bytes().is_ok());
+        assert_eq!(hello(128).to_bytes(), Err(WireError::InvalidLength { field: "cipher suites", len: 256 }));
+        assert_eq!(hello(0).to_bytes(), Err(WireError::InvalidLength { field: "cipher suites", len: 0 }));
+
+        let sealed = envelope::seal(CipherSuite::Aes256Gcm, b"k_1_s", &[0; 32], &vec![0; MAX_BODY_LEN], b"").unwrap();
+        let len = sealed.to_bytes().len();
+        assert_eq!(Message::ServerFlight(sealed).to_bytes(), Err(WireError::TooLong(len)));
+        assert_eq!(frame(MessageType::ServerCert, &vec![0; MAX_BODY_LEN + 1]), Err(WireError::TooLong(MAX_BODY_LEN + 1)));
+        assert!(frame(MessageType::ServerCert, &vec![0; MAX_BODY_LEN]).is_ok());
+
+        let mut cert = server_cert();
+        cert.certificate_chain.push(Vec::new());
+        let finished = ServerFinished { mac: [8; MAC_LEN] };
+        assert_eq!(encode_server_flight(&cert, &finished), Err(WireError::InvalidLength { field: "certificate", len: 0 }));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(512))]
+
+        #[test]
+        fn parsers_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..256)) {
+            let _ = Message::from_bytes(&bytes);
+            let _ = decode_server_flight(&bytes);
+            let _ = decode_client_finished(&bytes);
+        }
+
+        #[test]
+        fn mutated_messages_never_panic(at in any::<usize>(), byte in any::<u8>()) {
+            let mut bytes = client_hello().to_bytes().unwrap();
+            let i = at % bytes.len();
+            bytes[i] = byte;
+            if let Ok(message) = Message::from_bytes(&bytes) {
+                prop_assert_eq!(message.to_bytes().unwrap(), bytes);
+            }
+        }
+    }
+}
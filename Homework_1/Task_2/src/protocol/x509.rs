@@ -0,0 +1,423 @@
+//! X.509 validation of the server's certificate chain against a trust store of root certificates,
+//! done by `rustls-webpki`; `x509-parser` reads the key out of the server's certificate and checks
+//! the keyUsage extension, which webpki ignores.
+//!
+//! A chain is DER certificates, the server's own first and then the intermediates, which may be in
+//! any order and may include the root. The path must end at a trust anchor, every certificate on it
+//! must be inside its validity period, have no critical extension webpki does not process, and
+//! issuers must be CAs whose path length covers the CAs below them. The server's certificate must
+//! not be a CA, must allow serverAuth if it restricts extended key usage, and must name the server
+//! in its subjectAltName. Where keyUsage is present, the server's certificate must allow
+//! digitalSignature and every issuer keyCertSign; roots are checked for that when they are added.
+//! Otherwise, as in web PKI, a trust anchor is only a name and a key: its own validity and
+//! constraints are not checked.
+//!
+//! Certificate signatures are Ed25519 or ECDSA-with-SHA256 over P-256; those are also the key
+//! types a certificate may carry.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rustls_pki_types::{CertificateDer, ServerName, SignatureVerificationAlgorithm, TrustAnchor, UnixTime};
+use webpki::{EndEntityCert, KeyUsage as ExtendedKeyUsage};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::KeyUsage;
+use x509_parser::oid_registry::{OID_EC_P256, OID_KEY_TYPE_EC_PUBLIC_KEY, OID_SIG_ED25519};
+
+use crate::crypto::signature_scheme::SignatureAlgorithm;
+
+/// Longest chain accepted, root included.
+pub const MAX_CHAIN_LEN: usize = 5;
+
+/// Signature algorithms accepted on certificates.
+const SIGNATURE_ALGORITHMS: &[&dyn SignatureVerificationAlgorithm] = &[webpki::ring::ED25519, webpki::ring::ECDSA_P256_SHA256];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertificateError {
+    /// Not a DER certificate, or one with inconsistent or duplicate fields.
+    Malformed,
+    EmptyChain,
+    ChainTooLong(usize),
+    UnsupportedSignatureAlgorithm,
+    UnsupportedPublicKey(String),
+    InvalidPublicKey,
+    UnsupportedCriticalExtension,
+    /// A certificate's signature does not verify under its issuer's key.
+    BadSignature,
+    /// The chain does not lead to a certificate in the trust store.
+    UnknownIssuer,
+    Expired,
+    NotYetValid,
+    /// An issuer, or a trust anchor, is not a CA.
+    NotCa,
+    /// The server's certificate is a CA.
+    CaAsEndEntity,
+    /// An issuer has more CAs below it than its path length constraint allows.
+    PathLenExceeded,
+    /// The named key usage or extended key usage is missing.
+    MissingKeyUsage(&'static str),
+    /// The server's certificate is not issued for this name.
+    NameMismatch(String),
+    /// Any other reason webpki gives for rejecting the chain.
+    Rejected(webpki::Error),
+}
+
+impl fmt::Display for CertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertificateError::Malformed => write!(f, "malformed certificate"),
+            CertificateError::EmptyChain => write!(f, "empty certificate chain"),
+            CertificateError::ChainTooLong(len) => write!(f, "certificate chain of {len} exceeds {MAX_CHAIN_LEN}"),
+            CertificateError::UnsupportedSignatureAlgorithm => write!(f, "unsupported certificate signature algorithm"),
+            CertificateError::UnsupportedPublicKey(oid) => write!(f, "unsupported public key type {oid}"),
+            CertificateError::InvalidPublicKey => write!(f, "invalid public key in certificate"),
+            CertificateError::UnsupportedCriticalExtension => write!(f, "unsupported critical extension"),
+            CertificateError::BadSignature => write!(f, "certificate signature does not verify"),
+            CertificateError::UnknownIssuer => write!(f, "certificate issuer is not in the trust store"),
+            CertificateError::Expired => write!(f, "certificate has expired"),
+            CertificateError::NotYetValid => write!(f, "certificate is not valid yet"),
+            CertificateError::NotCa => write!(f, "issuer is not a CA"),
+            CertificateError::CaAsEndEntity => write!(f, "server certificate is a CA certificate"),
+            CertificateError::PathLenExceeded => write!(f, "path length constraint exceeded"),
+            CertificateError::MissingKeyUsage(usage) => write!(f, "certificate does not allow {usage}"),
+            CertificateError::NameMismatch(name) => write!(f, "certificate is not valid for {name}"),
+            CertificateError::Rejected(e) => write!(f, "certificate rejected: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CertificateError {}
+
+impl From<webpki::Error> for CertificateError {
+    fn from(e: webpki::Error) -> Self {
+        use webpki::Error;
+        match e {
+            Error::BadDer
+            | Error::BadDerTime
+            | Error::TrailingData(_)
+            | Error::UnsupportedCertVersion
+            | Error::InvalidSerialNumber
+            | Error::InvalidCertValidity
+            | Error::MalformedExtensions
+            | Error::ExtensionValueInvalid
+            | Error::EmptyEkuExtension => CertificateError::Malformed,
+            Error::UnsupportedSignatureAlgorithmContext(_) => CertificateError::UnsupportedSignatureAlgorithm,
+            // e.g. an Ed25519 signature from a P-256 issuer
+            Error::InvalidSignatureForPublicKey | Error::UnsupportedSignatureAlgorithmForPublicKeyContext(_) => {
+                CertificateError::BadSignature
+            }
+            Error::UnsupportedCriticalExtension => CertificateError::UnsupportedCriticalExtension,
+            Error::UnknownIssuer => CertificateError::UnknownIssuer,
+            Error::CertExpired { .. } => CertificateError::Expired,
+            Error::CertNotValidYet { .. } => CertificateError::NotYetValid,
+            Error::EndEntityUsedAsCa => CertificateError::NotCa,
+            Error::CaUsedAsEndEntity => CertificateError::CaAsEndEntity,
+            Error::PathLenConstraintViolated => CertificateError::PathLenExceeded,
+            Error::RequiredEkuNotFoundContext(_) => CertificateError::MissingKeyUsage("serverAuth"),
+            other => CertificateError::Rejected(other),
+        }
+    }
+}
+
+/// Public key types a certificate may carry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyType {
+    Ed25519,
+    P256,
+}
+
+impl KeyType {
+    /// Key type of `algorithm`'s keys, if certificates can carry them (not secp256k1).
+    pub fn for_algorithm(algorithm: SignatureAlgorithm) -> Option<Self> {
+        match algorithm {
+            SignatureAlgorithm::Ed25519 | SignatureAlgorithm::Ed25519ph => Some(KeyType::Ed25519),
+            SignatureAlgorithm::EcdsaP256 => Some(KeyType::P256),
+            SignatureAlgorithm::EcdsaSecp256k1 => None,
+        }
+    }
+}
+
+/// A certificate's subject key, as the handshake uses it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertifiedKey {
+    pub key_type: KeyType,
+    /// In the `SignatureAlgorithm` encoding: 32 bytes for Ed25519, SEC1 compressed for P-256.
+    pub public_key: Vec<u8>,
+}
+
+impl CertifiedKey {
+    /// Whether a signature with `algorithm` can come from this key.
+    pub fn supports(&self, algorithm: SignatureAlgorithm) -> bool {
+        KeyType::for_algorithm(algorithm) == Some(self.key_type)
+    }
+}
+
+/// The roots a client accepts server chains from.
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    roots: Vec<TrustAnchor<'static>>,
+}
+
+impl TrustStore {
+    /// Store holding `roots`, DER certificates that must each be a CA.
+    pub fn new(roots: impl IntoIterator<Item = Vec<u8>>) -> Result<Self, CertificateError> {
+        let mut store = Self::default();
+        for root in roots {
+            store.add(root)?;
+        }
+        Ok(store)
+    }
+
+    pub fn add(&mut self, root: Vec<u8>) -> Result<(), CertificateError> {
+        let cert = parse(&root)?;
+        public_key(&cert)?;
+        let ca = cert.basic_constraints().map_err(|_| CertificateError::Malformed)?.is_some_and(|ext| ext.value.ca);
+        if !ca {
+            return Err(CertificateError::NotCa);
+        }
+        require_key_usage(&cert, "keyCertSign", KeyUsage::key_cert_sign)?;
+        let der = CertificateDer::from(root.as_slice());
+        self.roots.push(webpki::anchor_from_trusted_cert(&der)?.to_owned());
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Validate `chain` for a server reached as `server_name` (DNS name or IP address) at `now`;
+    /// returns the key of the server's certificate.
+    pub fn verify_server(&self, chain: &[Vec<u8>], server_name: &str, now: SystemTime) -> Result<CertifiedKey, CertificateError> {
+        let (leaf, intermediates) = chain.split_first().ok_or(CertificateError::EmptyChain)?;
+        if chain.len() > MAX_CHAIN_LEN {
+            return Err(CertificateError::ChainTooLong(chain.len()));
+        }
+        let leaf_der = CertificateDer::from(leaf.as_slice());
+        let intermediates: Vec<_> = intermediates.iter().map(|der| CertificateDer::from(der.as_slice())).collect();
+        let now = UnixTime::since_unix_epoch(now.duration_since(UNIX_EPOCH).unwrap_or_default());
+
+        let cert = EndEntityCert::try_from(&leaf_der)?;
+        let path = cert.verify_for_usage(SIGNATURE_ALGORITHMS, &self.roots, &intermediates, now, ExtendedKeyUsage::server_auth(), None, None)?;
+        require_key_usage(&parse(leaf)?, "digitalSignature", KeyUsage::digital_signature)?;
+        for issuer in path.intermediate_certificates() {
+            require_key_usage(&parse(&issuer.der())?, "keyCertSign", KeyUsage::key_cert_sign)?;
+        }
+        let name_mismatch = || CertificateError::NameMismatch(server_name.to_string());
+        let name = ServerName::try_from(server_name).map_err(|_| name_mismatch())?;
+        cert.verify_is_valid_for_subject_name(&name).map_err(|_| name_mismatch())?;
+        certified_key(leaf)
+    }
+}
+
+/// Key of the certificate `der`, without validating it; for matching a certificate with its
+/// private key.
+pub fn certified_key(der: &[u8]) -> Result<CertifiedKey, CertificateError> {
+    public_key(&parse(der)?)
+}
+
+fn parse(der: &[u8]) -> Result<X509Certificate<'_>, CertificateError> {
+    match x509_parser::parse_x509_certificate(der) {
+        Ok((rest, cert)) if rest.is_empty() && cert.signature_algorithm == cert.tbs_certificate.signature => Ok(cert),
+        _ => Err(CertificateError::Malformed),
+    }
+}
+
+/// A certificate without the keyUsage extension may be used for anything.
+fn require_key_usage(cert: &X509Certificate<'_>, usage: &'static str, allows: fn(&KeyUsage) -> bool) -> Result<(), CertificateError> {
+    match cert.key_usage().map_err(|_| CertificateError::Malformed)? {
+        Some(ext) if !allows(ext.value) => Err(CertificateError::MissingKeyUsage(usage)),
+        _ => Ok(()),
+    }
+}
+
+fn public_key(cert: &X509Certificate<'_>) -> Result<CertifiedKey, CertificateError> {
+    let spki = cert.public_key();
+    let bits: &[u8] = &spki.subject_public_key.data;
+    let algorithm = &spki.algorithm;
+    if algorithm.algorithm == OID_SIG_ED25519 {
+        let bytes: [u8; 32] = bits.try_into().map_err(|_| CertificateError::InvalidPublicKey)?;
+        ed25519_dalek::VerifyingKey::from_bytes(&bytes).map_err(|_| CertificateError::InvalidPublicKey)?;
+        Ok(CertifiedKey { key_type: KeyType::Ed25519, public_key: bytes.to_vec() })
+    } else if algorithm.algorithm == OID_KEY_TYPE_EC_PUBLIC_KEY {
+        let curve = algorithm.parameters.as_ref().and_then(|p| p.as_oid().ok());
+        if curve != Some(OID_EC_P256) {
+            return Err(CertificateError::UnsupportedPublicKey(curve.map_or("no curve".into(), |c| c.to_id_string())));
+        }
+        let pk = p256::PublicKey::from_sec1_bytes(bits).map_err(|_| CertificateError::InvalidPublicKey)?;
+        Ok(CertifiedKey { key_type: KeyType::P256, public_key: pk.to_encoded_point(true).as_bytes().to_vec() })
+    } else {
+        Err(CertificateError::UnsupportedPublicKey(algorithm.algorithm.to_id_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ca::{self, CertificateAuthority, CertificateKey};
+    use rcgen::{ExtendedKeyUsagePurpose, IsCa, KeyUsagePurpose};
+    use std::slice;
+    use std::time::Duration;
+
+    fn key(algorithm: SignatureAlgorithm) -> CertificateKey {
+        CertificateKey::generate(algorithm).unwrap()
+    }
+
+    fn fresh_root() -> CertificateAuthority {
+        CertificateAuthority::generate("Test Root").unwrap()
+    }
+
+    fn store(ca: &CertificateAuthority) -> TrustStore {
+        TrustStore::new([ca.certificate().to_vec()]).unwrap()
+    }
+
+    fn verify(store: &TrustStore, chain: &[Vec<u8>], name: &str) -> Result<CertifiedKey, CertificateError> {
+        store.verify_server(chain, name, SystemTime::now())
+    }
+
+    #[test]
+    fn accepts_valid_chains() {
+        let root = fresh_root();
+        for algorithm in [SignatureAlgorithm::Ed25519, SignatureAlgorithm::EcdsaP256] {
+            let intermediate = root.intermediate(ca::ca_params("Test Intermediate", Some(0)), key(algorithm)).unwrap();
+            let (leaf, leaf_key) = intermediate.issue_server("server.test", algorithm).unwrap();
+            let chain = vec![leaf, intermediate.certificate().to_vec()];
+            let certified = verify(&store(&root), &chain, "SERVER.test").unwrap();
+            assert!(certified.supports(algorithm));
+            assert_eq!(certified.public_key, algorithm.verifying_key(leaf_key.signing_key().expose_secret()).unwrap());
+
+            // with the root sent along, in either order, and with the intermediate trusted directly
+            let with_root = [&chain[..], &[root.certificate().to_vec()]].concat();
+            assert_eq!(verify(&store(&root), &with_root, "server.test"), Ok(certified.clone()));
+            let reordered = [chain[0].clone(), root.certificate().to_vec(), chain[1].clone()];
+            assert_eq!(verify(&store(&root), &reordered, "server.test"), Ok(certified.clone()));
+            assert_eq!(verify(&store(&intermediate), &chain[..1], "server.test"), Ok(certified));
+        }
+
+        let root = fresh_root();
+        let wildcard = root.issue(&ca::server_params("*.example.test").unwrap(), &key(SignatureAlgorithm::Ed25519)).unwrap();
+        assert!(verify(&store(&root), slice::from_ref(&wildcard), "api.example.test").is_ok());
+        assert_eq!(verify(&store(&root), slice::from_ref(&wildcard), "example.test"), Err(CertificateError::NameMismatch("example.test".into())));
+        assert!(verify(&store(&root), &[wildcard], "a.b.example.test").is_err());
+        // a wildcard cannot cover a whole top-level domain
+        let tld = root.issue(&ca::server_params("*.test").unwrap(), &key(SignatureAlgorithm::Ed25519)).unwrap();
+        assert_eq!(verify(&store(&root), &[tld], "example.test"), Err(CertificateError::NameMismatch("example.test".into())));
+        let (by_ip, _) = root.issue_server("127.0.0.1", SignatureAlgorithm::Ed25519).unwrap();
+        assert!(verify(&store(&root), slice::from_ref(&by_ip), "127.0.0.1").is_ok());
+        assert!(verify(&store(&root), &[by_ip], "127.0.0.2").is_err());
+    }
+
+    #[test]
+    fn rejects_untrusted_or_broken_chains() {
+        let (root, other) = (fresh_root(), CertificateAuthority::generate("Other Root").unwrap());
+        let (leaf, _) = root.issue_server("server.test", SignatureAlgorithm::Ed25519).unwrap();
+        assert_eq!(verify(&store(&other), slice::from_ref(&leaf), "server.test"), Err(CertificateError::UnknownIssuer));
+        // a root of the same name but with another key
+        assert_eq!(verify(&store(&fresh_root()), slice::from_ref(&leaf), "server.test"), Err(CertificateError::BadSignature));
+        assert_eq!(verify(&TrustStore::default(), slice::from_ref(&leaf), "server.test"), Err(CertificateError::UnknownIssuer));
+        assert_eq!(verify(&store(&root), slice::from_ref(&leaf), "other.test"), Err(CertificateError::NameMismatch("other.test".into())));
+
+        let mut forged = leaf.clone();
+        *forged.last_mut().unwrap() ^= 1; // inside the signature
+        assert_eq!(verify(&store(&root), slice::from_ref(&forged), "server.test"), Err(CertificateError::BadSignature));
+        let with_root = [forged, root.certificate().to_vec()];
+        assert_eq!(verify(&store(&root), &with_root, "server.test"), Err(CertificateError::BadSignature));
+        assert_eq!(verify(&store(&root), &[leaf[1..].to_vec()], "server.test"), Err(CertificateError::Malformed));
+        assert_eq!(verify(&store(&root), &[], "server.test"), Err(CertificateError::EmptyChain));
+        assert_eq!(verify(&store(&root), &vec![leaf.clone(); 6], "server.test"), Err(CertificateError::ChainTooLong(6)));
+
+        // intermediate missing, or an unrelated one instead
+        let intermediate = root.intermediate(ca::ca_params("Test Intermediate", None), key(SignatureAlgorithm::Ed25519)).unwrap();
+        let (leaf, _) = intermediate.issue_server("server.test", SignatureAlgorithm::Ed25519).unwrap();
+        assert_eq!(verify(&store(&root), slice::from_ref(&leaf), "server.test"), Err(CertificateError::UnknownIssuer));
+        let unrelated = other.certificate().to_vec();
+        assert_eq!(verify(&store(&root), &[leaf, unrelated], "server.test"), Err(CertificateError::UnknownIssuer));
+
+        // a server certificate cannot be a trust anchor
+        assert_eq!(TrustStore::new([root.issue_server("server.test", SignatureAlgorithm::Ed25519).unwrap().0]).unwrap_err(), CertificateError::NotCa);
+    }
+
+    #[test]
+    fn enforces_validity_period() {
+        let mut params = ca::ca_params("Test Root", None);
+        params.not_before = rcgen::date_time_ymd(2019, 6, 1);
+        let root = CertificateAuthority::new(params, key(SignatureAlgorithm::Ed25519)).unwrap();
+        let mut params = ca::server_params("server.test").unwrap();
+        params.not_before = rcgen::date_time_ymd(2020, 1, 1);
+        params.not_after = rcgen::date_time_ymd(2021, 1, 1);
+        let expired = root.issue(&params, &key(SignatureAlgorithm::Ed25519)).unwrap();
+        assert_eq!(verify(&store(&root), slice::from_ref(&expired), "server.test"), Err(CertificateError::Expired));
+        let in_2020 = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        assert!(store(&root).verify_server(slice::from_ref(&expired), "server.test", in_2020).is_ok());
+        let in_2019 = UNIX_EPOCH + Duration::from_secs(1_550_000_000);
+        assert_eq!(store(&root).verify_server(&[expired], "server.test", in_2019), Err(CertificateError::NotYetValid));
+
+        // a root is only a name and a key: another root under the same name is tried when the first
+        // does not verify the chain, and an expired root certificate still anchors
+        let mut params = ca::ca_params("Test Root", None);
+        params.not_after = rcgen::date_time_ymd(2021, 1, 1);
+        let old_root = CertificateAuthority::new(params, key(SignatureAlgorithm::Ed25519)).unwrap();
+        let (leaf, _) = old_root.issue_server("server.test", SignatureAlgorithm::Ed25519).unwrap();
+        let both = TrustStore::new([root.certificate().to_vec(), old_root.certificate().to_vec()]).unwrap();
+        assert!(verify(&both, &[leaf], "server.test").is_ok());
+    }
+
+    #[test]
+    fn enforces_key_usage() {
+        let root = fresh_root();
+        let issue = |usages: Vec<KeyUsagePurpose>| {
+            let mut params = ca::server_params("server.test").unwrap();
+            params.key_usages = usages;
+            verify(&store(&root), &[root.issue(&params, &key(SignatureAlgorithm::Ed25519)).unwrap()], "server.test")
+        };
+        assert_eq!(issue(vec![KeyUsagePurpose::KeyEncipherment]), Err(CertificateError::MissingKeyUsage("digitalSignature")));
+        assert!(issue(vec![KeyUsagePurpose::DigitalSignature, KeyUsagePurpose::KeyEncipherment]).is_ok());
+        assert!(issue(vec![]).is_ok());
+
+        // an intermediate, or a root, that may not sign certificates
+        let mut params = ca::ca_params("Test Intermediate", None);
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature, KeyUsagePurpose::CrlSign];
+        let intermediate = root.intermediate(params, key(SignatureAlgorithm::Ed25519)).unwrap();
+        let (leaf, _) = intermediate.issue_server("server.test", SignatureAlgorithm::Ed25519).unwrap();
+        assert_eq!(
+            verify(&store(&root), &[leaf, intermediate.certificate().to_vec()], "server.test"),
+            Err(CertificateError::MissingKeyUsage("keyCertSign"))
+        );
+        let mut params = ca::ca_params("Test Root", None);
+        params.key_usages = vec![KeyUsagePurpose::CrlSign];
+        let root = CertificateAuthority::new(params, key(SignatureAlgorithm::Ed25519)).unwrap();
+        assert_eq!(TrustStore::new([root.certificate().to_vec()]).unwrap_err(), CertificateError::MissingKeyUsage("keyCertSign"));
+    }
+
+    #[test]
+    fn enforces_extended_key_usage_and_basic_constraints() {
+        let root = fresh_root();
+        let issue = |edit: fn(&mut rcgen::CertificateParams)| {
+            let mut params = ca::server_params("server.test").unwrap();
+            edit(&mut params);
+            verify(&store(&root), &[root.issue(&params, &key(SignatureAlgorithm::Ed25519)).unwrap()], "server.test")
+        };
+        assert_eq!(issue(|p| p.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth]), Err(CertificateError::MissingKeyUsage("serverAuth")));
+        assert_eq!(issue(|p| p.is_ca = IsCa::Ca(rcgen::BasicConstraints::Unconstrained)), Err(CertificateError::CaAsEndEntity));
+        assert!(issue(|p| p.extended_key_usages = vec![]).is_ok());
+
+        // a server certificate used as an issuer
+        let (fake_issuer, fake_key) = root.issue_server("issuer.test", SignatureAlgorithm::Ed25519).unwrap();
+        let fake = rcgen::Issuer::new(ca::server_params("issuer.test").unwrap(), fake_key);
+        let leaf = ca::server_params("server.test").unwrap().signed_by(&key(SignatureAlgorithm::Ed25519), &fake).unwrap().der().to_vec();
+        assert_eq!(verify(&store(&root), &[leaf, fake_issuer], "server.test"), Err(CertificateError::NotCa));
+
+        // an intermediate allowing no CAs below it, and one allowing one
+        for (path_len, expected) in [(0, Err(CertificateError::PathLenExceeded)), (1, Ok(()))] {
+            let upper = root.intermediate(ca::ca_params("Constrained Intermediate", Some(path_len)), key(SignatureAlgorithm::Ed25519)).unwrap();
+            let lower = upper.intermediate(ca::ca_params("Test Intermediate", None), key(SignatureAlgorithm::EcdsaP256)).unwrap();
+            let (leaf, _) = lower.issue_server("server.test", SignatureAlgorithm::EcdsaP256).unwrap();
+            let chain = [leaf, lower.certificate().to_vec(), upper.certificate().to_vec()];
+            assert_eq!(verify(&store(&root), &chain, "server.test").map(|_| ()), expected);
+        }
+    }
+}
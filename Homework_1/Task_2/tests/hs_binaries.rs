@@ -0,0 +1,83 @@
+//! `hs-server` and `hs-client` talking over a real localhost socket.
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn key_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hs-binaries-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn client_and_server_complete_a_handshake() {
+    let dir = key_dir("ok");
+    let keygen = Command::new(env!("CARGO_BIN_EXE_hs-server"))
+        .args(["keygen", "--dir", dir.to_str().unwrap(), "--scheme", "ecdsa-p256"])
+        .status()
+        .unwrap();
+    assert!(keygen.success());
+    // never overwrites an existing CA
+    let again = Command::new(env!("CARGO_BIN_EXE_hs-server")).args(["keygen", "--dir", dir.to_str().unwrap()]).output().unwrap();
+    assert!(!again.status.success());
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_hs-server"))
+        .args(["--listen", "127.0.0.1:0", "--once", "--timeout", "5"])
+        .arg("--cert").arg(dir.join("server.pem"))
+        .arg("--key").arg(dir.join("server.key"))
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(server.stdout.take().unwrap()).lines();
+    let addr = lines.next().unwrap().unwrap().strip_prefix("listening on ").unwrap().to_string();
+
+    let client = Command::new(env!("CARGO_BIN_EXE_hs-client"))
+        .args(["--connect", &addr, "--timeout", "5", "--message", "ping over the record layer"])
+        .arg("--ca").arg(dir.join("ca.pem"))
+        .output()
+        .unwrap();
+    assert!(client.status.success(), "{}", String::from_utf8_lossy(&client.stderr));
+    let stdout = String::from_utf8(client.stdout).unwrap();
+    assert!(stdout.contains("complete, cipher suite AES-256-GCM"));
+    assert!(stdout.contains("echo: ping over the record layer"));
+
+    assert!(server.wait().unwrap().success());
+    assert!(lines.next().unwrap().unwrap().contains("complete, cipher suite AES-256-GCM"));
+    assert_eq!(lines.next().unwrap().unwrap(), "received 26 bytes");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn client_rejects_a_server_from_another_ca() {
+    let (ours, theirs) = (key_dir("ours"), key_dir("theirs"));
+    for dir in [&ours, &theirs] {
+        let keygen = Command::new(env!("CARGO_BIN_EXE_hs-server")).args(["keygen", "--dir", dir.to_str().unwrap()]).output().unwrap();
+        assert!(keygen.status.success());
+    }
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_hs-server"))
+        .args(["--listen", "127.0.0.1:0", "--once", "--timeout", "5"])
+        .arg("--cert").arg(theirs.join("server.pem"))
+        .arg("--key").arg(theirs.join("server.key"))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let addr = BufReader::new(server.stdout.take().unwrap()).lines().next().unwrap().unwrap();
+    let addr = addr.strip_prefix("listening on ").unwrap();
+
+    let client = Command::new(env!("CARGO_BIN_EXE_hs-client"))
+        .args(["--connect", addr, "--timeout", "5"])
+        .arg("--ca").arg(ours.join("ca.pem"))
+        .output()
+        .unwrap();
+    assert!(!client.status.success());
+    // every keygen names its CA "hs-server CA", so the trusted root is found but its key does not match
+    assert!(String::from_utf8_lossy(&client.stderr).contains("certificate signature does not verify"));
+    // the client hangs up instead of sending ClientFinished
+    assert!(!server.wait().unwrap().success());
+    for dir in [ours, theirs] {
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
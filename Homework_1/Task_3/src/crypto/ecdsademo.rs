@@ -12,9 +12,12 @@ use k256::elliptic_curve::{
 use k256::{Scalar,AffinePoint, ProjectivePoint};
 use rand::rngs::OsRng;
 use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
 
-/// A simple ECDSA keypair representation
-#[derive(Clone, Debug)]
+use super::secret::SecretKey;
+
+/// A simple ECDSA keypair representation; `d` is wiped on drop and left out of `Debug`.
+#[derive(Clone)]
 pub struct Keypair {
     /// private exponent d
     pub d: Scalar,
@@ -37,8 +40,20 @@ impl Keypair {
     }
 
     /// return the private scalar as 32-byte big-endian
-    pub fn private_bytes(&self) -> [u8; 32] {
-        self.d.to_bytes().into()
+    pub fn private_bytes(&self) -> SecretKey<32> {
+        SecretKey::new(self.d.to_bytes().into())
+    }
+}
+
+impl Drop for Keypair {
+    fn drop(&mut self) {
+        self.d.zeroize();
+    }
+}
+
+impl std::fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keypair").field("d", &"REDACTED").field("q", &self.q).finish()
     }
 }
 
@@ -93,7 +108,7 @@ pub fn sign(
     d: &Scalar,
     msg: &[u8])
     -> Result<([u8; 32], [u8; 32]), &'static str> {
-    let k = Scalar::generate_vartime(&mut OsRng);
+    let k = Zeroizing::new(Scalar::generate_vartime(&mut OsRng));
     sign_with_nonce(d, msg, &k)
 }
 
@@ -186,6 +201,15 @@ mod tests {
 
         assert_eq!(r1, r2, "reusing k produces the same r");
     }
+
+    #[test]
+    fn debug_does_not_leak_private_key() {
+        let kp = Keypair::generate();
+        let shown = format!("{kp:?}");
+        assert!(shown.contains("REDACTED"));
+        assert!(!shown.to_lowercase().contains(&hex::encode(kp.private_bytes().expose_secret())));
+        assert_eq!(format!("{:?}", kp.private_bytes()), "SecretKey<32>(REDACTED)");
+    }
 }
 
 
@@ -1 +1,2 @@
-pub mod ecdsademo;
\ No newline at end of file
+pub mod ecdsademo;
+pub mod secret;
\ No newline at end of file
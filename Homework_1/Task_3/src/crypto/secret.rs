@@ -0,0 +1,111 @@
+//! Secret byte strings that clean up after themselves.
+//! - `SecretKey<N>`: keys and key-schedule outputs.
+//! - `SharedSecret<N>` (32 bytes unless stated): raw key-exchange / KEM outputs, meant for a KDF.
+//!
+//! Both are wiped when dropped, print as `SecretKey<32>(REDACTED)` and compare in constant time.
+//! The bytes are only reachable through `expose_secret()`, so every use shows up at the call site.
+
+use std::fmt;
+
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+macro_rules! secret_bytes {
+    ($(#[$doc:meta])* $name:ident $(= $default:literal)?) => {
+        $(#[$doc])*
+        #[derive(Clone)]
+        pub struct $name<const N: usize $(= $default)?>([u8; N]);
+
+        impl<const N: usize> $name<N> {
+            pub fn new(bytes: [u8; N]) -> Self {
+                Self(bytes)
+            }
+
+            /// `None` unless `bytes` is exactly N bytes long.
+            pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+                bytes.try_into().ok().map(Self)
+            }
+
+            pub fn expose_secret(&self) -> &[u8; N] {
+                &self.0
+            }
+
+            /// For filling the key in place.
+            pub fn expose_secret_mut(&mut self) -> &mut [u8; N] {
+                &mut self.0
+            }
+        }
+
+        impl<const N: usize> From<[u8; N]> for $name<N> {
+            fn from(bytes: [u8; N]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl<const N: usize> Zeroize for $name<N> {
+            fn zeroize(&mut self) {
+                self.0.zeroize();
+            }
+        }
+
+        impl<const N: usize> Drop for $name<N> {
+            fn drop(&mut self) {
+                self.zeroize();
+            }
+        }
+
+        impl<const N: usize> ZeroizeOnDrop for $name<N> {}
+
+        impl<const N: usize> ConstantTimeEq for $name<N> {
+            fn ct_eq(&self, other: &Self) -> Choice {
+                self.0[..].ct_eq(&other.0[..])
+            }
+        }
+
+        impl<const N: usize> PartialEq for $name<N> {
+            fn eq(&self, other: &Self) -> bool {
+                self.ct_eq(other).into()
+            }
+        }
+
+        impl<const N: usize> Eq for $name<N> {}
+
+        impl<const N: usize> fmt::Debug for $name<N> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, concat!(stringify!($name), "<{}>(REDACTED)"), N)
+            }
+        }
+    };
+}
+
+secret_bytes!(
+    /// Secret key material of N bytes.
+    SecretKey
+);
+secret_bytes!(
+    /// Output of a key agreement or KEM, before any KDF.
+    SharedSecret = 32
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_is_redacted() {
+        let key = SecretKey::new([0xab; 32]);
+        assert_eq!(format!("{key:?}"), "SecretKey<32>(REDACTED)");
+        assert_eq!(format!("{:?}", Some(SharedSecret::new([0xab; 32]))), "Some(SharedSecret<32>(REDACTED))");
+    }
+
+    #[test]
+    fn equality_and_zeroize() {
+        let mut a = SecretKey::new([1u8; 16]);
+        assert_eq!(a, SecretKey::from([1u8; 16]));
+        assert_ne!(a, SecretKey::from([2u8; 16]));
+        assert_eq!(SharedSecret::<4>::from_slice(&[1, 2, 3]), None);
+
+        a.zeroize();
+        assert_eq!(a.expose_secret(), &[0u8; 16]);
+    }
+}
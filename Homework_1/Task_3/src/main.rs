@@ -23,7 +23,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Generate keypair
     let keypair = Keypair::generate();
     println!("\nKeypair generated.");
-    println!("Private key (hex): {:02x?}", keypair.private_bytes());
+    println!("Private key (hex): {}", hex::encode(keypair.private_bytes().expose_secret()));
     println!("Public key (uncompressed): {:02x?}", keypair.public_bytes_uncompressed());
 
     // For demo: choose a fixed nonce (insecure)